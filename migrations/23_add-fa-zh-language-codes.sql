@@ -0,0 +1,2 @@
+ALTER TYPE language_code ADD VALUE IF NOT EXISTS 'fa';
+ALTER TYPE language_code ADD VALUE IF NOT EXISTS 'zh';
@@ -0,0 +1,3 @@
+ALTER TABLE Chats ADD COLUMN IF NOT EXISTS language language_code;
+
+COMMENT ON COLUMN Chats.language IS 'The language set by administrators of the chat for public replies; the language of a user is used if NULL';
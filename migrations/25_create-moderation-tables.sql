@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS Chat_Bans (
+    chat_id bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    uid bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    banned_by bigint NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT current_timestamp,
+    PRIMARY KEY (chat_id, uid)
+);
+
+ALTER TABLE Chats ADD COLUMN IF NOT EXISTS frozen boolean NOT NULL DEFAULT false;
+
+COMMENT ON COLUMN Chats.frozen IS 'The game is paused by administrators of the chat';
+
+CREATE TABLE IF NOT EXISTS Admin_Audit (
+    id bigserial PRIMARY KEY,
+    chat_id bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    admin_uid bigint NOT NULL,
+    action text NOT NULL,
+    target_uid bigint,
+    details text,
+    created_at timestamptz NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_audit_chat_id ON Admin_Audit(chat_id);
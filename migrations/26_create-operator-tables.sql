@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS Announcement_Texts (
+    language language_code PRIMARY KEY,
+    text text NOT NULL,
+    updated_by bigint NOT NULL,
+    updated_at timestamptz NOT NULL DEFAULT current_timestamp
+);
+
+COMMENT ON TABLE  Announcement_Texts      IS 'Announcements set by operators of the bot; they take precedence over the ANNOUNCEMENT_* environment variables';
+COMMENT ON COLUMN Announcement_Texts.text IS 'An empty string disables the announcement for the language';
+
+CREATE TABLE IF NOT EXISTS Gift_Restrictions (
+    uid bigint PRIMARY KEY,
+    custom_name text NOT NULL,
+    created_by bigint NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT current_timestamp
+);
+
+COMMENT ON TABLE Gift_Restrictions IS 'Users who cannot receive gifts, added by operators of the bot in addition to GIFT_RESTRICTIONS_FILE';
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS Chat_Disabled_Commands (
+    chat_id bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    command varchar(32) NOT NULL,
+    disabled_by bigint NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT current_timestamp,
+    PRIMARY KEY (chat_id, command)
+);
+
+COMMENT ON TABLE Chat_Disabled_Commands IS 'Commands turned off by administrators of the chat';
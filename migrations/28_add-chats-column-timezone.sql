@@ -0,0 +1,53 @@
+ALTER TABLE Chats ADD COLUMN IF NOT EXISTS timezone varchar(64);
+
+COMMENT ON COLUMN Chats.timezone IS 'A name from pg_timezone_names the day starts in; the time zone of the server if NULL';
+
+CREATE OR REPLACE FUNCTION chat_date(internal_chat_id bigint, ts timestamptz)
+    RETURNS date
+    LANGUAGE SQL
+    STABLE
+AS $$
+    SELECT (ts AT TIME ZONE coalesce(
+        (SELECT timezone FROM Chats WHERE id = internal_chat_id),
+        current_setting('TimeZone')
+    ))::date
+$$;
+
+CREATE OR REPLACE FUNCTION check_and_update_dicks_timestamp()
+    RETURNS TRIGGER
+    LANGUAGE PLPGSQL
+AS $$
+BEGIN
+    IF chat_date(NEW.chat_id, current_timestamp) = chat_date(NEW.chat_id, OLD.updated_at) AND NEW.bonus_attempts = 0 THEN
+        RAISE EXCEPTION 'Your dick has been already grown today!'
+            USING ERRCODE = 'GD0E1';
+    END IF;
+
+    IF NEW.bonus_attempts > 0 THEN
+        NEW.bonus_attempts := NEW.bonus_attempts - 1;
+    END IF;
+
+    RETURN NEW;
+END
+$$;
+
+CREATE OR REPLACE FUNCTION check_dod_timestamp()
+    RETURNS TRIGGER
+    LANGUAGE PLPGSQL
+AS $$
+DECLARE
+    dod_name varchar;
+    today date := chat_date(NEW.chat_id, current_timestamp);
+BEGIN
+    SELECT name INTO dod_name FROM Dick_of_Day dod
+        JOIN Users u ON dod.winner_uid = u.uid
+        WHERE dod.created_at = today AND chat_id = NEW.chat_id;
+    IF dod_name IS NOT NULL THEN
+        RAISE EXCEPTION '%', dod_name
+            USING ERRCODE = 'GD0E2';
+    END IF;
+
+    NEW.created_at := today;
+    RETURN NEW;
+END
+$$;
@@ -0,0 +1,6 @@
+ALTER TABLE Dicks
+    ADD COLUMN IF NOT EXISTS grown_on date,
+    ADD COLUMN IF NOT EXISTS grow_streak_current integer NOT NULL DEFAULT 0 CHECK (grow_streak_current >= 0),
+    ADD COLUMN IF NOT EXISTS grow_streak_max     integer NOT NULL DEFAULT 0 CHECK (grow_streak_max >= grow_streak_current);
+
+COMMENT ON COLUMN Dicks.grown_on IS 'The date of the last growth in the time zone of the chat';
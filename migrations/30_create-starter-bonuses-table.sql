@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS Starter_Bonuses (
+    chat_id bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    uid bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    bonus integer NOT NULL CHECK (bonus > 0),
+    created_at timestamptz NOT NULL DEFAULT current_timestamp,
+    PRIMARY KEY (chat_id, uid)
+);
+
+COMMENT ON TABLE Starter_Bonuses IS 'One-time bonuses for the first growth in a chat; kept when the dick is deleted to prevent farming';
@@ -0,0 +1,5 @@
+ALTER TABLE Chats ADD COLUMN IF NOT EXISTS active boolean NOT NULL DEFAULT true;
+ALTER TABLE Chats ADD COLUMN IF NOT EXISTS left_at timestamptz;
+
+COMMENT ON COLUMN Chats.active IS 'false if the bot has been kicked from the chat or has left it';
+COMMENT ON COLUMN Chats.left_at IS 'When the bot was removed from the chat; NULL while the chat is active';
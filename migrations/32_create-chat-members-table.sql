@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS Chat_Members (
+    chat_id bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    uid bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    left_at timestamptz,
+    updated_at timestamptz NOT NULL DEFAULT current_timestamp,
+    PRIMARY KEY (chat_id, uid)
+);
+
+COMMENT ON TABLE Chat_Members IS 'Memberships known from chat_member updates; users without a row are considered present';
+COMMENT ON COLUMN Chat_Members.left_at IS 'When the user left the chat or was kicked; NULL while the user is a member';
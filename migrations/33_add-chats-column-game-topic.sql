@@ -0,0 +1,3 @@
+ALTER TABLE Chats ADD COLUMN IF NOT EXISTS game_topic_id integer;
+
+COMMENT ON COLUMN Chats.game_topic_id IS 'The only forum topic the game commands are allowed in; any topic if NULL';
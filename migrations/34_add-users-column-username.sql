@@ -0,0 +1,6 @@
+ALTER TABLE Users ADD COLUMN IF NOT EXISTS username varchar(32);
+
+-- usernames are case-insensitive in Telegram
+CREATE INDEX IF NOT EXISTS idx_users_username ON Users(lower(username));
+
+COMMENT ON COLUMN Users.username IS 'The @username without the at sign, refreshed on every interaction; NULL if the user has none';
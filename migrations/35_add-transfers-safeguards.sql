@@ -0,0 +1,11 @@
+ALTER TABLE transfers ADD COLUMN IF NOT EXISTS tax integer NOT NULL DEFAULT 0 CHECK (tax >= 0 AND tax <= amount);
+
+CREATE INDEX IF NOT EXISTS idx_transfers_chat_id_from_uid_created_at ON transfers(chat_id, from_uid, created_at);
+
+-- existing dicks are left without the date since it's unknown when they were created
+ALTER TABLE Dicks ADD COLUMN IF NOT EXISTS created_at timestamptz;
+ALTER TABLE Dicks ALTER COLUMN created_at SET DEFAULT current_timestamp;
+
+COMMENT ON COLUMN transfers.amount IS 'The amount taken from the sender, including the tax';
+COMMENT ON COLUMN transfers.tax IS 'The part of the amount which was burned instead of being given to the recipient';
+COMMENT ON COLUMN Dicks.created_at IS 'When the user started playing in the chat; NULL for those who had started before it was tracked';
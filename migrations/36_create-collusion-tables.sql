@@ -0,0 +1,38 @@
+CREATE TABLE IF NOT EXISTS Battle_Log (
+    id bigserial PRIMARY KEY,
+    chat_id bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    winner_uid bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    loser_uid bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    bet integer NOT NULL CHECK (bet >= 0),
+    created_at timestamptz NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS idx_battle_log_created_at ON Battle_Log(created_at);
+
+COMMENT ON TABLE Battle_Log IS 'Recent battles kept for COLLUSION_WINDOW_DAYS to detect users feeding each other through PvP';
+
+CREATE TABLE IF NOT EXISTS Collusion_Flags (
+    id bigserial PRIMARY KEY,
+    chat_id bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    kind text NOT NULL CHECK (kind IN ('funnelling', 'pvp_pair', 'gift_only')),
+    uid bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    counterpart_uid bigint REFERENCES Users(uid) ON DELETE CASCADE,
+    amount bigint NOT NULL,
+    occurrences integer NOT NULL,
+    status text NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'confirmed', 'dismissed')),
+    created_at timestamptz NOT NULL DEFAULT current_timestamp,
+    reviewed_by bigint,
+    reviewed_at timestamptz
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_collusion_flags_unique ON Collusion_Flags(chat_id, kind, uid, (coalesce(counterpart_uid, 0)));
+
+COMMENT ON TABLE Collusion_Flags IS 'Suspicious patterns of transfers and battles found by the detector, to be reviewed by operators of the bot';
+COMMENT ON COLUMN Collusion_Flags.uid IS 'The suspected alt account: the one who gives the centimeters away';
+COMMENT ON COLUMN Collusion_Flags.counterpart_uid IS 'The one who receives the centimeters; NULL for those who just give them to everyone';
+COMMENT ON COLUMN Collusion_Flags.amount IS 'How many centimeters were moved within the window of the detection';
+COMMENT ON COLUMN Collusion_Flags.occurrences IS 'The number of transfers or battles behind the flag';
+
+ALTER TABLE transfers ADD COLUMN IF NOT EXISTS status text NOT NULL DEFAULT 'done' CHECK (status IN ('done', 'frozen', 'reverted'));
+
+COMMENT ON COLUMN transfers.status IS 'Transfers matching a collusion flag are frozen if GIFT_FREEZE_FLAGGED is enabled: the recipient gets nothing until an operator reviews the flag';
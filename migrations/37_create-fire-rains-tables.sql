@@ -0,0 +1,27 @@
+CREATE TABLE IF NOT EXISTS Fire_Rains (
+    id bigserial PRIMARY KEY,
+    chat_id bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    uid bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    amount integer NOT NULL CHECK (amount > 0),
+    shares integer NOT NULL CHECK (shares > 0 AND shares <= amount),
+    remaining integer NOT NULL CHECK (remaining >= 0 AND remaining <= amount),
+    message_id integer,
+    created_at timestamptz NOT NULL DEFAULT current_timestamp,
+    expires_at timestamptz NOT NULL,
+    finished_at timestamptz,
+    refunded integer NOT NULL DEFAULT 0 CHECK (refunded >= 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_fire_rains_unfinished ON Fire_Rains(expires_at) WHERE finished_at IS NULL;
+
+COMMENT ON TABLE Fire_Rains IS 'Centimeters thrown into a chat by /fire rain: the first users clicking the button catch a share each';
+COMMENT ON COLUMN Fire_Rains.remaining IS 'The centimeters nobody has caught yet, taken from the sender and returned to them after FIRE_RAIN_TIMEOUT_MINUTES';
+COMMENT ON COLUMN Fire_Rains.message_id IS 'The message with the button, edited on every catch';
+
+CREATE TABLE IF NOT EXISTS Fire_Rain_Claims (
+    rain_id bigint NOT NULL REFERENCES Fire_Rains(id) ON DELETE CASCADE,
+    uid bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    amount integer NOT NULL CHECK (amount > 0),
+    created_at timestamptz NOT NULL DEFAULT current_timestamp,
+    PRIMARY KEY (rain_id, uid)
+);
@@ -0,0 +1,6 @@
+-- duplicates left by the renamed users are released; the actual holders get their usernames back on their next update
+UPDATE Users SET username = NULL
+    WHERE lower(username) IN (SELECT lower(username) FROM Users GROUP BY lower(username) HAVING count(*) > 1);
+
+DROP INDEX IF EXISTS idx_users_username;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_username_unique ON Users(lower(username)) WHERE username IS NOT NULL;
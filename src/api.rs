@@ -1,10 +1,12 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
+    response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
-use log::debug;
+use log::{debug, error};
+use serde::Serialize;
 use rust_i18n::t;
 use std::sync::Arc;
 use teloxide::prelude::*;
@@ -12,7 +14,8 @@ use teloxide::types::ParseMode;
 
 use crate::{
     domain::{AdjustRequest, AdjustResponse, GetUserResponse},
-    repo::{ChatIdKind, Repositories},
+    config::ReloadableConfig,
+    repo::{ChatIdKind, SearchError},
 };
 
 use axum::{
@@ -22,9 +25,95 @@ use axum::{
     response::Response,
 };
 
+const TOMORROW_SQL_CODE: &str = "GD0E1";
+const FOREIGN_KEY_VIOLATION_SQL_CODE: &str = "23503";
+const UNIQUE_VIOLATION_SQL_CODE: &str = "23505";
+
+/// Errors returned by the API. The string codes are a part of the public contract,
+/// so don't rename the variants without a good reason.
+#[derive(Debug, strum_macros::Display, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum ApiError {
+    Unauthorized,
+    InvalidDelta,
+    UnknownLocale(String),
+    ChatNotFound,
+    UserNotFound,
+    AlreadyGrownToday,
+    Conflict,
+    Internal(anyhow::Error),
+}
+
+#[derive(Serialize)]
+struct ApiErrorBody {
+    error: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::InvalidDelta | Self::UnknownLocale(_) => StatusCode::BAD_REQUEST,
+            Self::ChatNotFound | Self::UserNotFound => StatusCode::NOT_FOUND,
+            Self::AlreadyGrownToday | Self::Conflict => StatusCode::CONFLICT,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Self::Unauthorized => "missing or invalid bearer token".to_owned(),
+            Self::InvalidDelta => "delta must not be zero".to_owned(),
+            Self::UnknownLocale(locale) => format!("unknown locale: {locale}"),
+            Self::ChatNotFound => "the chat is not known to the bot".to_owned(),
+            Self::UserNotFound => "the user is not known to the bot".to_owned(),
+            Self::AlreadyGrownToday => "the dick has been already grown today".to_owned(),
+            Self::Conflict => "the request conflicts with the current state".to_owned(),
+            // don't leak the details of internal errors to clients
+            Self::Internal(_) => "internal error".to_owned(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        if let Some(SearchError::NotFound(_)) = e.downcast_ref::<SearchError<ChatIdKind>>() {
+            return Self::ChatNotFound;
+        }
+        let code = e
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<sqlx::Error>())
+            .find_map(|db_err| match db_err {
+                sqlx::Error::Database(db_err) => db_err.code().map(|code| code.into_owned()),
+                _ => None,
+            });
+        match code.as_deref() {
+            Some(TOMORROW_SQL_CODE) => Self::AlreadyGrownToday,
+            Some(FOREIGN_KEY_VIOLATION_SQL_CODE) => Self::UserNotFound,
+            Some(UNIQUE_VIOLATION_SQL_CODE) => Self::Conflict,
+            _ => Self::Internal(e),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if let Self::Internal(e) = &self {
+            error!("API request failed: {e:?}");
+        }
+        let body = ApiErrorBody {
+            error: (&self).into(),
+            message: self.message(),
+        };
+        (self.status_code(), Json(body)).into_response()
+    }
+}
+
 #[derive(Clone)]
 pub struct ApiState {
-    pub repos: Repositories,
+    /// The snapshot is taken for every request to follow the reloaded configuration.
+    pub config: ReloadableConfig,
     pub bot: Bot,
     pub api_key: String,
 }
@@ -41,22 +130,32 @@ pub fn router(state: ApiState) -> Router {
 async fn adjust(
     State(state): State<Arc<ApiState>>,
     Json(payload): Json<AdjustRequest>,
-) -> Result<(StatusCode, Json<AdjustResponse>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<AdjustResponse>), ApiError> {
     debug!(
         "adjusting dick {:?} for user {:?}",
         payload.delta, payload.user_id
     );
 
+    if payload.delta == 0 {
+        return Err(ApiError::InvalidDelta);
+    }
+    let lang = match payload.locale {
+        Some(locale) if !rust_i18n::available_locales!().contains(&locale.as_str()) => {
+            return Err(ApiError::UnknownLocale(locale))
+        }
+        Some(locale) => locale,
+        None => "ru".to_string(),
+    };
+
     let chat_id = teloxide::types::ChatId(payload.chat_id);
     let uid = teloxide::types::UserId(payload.user_id);
-    let lang = payload.locale.unwrap_or_else(|| "ru".to_string());
 
-    let has = state
-        .repos
+    let snapshot = state.config.get();
+    let repos = &snapshot.repos;
+    let has = repos
         .dicks
         .is_user_has_dick(uid, &ChatIdKind::ID(chat_id))
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .await?;
 
     debug!("user {:?} has dick: {}", uid, has);
 
@@ -69,23 +168,19 @@ async fn adjust(
         ));
     }
 
-    let name = state
-        .repos
+    let name = repos
         .dicks
         .fetch_dick(uid, &ChatIdKind::ID(chat_id))
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .await?
         .map(|dick| dick.owner_name)
         .unwrap_or(format!("id:{}", uid.0));
 
     debug!("user {:?} dick name: {}", uid, name);
 
-    let res = state
-        .repos
+    let res = repos
         .dicks
         .grow_no_attempts_check(&ChatIdKind::ID(chat_id), uid, payload.delta)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .await?;
 
     debug!("user {:?} dick length: {}", uid, res.new_length);
 
@@ -119,16 +214,16 @@ async fn adjust(
 async fn get_user(
     State(state): State<Arc<ApiState>>,
     Path((chat_id, user_id)): Path<(i64, u64)>,
-) -> Result<(StatusCode, Json<GetUserResponse>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<GetUserResponse>), ApiError> {
     let chat_id = teloxide::types::ChatId(chat_id);
     let uid = teloxide::types::UserId(user_id);
 
-    let has = state
-        .repos
+    let snapshot = state.config.get();
+    let repos = &snapshot.repos;
+    let has = repos
         .dicks
         .is_user_has_dick(uid, &ChatIdKind::ID(chat_id))
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .await?;
 
     debug!("user {:?} has dick: {}", uid, has);
 
@@ -141,12 +236,10 @@ async fn get_user(
         ));
     }
 
-    let dick = state
-        .repos
+    let dick = repos
         .dicks
         .fetch_dick(uid, &ChatIdKind::ID(chat_id))
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .await?;
 
     if let Some(d) = dick {
         Ok((
@@ -174,7 +267,7 @@ async fn auth_bearer(
     State(state): State<Arc<ApiState>>,
     req: HttpRequest<Body>,
     next: Next,
-) -> Result<Response, (StatusCode, String)> {
+) -> Result<Response, ApiError> {
     let provided = req
         .headers()
         .get(header::AUTHORIZATION)
@@ -186,8 +279,36 @@ async fn auth_bearer(
         .unwrap_or("");
 
     if state.api_key.is_empty() || token != state.api_key {
-        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+        return Err(ApiError::Unauthorized);
     }
 
     Ok(next.run(req).await)
 }
+
+#[cfg(test)]
+mod test {
+    use super::ApiError;
+    use crate::repo::{ChatIdKind, SearchError};
+    use axum::http::StatusCode;
+    use teloxide::types::ChatId;
+
+    #[test]
+    fn test_error_codes() {
+        let err: &'static str = (&ApiError::AlreadyGrownToday).into();
+        assert_eq!(err, "already_grown_today");
+        let err: &'static str = (&ApiError::UnknownLocale("de".to_owned())).into();
+        assert_eq!(err, "unknown_locale");
+        assert_eq!(ApiError::InvalidDelta.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_from_anyhow() {
+        let not_found = anyhow::Error::new(SearchError::NotFound(ChatIdKind::ID(ChatId(-1))));
+        assert!(matches!(ApiError::from(not_found), ApiError::ChatNotFound));
+
+        let other = anyhow::anyhow!("something went wrong");
+        let err = ApiError::from(other);
+        assert!(matches!(err, ApiError::Internal(_)));
+        assert_eq!(err.message(), "internal error");
+    }
+}
@@ -2,8 +2,8 @@ use crate::config::CachedEnvToggles;
 use crate::handlers::pvp::BattleCommands;
 use crate::handlers::stats::StatsCommands;
 use crate::handlers::{
-    DickCommands, DickOfDayCommands, HelpCommands, ImportCommands, LoanCommands, PrivacyCommands,
-    PromoCommands,
+    DickCommands, DickOfDayCommands, HelpCommands, ImportCommands, LoanCommands,
+    PersonalDataCommands, PrivacyCommands, PromoCommands,
 };
 use crate::handlers::AdminCommands;
 use futures::future::join_all;
@@ -21,6 +21,7 @@ pub async fn set_my_commands(
     let personal_commands = vec![
         HelpCommands::bot_commands(),
         PrivacyCommands::bot_commands(),
+        PersonalDataCommands::bot_commands(),
         PromoCommands::bot_commands(),
         StatsCommands::bot_commands(),
     ];
@@ -23,7 +23,7 @@ pub struct Announcement {
 }
 
 impl Announcement {
-    pub(super) fn new(text: String) -> Option<Self> {
+    pub fn new(text: String) -> Option<Self> {
         text.is_empty().not().then(|| Self {
             hash: Arc::new(Sha256::digest(text.as_bytes()).to_vec()),
             text: Arc::new(text),
@@ -1,13 +1,19 @@
 use crate::config::announcements::*;
 use crate::config::env::*;
+use crate::config::schema::*;
 use crate::config::toggles::*;
 use crate::domain::Ratio;
-use crate::domain::SupportedLanguage::{EN, RU};
+use crate::domain::SupportedLanguage;
+use crate::handlers::utils::FireRounding;
+use anyhow::Context;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use strum::IntoEnumIterator;
+use teloxide::types::UserId;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct GiftRestrictionsConfig {
@@ -28,28 +34,31 @@ pub struct GiftRestrictionConfig {
 
 impl GiftRestrictionConfig {
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Self {
-        fs::read_to_string(path)
-            .ok()
-            .and_then(|content| toml::from_str::<GiftRestrictionsConfig>(&content).ok())
-            .map(|config| {
-                config
-                    .restricted_users
+        Self::try_load_from_file(path).unwrap_or_else(|e| {
+            log::warn!("Failed to load gift restrictions config: {e:#}");
+            Self {
+                restrictions: HashMap::new(),
+            }
+        })
+    }
+
+    pub fn try_load_from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read {}", path.display()))?;
+        let config = toml::from_str::<GiftRestrictionsConfig>(&content)
+            .with_context(|| format!("couldn't parse {}", path.display()))?;
+        let restrictions = config
+            .restricted_users
+            .into_iter()
+            .flat_map(|user_group| {
+                user_group
+                    .user_ids
                     .into_iter()
-                    .flat_map(|user_group| {
-                        user_group
-                            .user_ids
-                            .into_iter()
-                            .map(move |user_id| (user_id, user_group.custom_name.clone()))
-                    })
-                    .collect()
-            })
-            .map(|restrictions| Self { restrictions })
-            .unwrap_or_else(|| {
-                log::warn!("Failed to load gift restrictions config");
-                Self {
-                    restrictions: HashMap::new(),
-                }
+                    .map(move |user_id| (user_id, user_group.custom_name.clone()))
             })
+            .collect();
+        Ok(Self { restrictions })
     }
 }
 
@@ -58,13 +67,69 @@ impl GiftRestrictionConfig {
 pub struct AppConfig {
     pub features: FeatureToggles,
     pub top_limit: u16,
+    pub newcomers: NewcomersConfig,
     pub loan_payout_ratio: f32,
     pub dod_rich_exclusion_ratio: Option<Ratio>,
     pub pvp_default_bet: u16,
     pub fire_recipients: u16,
+    /// Unclaimed centimeters of a fire rain are returned to the sender after this timeout
+    pub fire_rain_timeout_minutes: u16,
+    pub fire_rounding: FireRounding,
+    pub gifts: GiftsConfig,
+    pub collusion: CollusionConfig,
     pub announcements: AnnouncementsConfig,
     pub command_toggles: CachedEnvToggles,
     pub gift_restriction: GiftRestrictionConfig,
+    pub gift_restrictions_file: Option<PathBuf>,
+    /// Users who maintain the bot and are allowed to perform global actions
+    pub operators: HashSet<u64>,
+    /// How long the lists of administrators of chats are cached; 0 disables the cache
+    pub chat_admins_cache_ttl: Duration,
+    /// The data of chats the bot was removed from is deleted after this number of days; 0 disables the purging
+    pub inactive_chats_retention_days: u16,
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(Default))]
+pub struct NewcomersConfig {
+    /// Newcomers are marked in the top during this period since the registration
+    pub grace_days: u32,
+    /// Granted once per chat on the first growth; 0 disables the bonus
+    pub starter_bonus: u16,
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(Default))]
+pub struct GiftsConfig {
+    /// How many centimeters a user may give away in a chat within 24 hours; 0 disables the cap
+    pub daily_cap: u32,
+    /// The minimal interval between transfers of a user in a chat; 0 disables the cooldown
+    pub cooldown_minutes: u32,
+    /// Recipients must have been known to the bot for this number of days
+    pub recipient_min_account_age_days: u16,
+    /// Recipients must have been playing in the chat for this number of days
+    pub recipient_min_chat_tenure_days: u16,
+    /// The share of every transfer which is burned instead of being given to the recipient
+    pub tax_ratio: f64,
+    /// Transfers matching a collusion flag are held until an operator reviews it
+    pub freeze_flagged: bool,
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(Default))]
+pub struct CollusionConfig {
+    /// How many days of transfers and battles are analyzed; 0 disables the detector and the log of battles
+    pub window_days: u16,
+    /// Senders are considered fresh during this period since the registration
+    pub fresh_account_days: u16,
+    /// How many centimeters a fresh account must give to one user to be flagged
+    pub min_funnelled: u32,
+    /// How many battles a pair must have to be analyzed
+    pub min_pvp_battles: u32,
+    /// The share of battles one of the pair must lose to be flagged
+    pub pvp_loss_ratio: f64,
+    /// How many gifts a user who never plays must give to be flagged
+    pub min_gifts_only: u32,
 }
 
 #[derive(Clone)]
@@ -75,31 +140,42 @@ pub struct DatabaseConfig {
 
 impl AppConfig {
     pub fn from_env() -> Self {
-        let top_limit = get_env_value_or_default("TOP_LIMIT", 10);
-        let loan_payout_ratio = get_env_value_or_default("LOAN_PAYOUT_COEF", 0.0);
-        let dod_selection_mode = get_optional_env_value("DOD_SELECTION_MODE");
-        let dod_rich_exclusion_ratio = get_optional_env_ratio("DOD_RICH_EXCLUSION_RATIO");
-        let chats_merging = get_env_value_or_default("CHATS_MERGING_ENABLED", false);
-        let top_unlimited = get_env_value_or_default("TOP_UNLIMITED_ENABLED", false);
-        let multiple_loans = get_env_value_or_default("MULTIPLE_LOANS_ENABLED", false);
-        let pvp_default_bet = get_env_value_or_default("PVP_DEFAULT_BET", 1);
-        let fire_recipients = get_env_value_or_default("FIRE_RECIPIENTS", 5);
-        let check_acceptor_length = get_env_value_or_default("PVP_CHECK_ACCEPTOR_LENGTH", false);
-        let callback_locks = get_env_value_or_default("PVP_CALLBACK_LOCKS_ENABLED", true);
-        let show_stats = get_env_value_or_default("PVP_STATS_SHOW", true);
-        let show_stats_notice = get_env_value_or_default("PVP_STATS_SHOW_NOTICE", true);
-        let announcement_max_shows = get_optional_env_value("ANNOUNCEMENT_MAX_SHOWS");
-        let announcement_en = get_optional_env_value("ANNOUNCEMENT_EN");
-        let announcement_ru = get_optional_env_value("ANNOUNCEMENT_RU");
-        let gift_restriction_file: String = get_optional_env_value("GIFT_RESTRICTIONS_FILE");
+        let top_limit = get_setting("TOP_LIMIT");
+        let loan_payout_ratio = get_setting("LOAN_PAYOUT_COEF");
+        let dod_selection_mode = get_setting("DOD_SELECTION_MODE");
+        let dod_rich_exclusion_ratio = get_optional_setting("DOD_RICH_EXCLUSION_RATIO")
+            .and_then(|ratio| Ratio::new(ratio).ok());
+        let chats_merging = get_setting("CHATS_MERGING_ENABLED");
+        let top_unlimited = get_setting("TOP_UNLIMITED_ENABLED");
+        let multiple_loans = get_setting("MULTIPLE_LOANS_ENABLED");
+        let pvp_default_bet = get_setting("PVP_DEFAULT_BET");
+        let fire_recipients = get_setting("FIRE_RECIPIENTS");
+        let check_acceptor_length = get_setting("PVP_CHECK_ACCEPTOR_LENGTH");
+        let callback_locks = get_setting("PVP_CALLBACK_LOCKS_ENABLED");
+        let show_stats = get_setting("PVP_STATS_SHOW");
+        let show_stats_notice = get_setting("PVP_STATS_SHOW_NOTICE");
+        let announcement_max_shows = get_setting("ANNOUNCEMENT_MAX_SHOWS");
+        let gift_restriction_file: String = get_optional_setting("GIFT_RESTRICTIONS_FILE").unwrap_or_default();
+        let operators = get_optional_setting::<String>("BOT_OPERATORS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .filter_map(|id| id.parse::<u64>()
+                .inspect_err(|_| log::warn!("invalid user ID in BOT_OPERATORS: {id}"))
+                .ok())
+            .collect();
 
         let gift_restriction = if gift_restriction_file.is_empty() {
             log::warn!("GIFT_RESTRICTIONS_FILE is empty, using default gift restrictions");
             GiftRestrictionConfig::default()
         } else {
             log::info!("Loading gift restrictions from file: {}", gift_restriction_file);
-            GiftRestrictionConfig::load_from_file(gift_restriction_file)
+            GiftRestrictionConfig::load_from_file(&gift_restriction_file)
         };
+        let gift_restrictions_file = Some(gift_restriction_file)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
 
         Self {
             features: FeatureToggles {
@@ -115,29 +191,58 @@ impl AppConfig {
                 },
             },
             top_limit,
+            newcomers: NewcomersConfig {
+                grace_days: get_setting("NEWCOMERS_GRACE_DAYS"),
+                starter_bonus: get_setting("NEWCOMERS_STARTER_BONUS"),
+            },
             loan_payout_ratio,
             dod_rich_exclusion_ratio,
             pvp_default_bet,
             fire_recipients,
+            fire_rain_timeout_minutes: get_setting("FIRE_RAIN_TIMEOUT_MINUTES"),
+            fire_rounding: get_setting("FIRE_ROUNDING"),
+            gifts: GiftsConfig {
+                daily_cap: get_setting("GIFT_DAILY_CAP"),
+                cooldown_minutes: get_setting("GIFT_COOLDOWN_MINUTES"),
+                recipient_min_account_age_days: get_setting("GIFT_RECIPIENT_MIN_ACCOUNT_AGE_DAYS"),
+                recipient_min_chat_tenure_days: get_setting("GIFT_RECIPIENT_MIN_CHAT_TENURE_DAYS"),
+                tax_ratio: get_setting("GIFT_TAX_RATIO"),
+                freeze_flagged: get_setting("GIFT_FREEZE_FLAGGED"),
+            },
+            collusion: CollusionConfig {
+                window_days: get_setting("COLLUSION_WINDOW_DAYS"),
+                fresh_account_days: get_setting("COLLUSION_FRESH_ACCOUNT_DAYS"),
+                min_funnelled: get_setting("COLLUSION_MIN_FUNNELLED"),
+                min_pvp_battles: get_setting("COLLUSION_MIN_PVP_BATTLES"),
+                pvp_loss_ratio: get_setting("COLLUSION_PVP_LOSS_RATIO"),
+                min_gifts_only: get_setting("COLLUSION_MIN_GIFTS_ONLY"),
+            },
             announcements: AnnouncementsConfig {
                 max_shows: announcement_max_shows,
-                announcements: [(EN, announcement_en), (RU, announcement_ru)]
-                    .map(|(lc, text)| (lc, Announcement::new(text)))
-                    .into_iter()
-                    .filter_map(|(lc, mb_ann)| mb_ann.map(|ann| (lc, ann)))
+                announcements: SupportedLanguage::iter()
+                    .map(|lc| (lc, get_optional_setting(&format!("ANNOUNCEMENT_{}", lc.as_ref().to_uppercase())).unwrap_or_default()))
+                    .filter_map(|(lc, text)| Announcement::new(text).map(|ann| (lc, ann)))
                     .collect(),
             },
             command_toggles: Default::default(),
             gift_restriction,
+            gift_restrictions_file,
+            operators,
+            chat_admins_cache_ttl: Duration::from_secs(get_setting("CHAT_ADMINS_CACHE_TTL")),
+            inactive_chats_retention_days: get_setting("INACTIVE_CHATS_RETENTION_DAYS"),
         }
     }
+
+    pub fn is_operator(&self, uid: UserId) -> bool {
+        self.operators.contains(&uid.0)
+    }
 }
 
 impl DatabaseConfig {
     pub fn from_env() -> anyhow::Result<Self> {
         Ok(Self {
             url: get_env_mandatory_value("DATABASE_URL")?,
-            max_connections: get_env_value_or_default("DATABASE_MAX_CONNECTIONS", 10),
+            max_connections: get_setting("DATABASE_MAX_CONNECTIONS"),
         })
     }
 }
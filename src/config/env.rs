@@ -1,15 +1,47 @@
-use crate::domain::Ratio;
 use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::env::VarError;
 use std::error::Error;
 use std::fmt::Display;
 use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+const FILE_VALUES_POISONED_MSG: &str = "the map of values from the configuration file was poisoned";
+
+/// Values loaded from the configuration file. The environment takes precedence over them.
+static FILE_VALUES: Lazy<RwLock<Arc<HashMap<String, String>>>> = Lazy::new(Default::default);
+
+/// Returns the previous values, so they can be restored if the new ones turn out to be invalid.
+pub(super) fn replace_file_values(values: HashMap<String, String>) -> HashMap<String, String> {
+    let mut guard = FILE_VALUES.write().expect(FILE_VALUES_POISONED_MSG);
+    let previous = std::mem::replace(&mut *guard, Arc::new(values));
+    Arc::unwrap_or_clone(previous)
+}
+
+pub(super) fn get_file_values() -> Arc<HashMap<String, String>> {
+    FILE_VALUES.read().expect(FILE_VALUES_POISONED_MSG).clone()
+}
+
+pub(super) fn get_value(key: &str) -> Result<String, VarError> {
+    get_value_with(&get_file_values(), key)
+}
+
+/// Looks the key up as if the configuration file had the given values.
+pub(super) fn get_value_with(file_values: &HashMap<String, String>, key: &str) -> Result<String, VarError> {
+    std::env::var(key).or_else(|e| file_values.get(key).cloned().ok_or(e))
+}
+
+pub(super) fn is_value_set(key: &str) -> bool {
+    get_value(key).is_ok()
+}
 
 pub(super) fn get_env_mandatory_value<T, E>(key: &str) -> anyhow::Result<T>
 where
     T: FromStr<Err = E>,
     E: Error + Send + Sync + 'static,
 {
-    std::env::var(key)?.parse().map_err(|e: E| anyhow!(e))
+    get_value(key)?.parse().map_err(|e: E| anyhow!(e))
 }
 
 pub fn get_env_value_or_default<T, E>(key: &str, default: T) -> T
@@ -17,7 +49,7 @@ where
     T: FromStr<Err = E> + Display,
     E: Error + Send + Sync + 'static,
 {
-    std::env::var(key)
+    get_value(key)
         .map_err(|e| {
             log::warn!("no value was found for an optional environment variable {key}, using the default value {default}");
             anyhow!(e)
@@ -29,18 +61,3 @@ where
             }))
         .unwrap_or(default)
 }
-
-pub(super) fn get_optional_env_value<T>(key: &str) -> T
-where
-    T: Default + FromStr + Display,
-    <T as FromStr>::Err: Error + Send + Sync + 'static,
-{
-    get_env_value_or_default(key, T::default())
-}
-
-pub(super) fn get_optional_env_ratio(key: &str) -> Option<Ratio> {
-    let value = get_env_value_or_default(key, -1.0);
-    Ratio::new(value)
-        .inspect_err(|_| log::warn!("{key} is disabled due to the invalid value: {value}"))
-        .ok()
-}
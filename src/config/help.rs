@@ -20,6 +20,7 @@ pub fn build_context_for_help_messages(
         bot_name: me.username().to_owned(),
         grow_min: incr_cfg.growth_range_min().to_string(),
         grow_max: incr_cfg.growth_range_max().to_string(),
+        growth_distribution: incr_cfg.growth_distribution_name().to_owned(),
         other_bots,
         admin_channel_ru: ensure_starts_with_at_sign(get_env_mandatory_value(
             "HELP_ADMIN_CHANNEL_RU",
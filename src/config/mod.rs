@@ -2,11 +2,15 @@ mod announcements;
 mod app;
 mod env;
 mod help;
+mod reload;
+mod schema;
 mod toggles;
 
 pub use announcements::*;
 pub use app::*;
 pub use help::*;
+pub use reload::*;
 pub use toggles::*;
 
 pub use env::get_env_value_or_default;
+pub use schema::{get_setting, render_effective_config, validate};
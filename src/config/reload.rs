@@ -0,0 +1,239 @@
+use crate::config::env::replace_file_values;
+use crate::config::schema::{load_file, validate_file_values};
+use crate::config::{
+    build_context_for_help_messages, get_env_value_or_default, get_setting, render_effective_config, validate,
+    AppConfig, GiftRestrictionConfig,
+};
+use crate::handlers::utils::Incrementor;
+use crate::help::HelpContainer;
+use crate::{handlers, help, metrics, repo};
+use anyhow::anyhow;
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use teloxide::types::Me;
+
+const SNAPSHOT_POISONED_MSG: &str = "the configuration snapshot was poisoned";
+const RELOAD_LOCK_POISONED_MSG: &str = "the configuration reload lock was poisoned";
+
+/// Everything built from the configuration. It's replaced as a whole, so a handler never sees
+/// a mix of the old and new values.
+#[derive(Clone)]
+pub struct ConfigSnapshot {
+    pub app_config: AppConfig,
+    pub repos: repo::Repositories,
+    pub incrementor: Incrementor,
+    pub help_container: HelpContainer,
+}
+
+/// Keeps the current [`ConfigSnapshot`] and rebuilds it when the configuration file
+/// or the gift restrictions file is changed, or when the process receives SIGHUP.
+#[derive(Clone)]
+pub struct ReloadableConfig {
+    current: Arc<RwLock<Arc<ConfigSnapshot>>>,
+    reload_lock: Arc<Mutex<()>>,
+    config_file: Option<PathBuf>,
+    db_conn: Pool<Postgres>,
+    me: Me,
+}
+
+impl ReloadableConfig {
+    /// The configuration file must be already loaded by [`load_config_file`].
+    pub fn new(config_file: Option<PathBuf>, db_conn: Pool<Postgres>, me: Me) -> anyhow::Result<Self> {
+        let snapshot = build_snapshot(&db_conn, &me)?;
+        Ok(Self {
+            current: Arc::new(RwLock::new(Arc::new(snapshot))),
+            reload_lock: Default::default(),
+            config_file,
+            db_conn,
+            me,
+        })
+    }
+
+    pub fn get(&self) -> Arc<ConfigSnapshot> {
+        self.current.read().expect(SNAPSHOT_POISONED_MSG).clone()
+    }
+
+    pub fn reload(&self, reason: &str) {
+        match self.try_reload() {
+            Ok(()) => {
+                log::info!("the configuration has been reloaded ({reason})");
+                metrics::CONFIG_RELOAD_COUNTER.succeeded.inc();
+            }
+            Err(e) => {
+                log::error!("couldn't reload the configuration ({reason}), the previous one is kept: {e:#}");
+                metrics::CONFIG_RELOAD_COUNTER.failed.inc();
+            }
+        }
+    }
+
+    fn try_reload(&self) -> anyhow::Result<()> {
+        let _guard = self.reload_lock.lock().expect(RELOAD_LOCK_POISONED_MSG);
+        let previous_values = install_file_values(self.config_file.as_deref())?;
+        match build_snapshot(&self.db_conn, &self.me) {
+            Ok(snapshot) => {
+                *self.current.write().expect(SNAPSHOT_POISONED_MSG) = Arc::new(snapshot);
+                Ok(())
+            }
+            Err(e) => {
+                replace_file_values(previous_values);
+                Err(e)
+            }
+        }
+    }
+
+    fn watched_files(&self) -> Vec<PathBuf> {
+        self.config_file
+            .iter()
+            .chain(self.get().app_config.gift_restrictions_file.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Starts background tasks reloading the configuration on SIGHUP and on changes of the files.
+    /// The files are polled every `CONFIG_WATCH_INTERVAL` seconds; 0 disables the polling.
+    pub fn spawn_watchers(&self) {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            match signal(SignalKind::hangup()) {
+                Ok(mut hangups) => {
+                    let this = self.clone();
+                    tokio::spawn(async move {
+                        while hangups.recv().await.is_some() {
+                            this.reload("SIGHUP");
+                        }
+                    });
+                }
+                Err(e) => log::error!("couldn't install the SIGHUP handler: {e}"),
+            }
+        }
+
+        let interval_secs: u64 = get_setting("CONFIG_WATCH_INTERVAL");
+        if interval_secs == 0 || self.watched_files().is_empty() {
+            return;
+        }
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut modification_times = get_modification_times(&this.watched_files());
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                let current_times = get_modification_times(&this.watched_files());
+                if current_times != modification_times {
+                    this.reload("a file has been changed");
+                    // the list of files may be changed by the reload itself
+                    modification_times = get_modification_times(&this.watched_files());
+                }
+            }
+        });
+    }
+}
+
+/// Loads the file set by the `CONFIG_FILE` environment variable, if any, and returns its path.
+/// An invalid file at startup is a fatal error, unlike reloads.
+pub fn load_config_file() -> anyhow::Result<Option<PathBuf>> {
+    let config_file = Some(get_env_value_or_default("CONFIG_FILE", String::new()))
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+    if let Some(path) = &config_file {
+        log::info!("Loading the configuration from file: {}", path.display());
+        replace_file_values(load_file(path)?);
+    }
+    Ok(config_file)
+}
+
+/// Prints the effective configuration and checks it. The database and Telegram are not touched.
+pub fn check_config(config_file: Option<&Path>) -> anyhow::Result<()> {
+    let file_values = config_file.map(load_file).transpose()?.unwrap_or_default();
+    println!("{}", render_effective_config(&file_values));
+    validate()?;
+    if let Some(path) = AppConfig::from_env().gift_restrictions_file {
+        GiftRestrictionConfig::try_load_from_file(path)?;
+    }
+    println!("The configuration is valid.");
+    Ok(())
+}
+
+/// Replaces the values of the configuration file only if they are valid, and returns the previous ones.
+fn install_file_values(config_file: Option<&Path>) -> anyhow::Result<HashMap<String, String>> {
+    let values = match config_file {
+        Some(path) => load_file(path)?,
+        None => HashMap::new(),
+    };
+    validate_file_values(&values)?;
+    Ok(replace_file_values(values))
+}
+
+fn build_snapshot(db_conn: &Pool<Postgres>, me: &Me) -> anyhow::Result<ConfigSnapshot> {
+    // the values are parsed without fallbacks below, so they must be checked first
+    validate()?;
+    let app_config = AppConfig::from_env();
+    if let Some(path) = &app_config.gift_restrictions_file {
+        GiftRestrictionConfig::try_load_from_file(path)?;
+    }
+    let repos = repo::Repositories::new(db_conn, &app_config);
+    let perks = handlers::perks::all(db_conn, &app_config);
+    let incrementor = Incrementor::from_env(&repos.dicks, perks);
+    let help_context = build_context_for_help_messages(
+        me.clone(),
+        &incrementor,
+        &handlers::ORIGINAL_BOT_USERNAMES,
+    )?;
+    let help_container = help::render_help_messages(help_context)
+        .map_err(|e| anyhow!("couldn't render the help messages: {e}"))?;
+    Ok(ConfigSnapshot {
+        app_config,
+        repos,
+        incrementor,
+        help_container,
+    })
+}
+
+fn get_modification_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::install_file_values;
+    use crate::config::env::replace_file_values;
+    use crate::config::get_setting;
+    use std::fs;
+
+    const REQUIRED_SETTINGS: &str = r#"
+        [database]
+        url = "postgres://localhost/test"
+
+        [help]
+        admin_channel_ru = "@channel_ru"
+        admin_channel_en = "@channel_en"
+        admin_chat_ru = "@chat_ru"
+        admin_chat_en = "@chat_en"
+        git_repo = "https://github.com/kozalosev/DickGrowerBot"
+    "#;
+
+    #[test]
+    fn test_reload_keeps_values_on_errors() {
+        let path = std::env::temp_dir().join("dickgrowerbot-test-reload.toml");
+        fs::write(&path, format!("{REQUIRED_SETTINGS}\n[top]\nlimit = 7\n")).expect("couldn't write the test config");
+        let original_values = install_file_values(Some(&path)).expect("couldn't install a valid config");
+        assert_eq!(get_setting::<u16>("TOP_LIMIT"), 7);
+
+        for invalid in ["[top]\nlimit = \"many\"\n", "[top]\nlimit = 0\n", "[growth]\nmin = 10\nmax = 5\n", "[top]\nlimit = [\n"] {
+            fs::write(&path, format!("{REQUIRED_SETTINGS}\n{invalid}")).expect("couldn't write the test config");
+            assert!(install_file_values(Some(&path)).is_err(), "{invalid} must be rejected");
+            assert_eq!(get_setting::<u16>("TOP_LIMIT"), 7, "the previous values must be kept after {invalid}");
+        }
+
+        replace_file_values(original_values);
+        fs::remove_file(&path).expect("couldn't remove the test config");
+    }
+}
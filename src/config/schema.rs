@@ -0,0 +1,486 @@
+use crate::config::env::{get_file_values, get_value, get_value_with};
+use crate::config::DickOfDaySelectionMode;
+use crate::handlers::utils::{FireRounding, GrowthDistributionKind};
+use anyhow::{anyhow, Context};
+use std::collections::{BTreeSet, HashMap};
+use std::env::VarError;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use DefaultValue::{Required, Unset, Value};
+
+const COMMANDS_SECTION: &str = "commands";
+const PERKS_SECTION: &str = "perks";
+const DISABLED_KEY: &str = "disabled";
+const DISABLED_COMMAND_PREFIX: &str = "DISABLE_CMD_";
+const DISABLED_PERK_PREFIX: &str = "DISABLE_";
+const HIDDEN_VALUE: &str = "<hidden>";
+
+#[derive(Copy, Clone)]
+enum Kind {
+    Bool,
+    Int { min: i64, max: i64 },
+    /// A floating point number within 0..1.
+    Ratio,
+    Text,
+    /// A text which must never be printed.
+    Secret,
+    Custom(fn(&str) -> Result<(), String>),
+}
+
+#[derive(Copy, Clone)]
+enum DefaultValue {
+    Value(&'static str),
+    Unset,
+    Required,
+}
+
+/// A setting is read from the environment variable `env` or from the `name` key of the `[section]`
+/// table of the configuration file. The environment takes precedence over the file.
+struct Setting {
+    section: &'static str,
+    name: &'static str,
+    env: &'static str,
+    kind: Kind,
+    default: DefaultValue,
+}
+
+const fn setting(
+    section: &'static str,
+    name: &'static str,
+    env: &'static str,
+    kind: Kind,
+    default: DefaultValue,
+) -> Setting {
+    Setting {
+        section,
+        name,
+        env,
+        kind,
+        default,
+    }
+}
+
+const fn int(min: i64, max: i64) -> Kind {
+    Kind::Int { min, max }
+}
+
+#[rustfmt::skip]
+static SETTINGS: &[Setting] = &[
+    setting("database", "url", "DATABASE_URL", Kind::Secret, Required),
+    setting("database", "max_connections", "DATABASE_MAX_CONNECTIONS", int(1, u32::MAX as i64), Value("10")),
+    setting("help", "admin_channel_ru", "HELP_ADMIN_CHANNEL_RU", Kind::Text, Required),
+    setting("help", "admin_channel_en", "HELP_ADMIN_CHANNEL_EN", Kind::Text, Required),
+    setting("help", "admin_chat_ru", "HELP_ADMIN_CHAT_RU", Kind::Text, Required),
+    setting("help", "admin_chat_en", "HELP_ADMIN_CHAT_EN", Kind::Text, Required),
+    setting("help", "git_repo", "HELP_GIT_REPO", Kind::Text, Required),
+    setting("growth", "min", "GROWTH_MIN", int(i16::MIN as i64, i16::MAX as i64), Value("-5")),
+    setting("growth", "max", "GROWTH_MAX", int(i16::MIN as i64, i16::MAX as i64), Value("10")),
+    setting("growth", "shrink_ratio", "GROW_SHRINK_RATIO", Kind::Ratio, Value("0.5")),
+    setting("growth", "distribution", "GROWTH_DISTRIBUTION", Kind::Custom(validate_growth_distribution), Value("UNIFORM")),
+    setting("growth", "diminishing_scale", "GROWTH_DIMINISHING_SCALE", int(1, u16::MAX as i64), Value("100")),
+    setting("growth", "dod_bonus_max", "GROWTH_DOD_BONUS_MAX", int(1, u8::MAX as i64), Value("5")),
+    setting("growth", "newcomers_grace_days", "NEWCOMERS_GRACE_DAYS", int(0, u32::MAX as i64), Value("7")),
+    setting("growth", "newcomers_starter_bonus", "NEWCOMERS_STARTER_BONUS", int(0, i16::MAX as i64), Value("5")),
+    setting("growth", "critical_chance", "GROWTH_CRITICAL_CHANCE", Kind::Ratio, Value("0.03")),
+    setting("growth", "critical_multiplier", "GROWTH_CRITICAL_MULTIPLIER", int(1, 100), Value("3")),
+    setting("growth", "catastrophe_chance", "GROWTH_CATASTROPHE_CHANCE", Kind::Ratio, Value("0.02")),
+    setting("growth", "catastrophe_loss_ratio", "GROWTH_CATASTROPHE_LOSS_RATIO", Kind::Ratio, Value("0.2")),
+    setting("growth", "jackpot_chance", "GROWTH_JACKPOT_CHANCE", Kind::Ratio, Value("0.001")),
+    setting("growth", "jackpot_amount", "GROWTH_JACKPOT_AMOUNT", int(1, i16::MAX as i64), Value("100")),
+    setting("top", "limit", "TOP_LIMIT", int(1, u16::MAX as i64), Value("10")),
+    setting("top", "unlimited", "TOP_UNLIMITED_ENABLED", Kind::Bool, Value("false")),
+    setting("chats", "merging", "CHATS_MERGING_ENABLED", Kind::Bool, Value("false")),
+    setting("chats", "inactive_retention_days", "INACTIVE_CHATS_RETENTION_DAYS", int(0, u16::MAX as i64), Value("0")),
+    setting("loans", "multiple", "MULTIPLE_LOANS_ENABLED", Kind::Bool, Value("false")),
+    setting("loans", "payout_ratio", "LOAN_PAYOUT_COEF", Kind::Ratio, Value("0.0")),
+    setting("dod", "selection_mode", "DOD_SELECTION_MODE", Kind::Custom(validate_dod_selection_mode), Value("RANDOM")),
+    setting("dod", "rich_exclusion_ratio", "DOD_RICH_EXCLUSION_RATIO", Kind::Ratio, Unset),
+    setting("pvp", "default_bet", "PVP_DEFAULT_BET", int(1, u16::MAX as i64), Value("1")),
+    setting("pvp", "check_acceptor_length", "PVP_CHECK_ACCEPTOR_LENGTH", Kind::Bool, Value("false")),
+    setting("pvp", "callback_locks", "PVP_CALLBACK_LOCKS_ENABLED", Kind::Bool, Value("true")),
+    setting("pvp", "show_stats", "PVP_STATS_SHOW", Kind::Bool, Value("true")),
+    setting("pvp", "show_stats_notice", "PVP_STATS_SHOW_NOTICE", Kind::Bool, Value("true")),
+    setting("fire", "recipients", "FIRE_RECIPIENTS", int(1, u16::MAX as i64), Value("5")),
+    setting("fire", "rain_timeout_minutes", "FIRE_RAIN_TIMEOUT_MINUTES", int(1, u16::MAX as i64), Value("10")),
+    setting("fire", "rounding", "FIRE_ROUNDING", Kind::Custom(validate_fire_rounding), Value("LARGEST")),
+    setting("perks", "help_pussies_coef", "HELP_PUSSIES_COEF", Kind::Ratio, Value("0.0")),
+    setting("perks", "grow_streak_days_per_cm", "GROW_STREAK_DAYS_PER_CM", int(0, u16::MAX as i64), Value("3")),
+    setting("perks", "grow_streak_max_bonus", "GROW_STREAK_MAX_BONUS", int(0, u16::MAX as i64), Value("5")),
+    setting("announcements", "max_shows", "ANNOUNCEMENT_MAX_SHOWS", int(0, u32::MAX as i64), Value("0")),
+    setting("announcements", "en", "ANNOUNCEMENT_EN", Kind::Text, Unset),
+    setting("announcements", "ru", "ANNOUNCEMENT_RU", Kind::Text, Unset),
+    setting("announcements", "fa", "ANNOUNCEMENT_FA", Kind::Text, Unset),
+    setting("announcements", "zh", "ANNOUNCEMENT_ZH", Kind::Text, Unset),
+    setting("gifts", "restrictions_file", "GIFT_RESTRICTIONS_FILE", Kind::Text, Unset),
+    setting("gifts", "daily_cap", "GIFT_DAILY_CAP", int(0, i32::MAX as i64), Value("0")),
+    setting("gifts", "cooldown_minutes", "GIFT_COOLDOWN_MINUTES", int(0, i32::MAX as i64), Value("0")),
+    setting("gifts", "recipient_min_account_age_days", "GIFT_RECIPIENT_MIN_ACCOUNT_AGE_DAYS", int(0, u16::MAX as i64), Value("0")),
+    setting("gifts", "recipient_min_chat_tenure_days", "GIFT_RECIPIENT_MIN_CHAT_TENURE_DAYS", int(0, u16::MAX as i64), Value("0")),
+    setting("gifts", "tax_ratio", "GIFT_TAX_RATIO", Kind::Ratio, Value("0.0")),
+    setting("gifts", "freeze_flagged", "GIFT_FREEZE_FLAGGED", Kind::Bool, Value("false")),
+    setting("collusion", "window_days", "COLLUSION_WINDOW_DAYS", int(0, u16::MAX as i64), Value("7")),
+    setting("collusion", "fresh_account_days", "COLLUSION_FRESH_ACCOUNT_DAYS", int(0, u16::MAX as i64), Value("3")),
+    setting("collusion", "min_funnelled", "COLLUSION_MIN_FUNNELLED", int(1, i32::MAX as i64), Value("50")),
+    setting("collusion", "min_pvp_battles", "COLLUSION_MIN_PVP_BATTLES", int(1, i32::MAX as i64), Value("10")),
+    setting("collusion", "pvp_loss_ratio", "COLLUSION_PVP_LOSS_RATIO", Kind::Ratio, Value("0.8")),
+    setting("collusion", "min_gifts_only", "COLLUSION_MIN_GIFTS_ONLY", int(1, i32::MAX as i64), Value("5")),
+    setting("bot", "operators", "BOT_OPERATORS", Kind::Custom(validate_user_ids), Unset),
+    setting("bot", "config_watch_interval", "CONFIG_WATCH_INTERVAL", int(0, u32::MAX as i64), Value("10")),
+    setting("bot", "chat_admins_cache_ttl", "CHAT_ADMINS_CACHE_TTL", int(0, u32::MAX as i64), Value("300")),
+];
+
+fn find(env: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|setting| setting.env == env)
+}
+
+/// Returns the value from the environment or the configuration file, or the default one.
+/// The configuration must be validated beforehand by [`validate`].
+pub fn get_setting<T: FromStr>(env: &str) -> T {
+    get_optional_setting(env)
+        .unwrap_or_else(|| panic!("{env} is not set and has no default value"))
+}
+
+pub fn get_optional_setting<T: FromStr>(env: &str) -> Option<T> {
+    let value = get_effective_value(env)?;
+    let parsed = value
+        .parse()
+        .unwrap_or_else(|_| panic!("{env} has an invalid value, the configuration must be validated first"));
+    Some(parsed)
+}
+
+fn get_effective_value(env: &str) -> Option<String> {
+    effective_value(env, get_value(env))
+}
+
+fn effective_value(env: &str, value: Result<String, VarError>) -> Option<String> {
+    let setting = find(env).unwrap_or_else(|| panic!("{env} is not declared in the schema"));
+    match (value, setting.default) {
+        (Ok(value), _) => Some(value),
+        (Err(_), Value(default)) => Some(default.to_owned()),
+        (Err(_), Unset | Required) => None,
+    }
+}
+
+/// Checks all the settings at once and reports every problem found.
+pub fn validate() -> anyhow::Result<()> {
+    validate_file_values(&get_file_values())
+}
+
+/// Checks the settings as if the configuration file had the given values, without installing them:
+/// the settings are read concurrently and an invalid value must never become visible.
+pub(super) fn validate_file_values(file_values: &HashMap<String, String>) -> anyhow::Result<()> {
+    let get_value = |env: &str| get_value_with(file_values, env);
+    let get_effective_value = |env: &str| effective_value(env, get_value(env));
+    let mut errors: Vec<String> = SETTINGS
+        .iter()
+        .filter_map(|setting| {
+            let value = match (get_value(setting.env), setting.default) {
+                (Ok(value), _) => value,
+                (Err(_), Required) => return Some(format!("{} is required", setting.path())),
+                (Err(_), Value(_) | Unset) => return None,
+            };
+            setting.kind.check(&value).err().map(|e| format!("{}: {e}", setting.path()))
+        })
+        .collect();
+
+    let growth_min = get_effective_value("GROWTH_MIN").and_then(|v| v.parse::<i16>().ok());
+    let growth_max = get_effective_value("GROWTH_MAX").and_then(|v| v.parse::<i16>().ok());
+    if let (Some(min), Some(max)) = (growth_min, growth_max) {
+        if min > max {
+            errors.push(format!("growth.min ({min}) must not be greater than growth.max ({max})"));
+        }
+    }
+
+    let event_chances = ["GROWTH_CRITICAL_CHANCE", "GROWTH_CATASTROPHE_CHANCE", "GROWTH_JACKPOT_CHANCE"]
+        .into_iter()
+        .map(|env| get_effective_value(env).and_then(|v| v.parse::<f64>().ok()))
+        .collect::<Option<Vec<f64>>>();
+    if let Some(chances) = event_chances {
+        let sum: f64 = chances.iter().sum();
+        if sum > 1.0 {
+            errors.push(format!("the sum of the growth event chances ({sum}) must not be greater than 1"));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("invalid configuration:\n- {}", errors.join("\n- ")))
+    }
+}
+
+/// Reads a configuration file and converts it into the values of the environment variables it stands for.
+/// Top-level keys may be the names of the variables themselves.
+pub(super) fn load_file(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("couldn't read {}", path.display()))?;
+    let table = toml::from_str::<toml::Table>(&content)
+        .with_context(|| format!("couldn't parse {}", path.display()))?;
+    let mut values = HashMap::new();
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(section) => {
+                for (name, value) in section {
+                    let (env, value) = convert_section_value(&key, &name, value)
+                        .with_context(|| format!("invalid value of {key}.{name} in {}", path.display()))?;
+                    values.extend(env.into_iter().map(|env| (env, value.clone())));
+                }
+            }
+            value if find(&key).is_some() || key.starts_with(DISABLED_PERK_PREFIX) => {
+                let value = scalar_to_string(value)
+                    .with_context(|| format!("invalid value of {key} in {}", path.display()))?;
+                values.insert(key, value);
+            }
+            _ => return Err(anyhow!("unknown key {key} in {}", path.display())),
+        }
+    }
+    Ok(values)
+}
+
+/// Returns the names of the environment variables and the value for them.
+fn convert_section_value(
+    section: &str,
+    name: &str,
+    value: toml::Value,
+) -> anyhow::Result<(Vec<String>, String)> {
+    match (section, name, value) {
+        (COMMANDS_SECTION | PERKS_SECTION, DISABLED_KEY, toml::Value::Array(items)) => {
+            let prefix = if section == COMMANDS_SECTION {
+                DISABLED_COMMAND_PREFIX
+            } else {
+                DISABLED_PERK_PREFIX
+            };
+            let keys = items
+                .into_iter()
+                .map(|item| match item {
+                    toml::Value::String(s) => Ok(format!("{prefix}{}", s.to_uppercase().replace('-', "_"))),
+                    _ => Err(anyhow!("the items must be strings")),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok((keys, true.to_string()))
+        }
+        (section, name, value) => {
+            let setting = SETTINGS
+                .iter()
+                .find(|setting| setting.section == section && setting.name == name)
+                .ok_or(anyhow!("unknown key"))?;
+            let value = match value {
+                toml::Value::Array(items) => items
+                    .into_iter()
+                    .map(scalar_to_string)
+                    .collect::<anyhow::Result<Vec<_>>>()?
+                    .join(","),
+                value => scalar_to_string(value)?,
+            };
+            Ok((vec![setting.env.to_owned()], value))
+        }
+    }
+}
+
+fn scalar_to_string(value: toml::Value) -> anyhow::Result<String> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(anyhow!("the value must be a scalar")),
+    }
+}
+
+/// Renders the effective configuration in the format of the configuration file,
+/// noting where every value comes from.
+pub fn render_effective_config(file_values: &HashMap<String, String>) -> String {
+    let mut output = String::new();
+    let mut current_section = "";
+    for setting in SETTINGS {
+        if setting.section != current_section {
+            if !current_section.is_empty() {
+                output.push('\n');
+            }
+            let _ = writeln!(output, "[{}]", setting.section);
+            current_section = setting.section;
+        }
+        let source = if std::env::var_os(setting.env).is_some() {
+            "environment"
+        } else if file_values.contains_key(setting.env) {
+            "file"
+        } else {
+            "default"
+        };
+        let line = match (get_value(setting.env), setting.default, setting.kind) {
+            (Ok(_), _, Kind::Secret) => format!("{} = \"{HIDDEN_VALUE}\"", setting.name),
+            (Ok(value), _, kind) => format!("{} = {}", setting.name, kind.render(&value)),
+            (Err(_), Value(default), kind) => format!("{} = {}", setting.name, kind.render(default)),
+            (Err(_), Unset | Required, _) => format!("# {} is not set", setting.name),
+        };
+        let _ = writeln!(output, "{line}  # {}, {source}", setting.env);
+    }
+
+    let keys: BTreeSet<String> = std::env::vars()
+        .map(|(key, _)| key)
+        .chain(file_values.keys().cloned())
+        .collect();
+    // commands are disabled by the mere presence of a variable, while perks require a true value
+    let disabled_commands = keys
+        .iter()
+        .filter_map(|key| key.strip_prefix(DISABLED_COMMAND_PREFIX))
+        .map(str::to_lowercase);
+    let disabled_perks = keys
+        .iter()
+        .filter(|key| !key.starts_with(DISABLED_COMMAND_PREFIX))
+        .filter(|key| get_value(key).is_ok_and(|value| value == "true"))
+        .filter_map(|key| key.strip_prefix(DISABLED_PERK_PREFIX))
+        .map(|name| name.to_lowercase().replace('_', "-"));
+    for (section, names) in [
+        (COMMANDS_SECTION, disabled_commands.collect::<Vec<_>>()),
+        (PERKS_SECTION, disabled_perks.collect()),
+    ] {
+        let list = names
+            .iter()
+            .map(|name| format!("{name:?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = writeln!(output, "\n[{section}]\n{DISABLED_KEY} = [{list}]");
+    }
+    output
+}
+
+impl Setting {
+    fn path(&self) -> String {
+        format!("{}.{} ({})", self.section, self.name, self.env)
+    }
+}
+
+impl Kind {
+    fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            Kind::Bool => value
+                .parse::<bool>()
+                .map(|_| ())
+                .map_err(|_| format!("'{value}' is not a boolean")),
+            Kind::Int { min, max } => match value.parse::<i64>() {
+                Ok(v) if (*min..=*max).contains(&v) => Ok(()),
+                Ok(v) => Err(format!("{v} is not within {min}..{max}")),
+                Err(_) => Err(format!("'{value}' is not an integer")),
+            },
+            Kind::Ratio => match value.parse::<f64>() {
+                Ok(v) if (0.0..=1.0).contains(&v) => Ok(()),
+                Ok(v) => Err(format!("{v} is not within 0..1")),
+                Err(_) => Err(format!("'{value}' is not a number")),
+            },
+            Kind::Text | Kind::Secret => Ok(()),
+            Kind::Custom(check) => check(value),
+        }
+    }
+
+    fn render(&self, value: &str) -> String {
+        match self {
+            Kind::Bool | Kind::Int { .. } | Kind::Ratio => value.to_owned(),
+            Kind::Text | Kind::Secret | Kind::Custom(_) => format!("{value:?}"),
+        }
+    }
+}
+
+fn validate_dod_selection_mode(value: &str) -> Result<(), String> {
+    DickOfDaySelectionMode::from_str(value)
+        .map(|_| ())
+        .map_err(|_| format!("'{value}' is not one of RANDOM, EXCLUSION, WEIGHTS"))
+}
+
+fn validate_growth_distribution(value: &str) -> Result<(), String> {
+    GrowthDistributionKind::from_str(value)
+        .map(|_| ())
+        .map_err(|_| format!("'{value}' is not one of UNIFORM, NORMAL, EXPONENTIAL, DIMINISHING"))
+}
+
+fn validate_fire_rounding(value: &str) -> Result<(), String> {
+    FireRounding::from_str(value)
+        .map(|_| ())
+        .map_err(|_| format!("'{value}' is not one of DOWN, FIRST, LARGEST"))
+}
+
+fn validate_user_ids(value: &str) -> Result<(), String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .find(|id| id.parse::<u64>().is_err())
+        .map_or(Ok(()), |id| Err(format!("'{id}' is not a user ID")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load_file, validate_file_values, Kind};
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn test_load_file() {
+        let path = std::env::temp_dir().join("dickgrowerbot-test-schema.toml");
+        let content = r#"
+            GROWTH_DOD_BONUS_MAX = 3
+
+            [growth]
+            min = -3
+            shrink_ratio = 0.4
+
+            [dod]
+            selection_mode = "WEIGHTS"
+
+            [bot]
+            operators = [1, 2]
+
+            [commands]
+            disabled = ["stats"]
+
+            [perks]
+            disabled = ["help-pussies"]
+        "#;
+        fs::write(&path, content).expect("couldn't write the test config");
+        let values = load_file(&path).expect("couldn't load the test config");
+        let get = |key: &str| values.get(key).map(String::as_str);
+        assert_eq!(get("GROWTH_DOD_BONUS_MAX"), Some("3"));
+        assert_eq!(get("GROWTH_MIN"), Some("-3"));
+        assert_eq!(get("GROW_SHRINK_RATIO"), Some("0.4"));
+        assert_eq!(get("DOD_SELECTION_MODE"), Some("WEIGHTS"));
+        assert_eq!(get("BOT_OPERATORS"), Some("1,2"));
+        assert_eq!(get("DISABLE_CMD_STATS"), Some("true"));
+        assert_eq!(get("DISABLE_HELP_PUSSIES"), Some("true"));
+
+        for invalid in ["[growth]\nmaximum = 10\n", "UNKNOWN = 1\n", "[growth]\nmin = { x = 1 }\n"] {
+            fs::write(&path, invalid).expect("couldn't write the test config");
+            assert!(load_file(&path).is_err(), "{invalid} must be rejected");
+        }
+
+        fs::remove_file(&path).expect("couldn't remove the test config");
+    }
+
+    #[test]
+    fn test_validate_file_values() {
+        let errors = |pairs: &[(&str, &str)]| {
+            let values: HashMap<String, String> = pairs.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            validate_file_values(&values).err().map(|e| e.to_string()).unwrap_or_default()
+        };
+        assert!(errors(&[("GROWTH_MIN", "10"), ("GROWTH_MAX", "5")]).contains("growth.min (10) must not be greater than growth.max (5)"));
+        assert!(errors(&[("GROW_SHRINK_RATIO", "1.5")]).contains("growth.shrink_ratio"));
+        assert!(errors(&[("GROWTH_DOD_BONUS_MAX", "0")]).contains("growth.dod_bonus_max"));
+        assert!(errors(&[("GROWTH_CRITICAL_CHANCE", "0.6"), ("GROWTH_CATASTROPHE_CHANCE", "0.6")]).contains("growth event chances"));
+        assert!(!errors(&[("GROWTH_MIN", "-5"), ("GROWTH_MAX", "5"), ("GROW_SHRINK_RATIO", "0.4")]).contains("growth."));
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(Kind::Bool.check("true").is_ok());
+        assert!(Kind::Bool.check("yes").is_err());
+        assert!(Kind::Int { min: 1, max: 10 }.check("10").is_ok());
+        assert!(Kind::Int { min: 1, max: 10 }.check("0").is_err());
+        assert!(Kind::Ratio.check("0.5").is_ok());
+        assert!(Kind::Ratio.check("1.5").is_err());
+        assert!(Kind::Ratio.check("-0.1").is_err());
+    }
+}
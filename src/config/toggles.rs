@@ -1,3 +1,4 @@
+use crate::config::env::is_value_set;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
@@ -69,6 +70,6 @@ impl CachedEnvToggles {
     }
 
     fn enabled_in_env(key: &str) -> bool {
-        std::env::var_os(format!("DISABLE_CMD_{}", key.to_uppercase())).is_none()
+        !is_value_set(&format!("DISABLE_CMD_{}", key.to_uppercase()))
     }
 }
@@ -2,6 +2,8 @@ use derive_more::{Constructor, From};
 use once_cell::sync::Lazy;
 use std::borrow::ToOwned;
 use std::ops::Deref;
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter, EnumString, IntoStaticStr};
 use teloxide::types::User;
 
 static DEFAULT: Lazy<LanguageCode> = Lazy::new(|| LanguageCode("en".to_string()));
@@ -10,12 +12,17 @@ static RU_SPEAKING_LOCALES: [&str; 3] = ["ru", "uk", "be"];
 #[derive(Clone, Debug, Constructor, From)]
 pub struct LanguageCode(String);
 
-#[derive(Hash, Copy, Clone, Eq, PartialEq, sqlx::Type)]
+/// Every locale from the `locales` directory must be listed here
+/// and in the `language_code` type of the database.
+#[derive(Hash, Copy, Clone, Eq, PartialEq, sqlx::Type, EnumIter, AsRefStr, IntoStaticStr, EnumString)]
 #[sqlx(type_name = "language_code", rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
 #[cfg_attr(test, derive(Debug))]
 pub enum SupportedLanguage {
     EN,
     RU,
+    FA,
+    ZH,
 }
 
 impl LanguageCode {
@@ -35,13 +42,11 @@ impl LanguageCode {
 
     pub fn to_supported_language(&self) -> SupportedLanguage {
         let code = self.to_ascii_lowercase();
-        if code.len() < 2 {
-            SupportedLanguage::EN
-        } else if RU_SPEAKING_LOCALES.contains(&&code[..2]) {
-            SupportedLanguage::RU
-        } else {
-            SupportedLanguage::EN
-        }
+        let primary_subtag = code.split(['-', '_']).next().unwrap_or_default();
+        SupportedLanguage::iter()
+            .find(|lang| lang.as_ref() == primary_subtag)
+            .or_else(|| RU_SPEAKING_LOCALES.contains(&primary_subtag).then_some(SupportedLanguage::RU))
+            .unwrap_or(SupportedLanguage::EN)
     }
 
     fn get_language_code_or_log_if_missing(user: &User) -> Option<&String> {
@@ -51,10 +56,12 @@ impl LanguageCode {
         })
     }
 
+    /// Negotiates the locale at once, so that all translations are looked up in one of the supported locales.
     fn from_maybe_string(maybe_string: Option<&String>) -> Self {
         maybe_string
             .map(ToOwned::to_owned)
             .map(Self)
+            .map(|code| code.to_supported_language().into())
             .unwrap_or_else(|| DEFAULT.clone())
     }
 }
@@ -67,6 +74,12 @@ impl Deref for LanguageCode {
     }
 }
 
+impl From<SupportedLanguage> for LanguageCode {
+    fn from(value: SupportedLanguage) -> Self {
+        Self(value.as_ref().to_owned())
+    }
+}
+
 impl From<&User> for LanguageCode {
     fn from(value: &User) -> Self {
         Self::from_user(value)
@@ -82,7 +95,7 @@ impl From<Option<&User>> for LanguageCode {
 #[cfg(test)]
 mod test_from_maybe_string {
     use crate::domain::LanguageCode;
-    use crate::domain::SupportedLanguage::{EN, RU};
+    use crate::domain::SupportedLanguage::{EN, FA, RU, ZH};
 
     #[test]
     fn success() {
@@ -95,7 +108,8 @@ mod test_from_maybe_string {
             "EN", "en", "En", "eN", "en-US", "EN-us", "eN-Us", "En-uS", "c", "C", "POSIX",
         ]
         .map(|code| (code, EN));
-        let cases = ru.into_iter().chain(en);
+        let others = [("fa", FA), ("fa-IR", FA), ("zh", ZH), ("zh-hans", ZH), ("ZH_tw", ZH), ("uk", RU), ("de", EN)];
+        let cases = ru.into_iter().chain(en).chain(others);
 
         for (case, expected) in cases {
             let value = case.to_string();
@@ -104,7 +118,8 @@ mod test_from_maybe_string {
                 result.to_supported_language(),
                 expected,
                 "Case: {case}, result: {result:?}"
-            )
+            );
+            assert_eq!(result.as_str(), expected.as_ref())
         }
     }
 
@@ -116,3 +131,63 @@ mod test_from_maybe_string {
         }
     }
 }
+
+#[cfg(test)]
+mod test_translations {
+    use super::SupportedLanguage;
+    use std::collections::BTreeSet;
+    use std::fs;
+    use strum::IntoEnumIterator;
+
+    /// Collects the flattened keys of all leaf values from a locale file.
+    /// It's enough for our files consisting of nested maps with quoted strings only.
+    fn read_keys(lang: SupportedLanguage) -> BTreeSet<String> {
+        let path = format!("{}/locales/{}.yml", env!("CARGO_MANIFEST_DIR"), lang.as_ref());
+        let content = fs::read_to_string(&path).unwrap_or_else(|e| panic!("couldn't read {path}: {e}"));
+        let mut stack: Vec<(usize, String)> = Vec::new();
+        let mut keys = BTreeSet::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let indent = line.len() - line.trim_start().len();
+            let Some((key, value)) = line.trim().split_once(':') else {
+                continue
+            };
+            while stack.last().is_some_and(|(level, _)| *level >= indent) {
+                stack.pop();
+            }
+            stack.push((indent, key.to_owned()));
+            if !value.trim().is_empty() {
+                let path: Vec<&str> = stack.iter().map(|(_, key)| key.as_str()).collect();
+                keys.insert(path.join("."));
+            }
+        }
+        keys
+    }
+
+    #[test]
+    fn all_locales_are_supported() {
+        let locales = rust_i18n::available_locales!();
+        let supported: Vec<&str> = SupportedLanguage::iter().map(|lang| lang.into()).collect();
+        for locale in &locales {
+            assert!(supported.contains(locale), "{locale} is not in SupportedLanguage");
+        }
+        for lang in supported {
+            assert!(locales.contains(&lang), "there is no locale file for {lang}");
+        }
+    }
+
+    #[test]
+    fn no_missing_translations() {
+        let reference = read_keys(SupportedLanguage::EN);
+        let missing: Vec<String> = SupportedLanguage::iter()
+            .flat_map(|lang| {
+                let keys = read_keys(lang);
+                reference
+                    .iter()
+                    .filter(|key| !keys.contains(*key))
+                    .map(|key| format!("{}: {key}", lang.as_ref()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert!(missing.is_empty(), "missing translations:\n{}", missing.join("\n"));
+    }
+}
@@ -5,7 +5,8 @@ use unicode_general_category::GeneralCategory::Format;
 
 const LTR_MARK: char = '\u{200E}';
 
-#[derive(Debug, Clone, Constructor, From, Eq, PartialEq)]
+#[derive(Debug, Clone, Constructor, From, Eq, PartialEq, sqlx::Type)]
+#[sqlx(transparent)]
 pub struct Username(String);
 
 impl Username {
@@ -1,97 +1,514 @@
-use crate::domain::LanguageCode;
-use crate::handlers::{reply_html, HandlerResult};
-use crate::reply_html;
-use crate::repo;
+use crate::domain::{LanguageCode, SupportedLanguage};
+use crate::handlers::utils::callbacks;
+use crate::handlers::utils;
+use crate::handlers::utils::{ChatAdminsCache, Target};
+use crate::handlers::utils::callbacks::{
+    CallbackDataWithPrefix, EditMessageReqParamsKind, InvalidCallbackData,
+    InvalidCallbackDataBuilder,
+};
+use crate::handlers::{
+    checks, get_topic_id, get_topic_link, reply_html, send_error_callback_answer, CallbackButton,
+    HandlerImplResult, HandlerResult,
+};
+use crate::repo::{AdminAction, ChatIdPartiality};
+use crate::{check_invoked_by_owner_and_get_answer_params, config, reply_html, repo};
+use derive_more::Display;
 use rust_i18n::t;
+use std::str::FromStr;
+use std::time::Duration;
+use strum::IntoEnumIterator;
 use teloxide::macros::BotCommands;
 use teloxide::requests::Requester;
-use teloxide::types::{Message, UserId};
+use teloxide::types::{CallbackQuery, Message, ReplyMarkup, UserId};
+use teloxide::utils::html;
 use teloxide::Bot;
 
+const LANGUAGE_RESET_KEYWORD: &str = "auto";
+const TIMEZONE_RESET_KEYWORD: &str = "reset";
+const GLOBAL_DELETION_KEYWORD: &str = "all";
+const GAME_TOPIC_RESET_KEYWORD: &str = "reset";
+
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
 pub enum AdminCommands {
     #[command(description = "deluser")]
     Deluser,
+    #[command(description = "language")]
+    Language(String),
+    #[command(description = "timezone")]
+    Timezone(String),
+    #[command(description = "ban")]
+    Ban,
+    #[command(description = "unban")]
+    Unban,
+    #[command(description = "setlength")]
+    SetLength,
+    #[command(description = "resetchat")]
+    ResetChat,
+    #[command(description = "freeze")]
+    Freeze,
+    #[command(description = "disable")]
+    Disable(String),
+    #[command(description = "enable")]
+    Enable(String),
+    #[command(description = "gametopic")]
+    GameTopic(String),
 }
 
 pub async fn admin_cmd_handler(
     bot: Bot,
     msg: Message,
-    _cmd: AdminCommands,
+    cmd: AdminCommands,
     repos: repo::Repositories,
+    config: config::AppConfig,
+    admins: ChatAdminsCache,
 ) -> HandlerResult {
     let lang_code = LanguageCode::from_maybe_user(msg.from.as_ref());
 
-    let is_admin = {
-        let admin_ids = bot
-            .get_chat_administrators(msg.chat.id)
-            .await?
-            .into_iter()
-            .map(|m| m.user.id)
-            .collect::<Vec<_>>();
-        let from_id = msg
-            .from
-            .as_ref()
-            .map(|u| u.id)
-            .ok_or_else(|| anyhow::anyhow!("not from a user"))?;
-        admin_ids.into_iter().any(|id| id == from_id)
-    };
-    if !is_admin {
-        let answer = t!("commands.deluser.errors.not_admin", locale = &lang_code).to_string();
+    let Some(admin) = admins.authorize(&bot, &msg, &config).await? else {
+        let answer = t!("errors.not_admin", locale = &lang_code).to_string();
         reply_html!(bot, msg, answer);
         return Ok(());
+    };
+    let ctx = AdminContext {
+        repos: &repos,
+        msg: &msg,
+        lang_code: &lang_code,
+        admin,
+    };
+
+    let answer = match cmd {
+        AdminCommands::Deluser => deluser_impl(ctx, &config).await?,
+        AdminCommands::Language(arg) => language_impl(ctx, arg.trim()).await?,
+        AdminCommands::Timezone(arg) => timezone_impl(ctx, arg.trim()).await?,
+        AdminCommands::Ban => ban_impl(ctx).await?,
+        AdminCommands::Unban => unban_impl(ctx).await?,
+        AdminCommands::SetLength => set_length_impl(ctx).await?,
+        AdminCommands::Freeze => freeze_impl(ctx).await?,
+        AdminCommands::Disable(arg) => disable_command_impl(ctx, arg.trim()).await?,
+        AdminCommands::Enable(arg) => enable_command_impl(ctx, arg.trim()).await?,
+        AdminCommands::GameTopic(arg) => game_topic_impl(ctx, arg.trim()).await?,
+        AdminCommands::ResetChat => {
+            let result = reset_chat_impl(admin, &lang_code);
+            let mut request = reply_html(bot, &msg, result.text());
+            request.reply_markup = result.keyboard().map(ReplyMarkup::InlineKeyboard);
+            request.await?;
+            return Ok(());
+        }
+    };
+    reply_html!(bot, msg, answer);
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+struct AdminContext<'a> {
+    repos: &'a repo::Repositories,
+    msg: &'a Message,
+    lang_code: &'a LanguageCode,
+    admin: UserId,
+}
+
+impl AdminContext<'_> {
+    fn chat_id(&self) -> ChatIdPartiality {
+        self.msg.chat.id.into()
+    }
+
+    async fn log(&self, action: AdminAction, target: Option<UserId>, details: Option<String>) {
+        let _ = self
+            .repos
+            .moderation
+            .log_action(&self.chat_id(), self.admin, action, target, details)
+            .await
+            .inspect_err(|e| log::error!("couldn't log the admin action {action}: {e}"));
+    }
+}
+
+/// The target of a command is the author of the replied message, a mentioned user or a user ID passed as
+/// the first argument. The rest of the arguments are returned too. An error message is returned instead
+/// if the target is mentioned by an unknown username.
+async fn parse_target<'a>(ctx: AdminContext<'a>) -> anyhow::Result<Result<(Option<UserId>, Vec<&'a str>), String>> {
+    let (target, args) = utils::resolve_target(ctx.repos, ctx.msg).await?;
+    let target = match target {
+        Target::User(uid) => Some(uid),
+        Target::Missing => None,
+        Target::UnknownUsername(username) => return Ok(Err(
+            t!("errors.username_not_found", locale = ctx.lang_code, username = html::escape(&username)).to_string()
+        )),
+    };
+    Ok(Ok((target, args)))
+}
+
+/// Returns the name of the target user or an error message if the user is unknown.
+async fn get_target_name(ctx: AdminContext<'_>, target: UserId) -> anyhow::Result<Result<String, String>> {
+    let name = ctx.repos.users.get(target).await?.map(|u| u.name.escaped()).ok_or_else(|| {
+        t!("errors.user_not_found", locale = ctx.lang_code, uid = target.0).to_string()
+    });
+    Ok(name)
+}
+
+/// Deletes the user from the current chat. Only operators of the bot may delete a user from all chats.
+async fn deluser_impl(ctx: AdminContext<'_>, config: &config::AppConfig) -> anyhow::Result<String> {
+    let lang_code = ctx.lang_code;
+    let (target_uid, args) = match parse_target(ctx).await? {
+        Ok((Some(uid), args)) => (uid, args),
+        Ok((None, _)) => return Ok(t!("commands.deluser.errors.usage", locale = lang_code,
+            all = GLOBAL_DELETION_KEYWORD).to_string()),
+        Err(err_text) => return Ok(err_text),
+    };
+    let global = args.iter().any(|arg| arg.eq_ignore_ascii_case(GLOBAL_DELETION_KEYWORD));
+    if global && !config.is_operator(ctx.admin) {
+        return Ok(t!("commands.deluser.errors.not_operator", locale = lang_code).to_string());
     }
 
-    let target_uid: Option<UserId> = if let Some(reply) = msg.reply_to_message() {
-        reply.from.as_ref().map(|u| u.id)
+    let user_name = match get_target_name(ctx, target_uid).await? {
+        Ok(name) => name,
+        Err(err_text) => return Ok(err_text),
+    };
+
+    let answer = if global {
+        ctx.repos.users.delete_everything(target_uid).await?;
+        ctx.log(AdminAction::DeleteUser, Some(target_uid), Some(GLOBAL_DELETION_KEYWORD.to_owned())).await;
+        t!("commands.deluser.success_global", locale = lang_code, name = user_name)
+    } else if ctx.repos.users.delete_from_chat(target_uid, &ctx.chat_id().kind()).await? > 0 {
+        ctx.log(AdminAction::DeleteUser, Some(target_uid), None).await;
+        t!("commands.deluser.success", locale = lang_code, name = user_name)
     } else {
-        msg.text().and_then(|t| {
-            let mut parts = t.split_whitespace();
-            let _cmd = parts.next()?;
-            let id_str = parts.next()?;
-            id_str.parse::<u64>().ok().map(UserId)
-        })
-    };
-
-    let target_uid = match target_uid {
-        Some(uid) => uid,
-        None => {
-            let answer = t!("commands.deluser.errors.usage", locale = &lang_code).to_string();
-            reply_html!(bot, msg, answer);
-            return Ok(());
+        t!("commands.deluser.errors.not_in_chat", locale = lang_code, name = user_name)
+    };
+    Ok(answer.to_string())
+}
+
+async fn language_impl(ctx: AdminContext<'_>, arg: &str) -> anyhow::Result<String> {
+    let (repos, lang_code) = (ctx.repos, ctx.lang_code);
+    let chat_id = ctx.chat_id();
+    let available = SupportedLanguage::iter()
+        .map(|lang| format!("<code>{}</code>", lang.as_ref()))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let answer = if arg.is_empty() {
+        let status = match repos.chats.get_language(&chat_id.kind()).await? {
+            Some(lang) => t!("commands.language.current", locale = lang_code, language = lang.as_ref()),
+            None => t!("commands.language.not_set", locale = lang_code),
+        };
+        let usage = t!("commands.language.usage", locale = lang_code,
+            available = available, reset = LANGUAGE_RESET_KEYWORD);
+        format!("{status}\n\n{usage}")
+    } else if arg.eq_ignore_ascii_case(LANGUAGE_RESET_KEYWORD) {
+        repos.chats.set_language(&chat_id, None).await?;
+        ctx.log(AdminAction::Language, None, Some(LANGUAGE_RESET_KEYWORD.to_owned())).await;
+        t!("commands.language.reset", locale = lang_code).to_string()
+    } else if let Ok(lang) = SupportedLanguage::from_str(arg) {
+        repos.chats.set_language(&chat_id, Some(lang)).await?;
+        ctx.log(AdminAction::Language, None, Some(lang.as_ref().to_owned())).await;
+        t!("commands.language.success", locale = lang.as_ref(), language = lang.as_ref()).to_string()
+    } else {
+        t!("commands.language.errors.unknown", locale = lang_code,
+            language = html::escape(arg), available = available).to_string()
+    };
+    Ok(answer)
+}
+
+async fn timezone_impl(ctx: AdminContext<'_>, arg: &str) -> anyhow::Result<String> {
+    let (repos, lang_code) = (ctx.repos, ctx.lang_code);
+    let chat_id = ctx.chat_id();
+    let answer = if arg.is_empty() {
+        let status = match repos.chats.get_timezone(&chat_id.kind()).await? {
+            Some(timezone) => t!("commands.timezone.current", locale = lang_code, timezone = timezone),
+            None => t!("commands.timezone.not_set", locale = lang_code),
+        };
+        let usage = t!("commands.timezone.usage", locale = lang_code, reset = TIMEZONE_RESET_KEYWORD);
+        format!("{status}\n\n{usage}")
+    } else if arg.eq_ignore_ascii_case(TIMEZONE_RESET_KEYWORD) {
+        repos.chats.set_timezone(&chat_id, None).await?;
+        ctx.log(AdminAction::Timezone, None, Some(TIMEZONE_RESET_KEYWORD.to_owned())).await;
+        t!("commands.timezone.reset", locale = lang_code).to_string()
+    } else if let Some(timezone) = repos.chats.find_timezone(arg).await? {
+        repos.chats.set_timezone(&chat_id, Some(&timezone)).await?;
+        ctx.log(AdminAction::Timezone, None, Some(timezone.clone())).await;
+        t!("commands.timezone.success", locale = lang_code, timezone = timezone).to_string()
+    } else {
+        t!("commands.timezone.errors.unknown", locale = lang_code, timezone = html::escape(arg)).to_string()
+    };
+    Ok(answer)
+}
+
+async fn ban_impl(ctx: AdminContext<'_>) -> anyhow::Result<String> {
+    let lang_code = ctx.lang_code;
+    let target = match parse_target(ctx).await? {
+        Ok((Some(uid), _)) => uid,
+        Ok((None, _)) => return Ok(t!("commands.ban.usage", locale = lang_code).to_string()),
+        Err(err_text) => return Ok(err_text),
+    };
+    let name = match get_target_name(ctx, target).await? {
+        Ok(name) => name,
+        Err(err_text) => return Ok(err_text),
+    };
+    let answer = if ctx.repos.moderation.ban(&ctx.chat_id(), target, ctx.admin).await? {
+        ctx.log(AdminAction::Ban, Some(target), None).await;
+        t!("commands.ban.success", locale = lang_code, name = name)
+    } else {
+        t!("commands.ban.already", locale = lang_code, name = name)
+    };
+    Ok(answer.to_string())
+}
+
+async fn unban_impl(ctx: AdminContext<'_>) -> anyhow::Result<String> {
+    let lang_code = ctx.lang_code;
+    let target = match parse_target(ctx).await? {
+        Ok((Some(uid), _)) => uid,
+        Ok((None, _)) => return Ok(t!("commands.unban.usage", locale = lang_code).to_string()),
+        Err(err_text) => return Ok(err_text),
+    };
+    let name = match get_target_name(ctx, target).await? {
+        Ok(name) => name,
+        Err(err_text) => return Ok(err_text),
+    };
+    let answer = if ctx.repos.moderation.unban(&ctx.chat_id().kind(), target).await? {
+        ctx.log(AdminAction::Unban, Some(target), None).await;
+        t!("commands.unban.success", locale = lang_code, name = name)
+    } else {
+        t!("commands.unban.not_banned", locale = lang_code, name = name)
+    };
+    Ok(answer.to_string())
+}
+
+async fn set_length_impl(ctx: AdminContext<'_>) -> anyhow::Result<String> {
+    let lang_code = ctx.lang_code;
+    let (target, args) = match parse_target(ctx).await? {
+        Ok(parsed) => parsed,
+        Err(err_text) => return Ok(err_text),
+    };
+    let (target, length) = match (target, args.first().and_then(|arg| arg.parse::<i32>().ok())) {
+        (Some(target), Some(length)) => (target, length),
+        _ => return Ok(t!("commands.setlength.usage", locale = lang_code).to_string()),
+    };
+    let name = match get_target_name(ctx, target).await? {
+        Ok(name) => name,
+        Err(err_text) => return Ok(err_text),
+    };
+    let answer = match ctx.repos.dicks.set_length(&ctx.chat_id().kind(), target, length).await? {
+        Some(res) => {
+            ctx.log(AdminAction::SetLength, Some(target), Some(length.to_string())).await;
+            t!("commands.setlength.success", locale = lang_code, name = name, length = res.new_length)
         }
+        None => t!("commands.setlength.no_dick", locale = lang_code, name = name),
     };
+    Ok(answer.to_string())
+}
 
-    let user = repos.users.get(target_uid).await?;
-    if user.is_none() {
-        let answer = t!(
-            "commands.deluser.errors.not_found",
-            locale = &lang_code,
-            uid = target_uid.0
-        )
-        .to_string();
-        reply_html!(bot, msg, answer);
-        return Ok(());
+async fn freeze_impl(ctx: AdminContext<'_>) -> anyhow::Result<String> {
+    let chat_id = ctx.chat_id();
+    let frozen = !ctx.repos.moderation.is_frozen(&chat_id.kind()).await?;
+    ctx.repos.moderation.set_frozen(&chat_id, frozen).await?;
+    let (action, key) = if frozen {
+        (AdminAction::Freeze, "commands.freeze.frozen")
+    } else {
+        (AdminAction::Unfreeze, "commands.freeze.unfrozen")
+    };
+    ctx.log(action, None, None).await;
+    Ok(t!(key, locale = ctx.lang_code).to_string())
+}
+
+/// Without an argument, shows the commands disabled in the chat and the ones which may be disabled.
+async fn disable_command_impl(ctx: AdminContext<'_>, arg: &str) -> anyhow::Result<String> {
+    let lang_code = ctx.lang_code;
+    let Some(command) = parse_toggleable_command(arg) else {
+        let disabled = ctx.repos.moderation.get_disabled_commands(&ctx.chat_id().kind()).await?;
+        let disabled = if disabled.is_empty() {
+            t!("commands.disable.none", locale = lang_code).to_string()
+        } else {
+            format_commands(disabled.iter().map(String::as_str))
+        };
+        return Ok(t!("commands.disable.usage", locale = lang_code, disabled = disabled,
+            available = format_commands(checks::get_toggleable_commands().into_iter())).to_string());
+    };
+    let answer = if ctx.repos.moderation.disable_command(&ctx.chat_id(), command, ctx.admin).await? {
+        ctx.log(AdminAction::DisableCommand, None, Some(command.to_owned())).await;
+        t!("commands.disable.success", locale = lang_code, command = command)
+    } else {
+        t!("commands.disable.already", locale = lang_code, command = command)
+    };
+    Ok(answer.to_string())
+}
+
+async fn enable_command_impl(ctx: AdminContext<'_>, arg: &str) -> anyhow::Result<String> {
+    let lang_code = ctx.lang_code;
+    let Some(command) = parse_toggleable_command(arg) else {
+        return Ok(t!("commands.enable.usage", locale = lang_code).to_string());
+    };
+    let answer = if ctx.repos.moderation.enable_command(&ctx.chat_id().kind(), command).await? {
+        ctx.log(AdminAction::EnableCommand, None, Some(command.to_owned())).await;
+        t!("commands.enable.success", locale = lang_code, command = command)
+    } else {
+        t!("commands.enable.not_disabled", locale = lang_code, command = command)
+    };
+    Ok(answer.to_string())
+}
+
+/// Restricts the game to the topic the command is sent into. Without a topic, shows the current one.
+async fn game_topic_impl(ctx: AdminContext<'_>, arg: &str) -> anyhow::Result<String> {
+    let (repos, lang_code) = (ctx.repos, ctx.lang_code);
+    let chat_id = ctx.chat_id();
+    let answer = if arg.eq_ignore_ascii_case(GAME_TOPIC_RESET_KEYWORD) {
+        repos.moderation.set_game_topic(&chat_id, None).await?;
+        ctx.log(AdminAction::GameTopic, None, Some(GAME_TOPIC_RESET_KEYWORD.to_owned())).await;
+        t!("commands.gametopic.reset", locale = lang_code).to_string()
+    } else if let Some(thread_id) = get_topic_id(ctx.msg) {
+        let topic = thread_id.0 .0;
+        repos.moderation.set_game_topic(&chat_id, Some(topic)).await?;
+        ctx.log(AdminAction::GameTopic, None, Some(topic.to_string())).await;
+        t!("commands.gametopic.success", locale = lang_code).to_string()
+    } else {
+        let status = match repos.moderation.get_game_topic(&chat_id.kind()).await? {
+            Some(topic) => t!("commands.gametopic.current", locale = lang_code,
+                link = get_topic_link(ctx.msg.chat.id, topic)),
+            None => t!("commands.gametopic.not_set", locale = lang_code),
+        };
+        let usage = t!("commands.gametopic.usage", locale = lang_code, reset = GAME_TOPIC_RESET_KEYWORD);
+        format!("{status}\n\n{usage}")
+    };
+    Ok(answer)
+}
+
+fn parse_toggleable_command(arg: &str) -> Option<&'static str> {
+    let arg = arg.trim_start_matches('/');
+    checks::get_toggleable_commands()
+        .into_iter()
+        .find(|cmd| cmd.eq_ignore_ascii_case(arg))
+}
+
+fn format_commands<'a>(commands: impl Iterator<Item = &'a str>) -> String {
+    commands
+        .map(|cmd| format!("<code>{cmd}</code>"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn reset_chat_impl(admin: UserId, lang_code: &LanguageCode) -> HandlerImplResult<ResetChatCallbackData> {
+    let btn_confirm = CallbackButton::new(
+        t!("commands.resetchat.confirmation.buttons.confirm", locale = lang_code).to_string(),
+        ResetChatCallbackData {
+            uid: admin,
+            action: ResetChatCallbackAction::Confirmed,
+        },
+    );
+    let btn_cancel = CallbackButton::new(
+        t!("commands.resetchat.confirmation.buttons.cancel", locale = lang_code).to_string(),
+        ResetChatCallbackData {
+            uid: admin,
+            action: ResetChatCallbackAction::Cancelled,
+        },
+    );
+    HandlerImplResult::WithKeyboard {
+        text: t!("commands.resetchat.confirmation.text", locale = lang_code).to_string(),
+        buttons: vec![btn_confirm, btn_cancel],
     }
-    let user_name = user.map(|u| u.name.value_ref().to_string()).unwrap_or("unknown".to_string());
-
-    let affected = repos.users.delete_everything(target_uid).await?;
-    let answer = if affected > 0 {
-        t!(
-            "commands.deluser.success",
-            locale = &lang_code,
-            name = user_name
-        )
-        .to_string()
+}
+
+#[inline]
+pub fn reset_chat_callback_filter(query: CallbackQuery) -> bool {
+    ResetChatCallbackData::check_prefix(query)
+}
+
+pub async fn reset_chat_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    repos: repo::Repositories,
+    admins: ChatAdminsCache,
+) -> HandlerResult {
+    let data = ResetChatCallbackData::parse(&query)?;
+    let (chat_id, message_id) = match callbacks::get_params_for_message_edit(&query)? {
+        EditMessageReqParamsKind::Chat(chat_id, message_id) => (chat_id, message_id),
+        EditMessageReqParamsKind::Inline { .. } => {
+            return send_error_callback_answer(bot, query, "inline.callback.errors.invalid_data").await
+        }
+    };
+    // the rights may be revoked between the command and the confirmation, so the cache is bypassed for this destructive action
+    if !admins.is_admin(&bot, chat_id, query.from.id, Duration::ZERO).await? {
+        return send_error_callback_answer(bot, query, "errors.not_admin").await;
+    }
+    // the real user behind an anonymous administrator is unknown, so any administrator may confirm the reset
+    let owner = if data.uid.is_anonymous() || data.uid.is_channel() {
+        query.from.id
     } else {
-        t!(
-            "commands.deluser.errors.not_found",
-            locale = &lang_code,
-            uid = target_uid.0
-        )
-        .to_string()
+        data.uid
     };
-    reply_html!(bot, msg, answer);
+    let (answer, lang_code) = check_invoked_by_owner_and_get_answer_params!(bot, query, owner);
+
+    let text = match data.action {
+        ResetChatCallbackAction::Confirmed => {
+            let count = repos.moderation.reset_chat(&chat_id.into()).await?;
+            let _ = repos
+                .moderation
+                .log_action(&chat_id.into(), data.uid, AdminAction::ResetChat, None, Some(count.to_string()))
+                .await
+                .inspect_err(|e| log::error!("couldn't log the admin action {}: {e}", AdminAction::ResetChat));
+            t!("commands.resetchat.success", locale = &lang_code, count = count)
+        }
+        ResetChatCallbackAction::Cancelled => t!("commands.resetchat.cancelled", locale = &lang_code),
+    };
+    bot.edit_message_text(chat_id, message_id, text).await?;
+
+    answer.await?;
     Ok(())
 }
+
+#[derive(Display)]
+#[display("{uid}:{action}")]
+pub(crate) struct ResetChatCallbackData {
+    uid: UserId,
+    action: ResetChatCallbackAction,
+}
+
+#[derive(Display)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub(crate) enum ResetChatCallbackAction {
+    #[display("confirmed")]
+    Confirmed,
+    #[display("cancelled")]
+    Cancelled,
+}
+
+impl CallbackDataWithPrefix for ResetChatCallbackData {
+    fn prefix() -> &'static str {
+        "resetchat"
+    }
+}
+
+impl TryFrom<String> for ResetChatCallbackData {
+    type Error = InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.as_str().split(':');
+        let uid = callbacks::parse_part(&mut parts, &err, "uid").map(UserId)?;
+        let action = match parts.next().ok_or_else(|| err.missing_part("action"))? {
+            "confirmed" => ResetChatCallbackAction::Confirmed,
+            "cancelled" => ResetChatCallbackAction::Cancelled,
+            _ => return Err(err.split_err()),
+        };
+        Ok(Self { uid, action })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ResetChatCallbackAction, ResetChatCallbackData};
+    use crate::handlers::utils::callbacks::CallbackDataWithPrefix;
+    use teloxide::types::UserId;
+
+    #[test]
+    fn test_serialize_and_parse() {
+        let uid = UserId(123456);
+        for action in [ResetChatCallbackAction::Confirmed, ResetChatCallbackAction::Cancelled] {
+            let expected = format!("resetchat:{uid}:{action}");
+            let data = ResetChatCallbackData { uid, action };
+            assert_eq!(data.to_data_string(), expected);
+
+            let parsed = ResetChatCallbackData::try_from(expected["resetchat:".len()..].to_owned())
+                .expect("callback data must be parsed successfully");
+            assert_eq!(parsed.uid, uid);
+            assert_eq!(parsed.action, data.action);
+        }
+    }
+}
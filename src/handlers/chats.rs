@@ -0,0 +1,98 @@
+use std::time::Duration;
+use teloxide::types::{ChatId, ChatMemberUpdated, Message, User};
+
+use crate::handlers::HandlerResult;
+use crate::{config, metrics, repo};
+
+const INACTIVE_CHATS_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Telegram sends a service message to both the group and its new supergroup when the former is upgraded.
+pub fn is_chat_migration(msg: Message) -> bool {
+    msg.migrate_to_chat_id().is_some() || msg.migrate_from_chat_id().is_some()
+}
+
+pub async fn chat_migration_handler(msg: Message, repos: repo::Repositories) -> HandlerResult {
+    let (from, to) = match (msg.migrate_to_chat_id(), msg.migrate_from_chat_id()) {
+        (Some(to), _) => (msg.chat.id, *to),
+        (None, Some(from)) => (*from, msg.chat.id),
+        (None, None) => return Ok(()),
+    };
+    // the second message is a no-op since the group is not known by its old ID anymore
+    if !repos.chats.migrate_chat(from, to).await? {
+        log::debug!("the chat {from} is unknown or has been already migrated to {to}");
+    }
+    Ok(())
+}
+
+/// Tracks whether the bot is still a member of a chat: the chat becomes inactive when the bot is kicked or leaves,
+/// and active again when it's added back.
+pub async fn my_chat_member_handler(update: ChatMemberUpdated, repos: repo::Repositories) -> HandlerResult {
+    let chat_id = update.chat.id;
+    let active = update.new_chat_member.is_present();
+    if active == update.old_chat_member.is_present() {
+        return Ok(())
+    }
+    if active {
+        metrics::CHAT_MEMBERSHIP_COUNTER.added.inc();
+    } else {
+        metrics::CHAT_MEMBERSHIP_COUNTER.removed.inc();
+    }
+    // the chat will be created on the first command if the bot is added to a new one
+    if repos.chats.set_active(chat_id, active).await? {
+        log::info!("the bot has been {} the chat {chat_id}", if active { "added to" } else { "removed from" });
+    }
+    Ok(())
+}
+
+/// Users who left a chat are excluded from the top, the elections of the Dick of the Day and the recipients of fire
+/// until they rejoin or grow in the chat again. The bot receives such updates only if it's an administrator of the chat.
+pub async fn chat_member_handler(update: ChatMemberUpdated, repos: repo::Repositories) -> HandlerResult {
+    let present = update.new_chat_member.is_present();
+    if present == update.old_chat_member.is_present() {
+        return Ok(())
+    }
+    set_membership(&repos, update.chat.id, &update.new_chat_member.user, present).await
+}
+
+/// Service messages about joined and left members are a fallback for chats where the bot is not an administrator.
+pub fn is_membership_message(msg: Message) -> bool {
+    msg.left_chat_member().is_some() || msg.new_chat_members().is_some()
+}
+
+pub async fn membership_message_handler(msg: Message, repos: repo::Repositories) -> HandlerResult {
+    if let Some(user) = msg.left_chat_member() {
+        set_membership(&repos, msg.chat.id, user, false).await?;
+    }
+    for user in msg.new_chat_members().unwrap_or_default() {
+        set_membership(&repos, msg.chat.id, user, true).await?;
+    }
+    Ok(())
+}
+
+async fn set_membership(repos: &repo::Repositories, chat_id: ChatId, user: &User, present: bool) -> HandlerResult {
+    if !user.is_bot && repos.users.set_membership(chat_id, user.id, present).await? {
+        log::debug!("the user {} is {} the chat {chat_id}", user.id, if present { "a member of" } else { "not in" });
+    }
+    Ok(())
+}
+
+/// Deletes the data of inactive chats every `INACTIVE_CHATS_RETENTION_DAYS` days after the bot was removed from them.
+/// The setting is taken from the current configuration on every run, so it may be changed without a restart; 0 disables the purging.
+pub fn spawn_inactive_chats_purger(config: config::ReloadableConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(INACTIVE_CHATS_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let snapshot = config.get();
+            let retention_days = snapshot.app_config.inactive_chats_retention_days;
+            if retention_days == 0 {
+                continue
+            }
+            match snapshot.repos.chats.purge_inactive(retention_days).await {
+                Ok(0) => {}
+                Ok(purged) => log::info!("the data of {purged} inactive chat(s) has been purged"),
+                Err(e) => log::error!("couldn't purge inactive chats: {e:?}"),
+            }
+        }
+    });
+}
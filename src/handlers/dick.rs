@@ -1,7 +1,7 @@
 use std::future::IntoFuture;
 
 use anyhow::{anyhow, Context};
-use chrono::{Datelike, Utc};
+use chrono::Utc;
 use futures::future::join;
 use futures::TryFutureExt;
 use rust_i18n::t;
@@ -18,13 +18,14 @@ use rand::rngs::OsRng;
 use rand::Rng;
 
 use crate::domain::{LanguageCode, Username};
-use crate::handlers::utils::{callbacks, page, Incrementor};
-use crate::handlers::{reply_html, utils, HandlerResult};
-use crate::repo::{ChatIdPartiality, TransferKind, UID};
+use crate::handlers::utils::{callbacks, page, Incrementor, Target, WeightedTarget};
+use crate::handlers::{fire_rain, reply_html, send_command_disabled_callback_answer, utils, HandlerResult};
+use crate::repo::{ChatIdKind, ChatIdPartiality, TransferKind, TransferLeg, TransferReceipt, TransferRejection, UID};
 use crate::{config, metrics, repo};
 
 const TOMORROW_SQL_CODE: &str = "GD0E1";
 const CALLBACK_PREFIX_TOP_PAGE: &str = "top:page:";
+const GROW_STREAK_MILESTONES: [i32; 5] = [7, 30, 100, 200, 365];
 
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
@@ -56,7 +57,7 @@ pub async fn dick_cmd_handler(
     match cmd {
         DickCommands::Grow => {
             metrics::CMD_GROW_COUNTER.chat.inc();
-            let answer = grow_impl(&repos, incr, from_refs).await?;
+            let answer = grow_impl(&repos, incr, &config, from_refs).await?;
             reply_html(bot, &msg, answer)
         }
         DickCommands::Top => {
@@ -77,6 +78,10 @@ pub async fn dick_cmd_handler(
             let answer = gift_impl(&repos, &msg, from_refs, &config).await?;
             reply_html(bot, &msg, answer)
         }
+        DickCommands::Fire if fire_rain::is_rain(&msg) => {
+            metrics::CMD_FIRE_COUNTER.chat.inc();
+            return fire_rain::cmd_handler(bot, &msg, from_refs, &repos, &config).await;
+        }
         DickCommands::Fire => {
             metrics::CMD_FIRE_COUNTER.chat.inc();
             let answer = fire_impl(&repos, &msg, from_refs, &config).await?;
@@ -93,11 +98,13 @@ pub struct FromRefs<'a>(pub &'a User, pub &'a ChatIdPartiality);
 pub(crate) async fn grow_impl(
     repos: &repo::Repositories,
     incr: Incrementor,
+    config: &config::AppConfig,
     from_refs: FromRefs<'_>,
 ) -> anyhow::Result<String> {
     let (from, chat_id) = (from_refs.0, from_refs.1);
     let name = utils::get_full_name(from);
-    let user = repos.users.create_or_update(from.id, &name).await?;
+    let user = repos.users.create_or_update(from.id, &name, from.username.as_deref()).await?;
+    let first_growth = !repos.dicks.is_user_has_dick(from.id, &chat_id.kind()).await?;
     let days_since_registration = (Utc::now() - user.created_at).num_days() as u32;
     let increment = incr
         .growth_increment(from.id, chat_id.kind(), days_since_registration)
@@ -127,12 +134,19 @@ pub(crate) async fn grow_impl(
                 incr = increment.total.abs(),
                 length = new_length
             );
+            let event_part = increment.event_part_of_answer(&lang_code);
             let perks_part = increment.perks_part_of_answer(&lang_code);
+            let streak_part = grow_streak_part(repos, from.id, &chat_id.kind(), &lang_code).await;
+            let newcomer_part = if first_growth {
+                newcomer_part(repos, config, from.id, &chat_id.kind(), &lang_code).await
+            } else {
+                String::new()
+            };
             if let Some(pos) = pos_in_top {
                 let position = t!("commands.grow.position", locale = &lang_code, pos = pos);
-                format!("{answer}\n{position}{perks_part}")
+                format!("{event_part}{answer}\n{position}{streak_part}{perks_part}{newcomer_part}")
             } else {
-                format!("{answer}{perks_part}")
+                format!("{event_part}{answer}{streak_part}{perks_part}{newcomer_part}")
             }
         }
         Err(e) => {
@@ -147,10 +161,63 @@ pub(crate) async fn grow_impl(
             }
         }
     };
-    let time_left_part = utils::date::get_time_till_next_day_string(&lang_code);
+    let utc_offset = utils::get_chat_utc_offset(repos, &chat_id.kind()).await;
+    let time_left_part = utils::date::get_time_till_next_day_string(&lang_code, utc_offset);
     Ok(format!("{main_part}{time_left_part}"))
 }
 
+/// Returns an empty string for the first day of a streak.
+async fn grow_streak_part(
+    repos: &repo::Repositories,
+    uid: UserId,
+    chat_id: &repo::ChatIdKind,
+    lang_code: &LanguageCode,
+) -> String {
+    let streak = match repos.dicks.fetch_grow_streak(uid, chat_id).await {
+        Ok(streak) if streak.current > 1 => streak.current,
+        Ok(_) => return String::new(),
+        Err(e) => {
+            log::error!("couldn't fetch the grow streak of {uid} in {chat_id}: {e}");
+            return String::new();
+        }
+    };
+    let streak_line = t!("commands.grow.streak", locale = lang_code, days = streak);
+    if GROW_STREAK_MILESTONES.contains(&streak) {
+        metrics::GROW_STREAK_MILESTONE_COUNTER.inc();
+        log::info!("{uid} has reached the grow streak milestone of {streak} days in {chat_id}");
+        let milestone = t!("commands.grow.streak_milestone", locale = lang_code, days = streak);
+        format!("\n{streak_line}\n{milestone}")
+    } else {
+        format!("\n{streak_line}")
+    }
+}
+
+/// The tutorial for the first growth in a chat and the one-time starter bonus.
+async fn newcomer_part(
+    repos: &repo::Repositories,
+    config: &config::AppConfig,
+    uid: UserId,
+    chat_id: &repo::ChatIdKind,
+    lang_code: &LanguageCode,
+) -> String {
+    let tutorial = t!("commands.grow.newcomer.tutorial", locale = lang_code);
+    let bonus = config.newcomers.starter_bonus;
+    if bonus == 0 {
+        return format!("\n\n{tutorial}");
+    }
+    match repos.dicks.grant_starter_bonus(uid, chat_id, bonus).await {
+        Ok(Some(length)) => {
+            let bonus_line = t!("commands.grow.newcomer.starter_bonus", locale = lang_code, bonus = bonus, length = length);
+            format!("\n\n{tutorial}\n\n{bonus_line}")
+        }
+        Ok(None) => format!("\n\n{tutorial}"),
+        Err(e) => {
+            log::error!("couldn't grant the starter bonus to {uid} in {chat_id}: {e}");
+            format!("\n\n{tutorial}")
+        }
+    }
+}
+
 pub(crate) async fn gift_impl(
     repos: &repo::Repositories,
     msg: &Message,
@@ -160,89 +227,73 @@ pub(crate) async fn gift_impl(
     let (from, chat_id) = (from_refs.0, from_refs.1);
     let lang_code = LanguageCode::from_user(from);
 
-    let text = msg.text().unwrap_or("");
-    let parts: Vec<&str> = text.split_whitespace().collect();
-
-    let reply_msg = msg.reply_to_message();
-
-    if parts.len() < 2 {
-        return Ok(format!(
-            "{}",
-            t!("commands.gift.error.usage", locale = &lang_code)
-        ));
-    }
-
-    let amount_str = parts[1];
-    let amount: u16 = match amount_str.parse() {
-        Ok(amt) if amt > 0 => amt,
+    let (target, args) = utils::resolve_target(repos, msg).await?;
+    let (recipient, amount_str) = match (target, args.first()) {
+        (Target::User(uid), Some(amount_str)) => (uid, *amount_str),
+        (Target::UnknownUsername(username), _) => {
+            return Ok(t!("errors.username_not_found", locale = &lang_code,
+                username = teloxide::utils::html::escape(&username)).to_string())
+        }
         _ => {
             return Ok(format!(
                 "{}",
-                t!("commands.gift.error.invalid_amount", locale = &lang_code)
+                t!("commands.gift.error.usage", locale = &lang_code)
             ))
         }
     };
 
-    log::debug!("from: {from:?}, chat_id: {chat_id:?}, amount: {amount}");
-
-    let recipient = match reply_msg.as_ref().and_then(|msg| msg.from.as_ref()) {
-        Some(user) => user,
-        None => {
-            log::warn!("no FROM field in the gift command handler");
-            log::debug!("reply_msg: {reply_msg:?}");
+    let amount: u16 = match amount_str.parse() {
+        Ok(amt) if amt > 0 => amt,
+        _ => {
             return Ok(format!(
                 "{}",
-                t!("commands.gift.error.usage", locale = &lang_code)
-            ));
+                t!("commands.gift.error.invalid_amount", locale = &lang_code)
+            ))
         }
     };
 
-    if recipient.id == from.id {
+    log::debug!("from: {from:?}, chat_id: {chat_id:?}, recipient: {recipient}, amount: {amount}");
+
+    if recipient == from.id {
         return Ok(format!(
             "{}",
             t!("commands.gift.error.same_person", locale = &lang_code)
         ));
     }
 
-    if let Some(custom_name) = config.gift_restriction.restrictions.get(&recipient.id.0) {
-        return Ok(format!(
-            "{}",
-            t!(
-                "commands.gift.error.restricted_user",
-                locale = &lang_code,
-                name = custom_name
-            )
-        ));
+    let chat_id_kind = chat_id.kind();
+    let (sender_banned, recipient_banned) = futures::join!(
+        repos.moderation.is_banned(&chat_id_kind, from.id),
+        repos.moderation.is_banned(&chat_id_kind, recipient),
+    );
+    if sender_banned? {
+        return Ok(t!("errors.banned", locale = &lang_code).to_string());
+    }
+    if recipient_banned? {
+        return Ok(t!("commands.gift.error.banned_recipient", locale = &lang_code).to_string());
     }
 
-    let sender_length: i32 = repos
-        .dicks
-        .fetch_length(from.id, &chat_id.kind())
-        .await
-        .unwrap_or_default();
-
-    if sender_length < amount as i32 {
+    if let Some(custom_name) = get_gift_restriction(repos, config, recipient).await? {
         return Ok(format!(
             "{}",
             t!(
-                "commands.gift.error.not_enough",
+                "commands.gift.error.restricted_user",
                 locale = &lang_code,
-                current = sender_length,
-                required = amount
+                name = custom_name
             )
         ));
     }
 
     match repos
         .dicks
-        .is_user_has_dick(recipient.id, &chat_id.kind())
+        .is_user_has_dick(recipient, &chat_id.kind())
         .await
     {
         Ok(true) => {}
         Ok(false) => {
             log::debug!(
                 "recipient {} doesn't have a dick in {}",
-                recipient.id,
+                recipient,
                 chat_id
             );
             return Ok(format!(
@@ -262,36 +313,32 @@ pub(crate) async fn gift_impl(
         }
     };
 
+    let legs = [TransferLeg { to: recipient, amount }];
     let transfer_result = repos
-        .dicks
-        .move_length(chat_id, from.id, recipient.id, amount)
+        .transfers
+        .transfer(chat_id, from.id, &legs, TransferKind::Gift, &config.gifts, true)
         .await;
 
     match transfer_result {
-        Ok((sender_result, recipient_result)) => {
+        Ok(Ok(receipt)) => {
             let sender_name = utils::get_full_name(from);
-            let recipient_name = utils::get_full_name(recipient);
-
-            if let Err(e) = repos
-                .transfers
-                .log(chat_id, from.id, recipient.id, amount as i32, TransferKind::Gift)
-                .await
-            {
-                log::warn!("failed to log gift transfer: {e}");
-            }
+            let recipient_name = repos.users.get(recipient).await?
+                .map(|user| user.name)
+                .unwrap_or_else(|| Username::new(recipient.to_string()));
 
-            Ok(format!(
-                "{}",
-                t!(
-                    "commands.gift.result",
-                    locale = &lang_code,
-                    sender = sender_name.value_ref(),
-                    recipient = recipient_name.value_ref(),
-                    amount = amount,
-                    sender_length = sender_result.new_length,
-                    recipient_length = recipient_result.new_length
-                )
-            ))
+            let result = t!(
+                "commands.gift.result",
+                locale = &lang_code,
+                sender = sender_name.value_ref(),
+                recipient = recipient_name.value_ref(),
+                amount = amount,
+                sender_length = receipt.sender_length,
+                recipient_length = receipt.recipient_lengths[0]
+            );
+            Ok(append_receipt_notes(result.to_string(), "commands.gift", &receipt, &lang_code))
+        }
+        Ok(Err(rejection)) => {
+            explain_transfer_rejection(repos, rejection, "commands.gift.error.not_enough", amount, &lang_code).await
         }
         Err(e) => Ok(format!(
             "{}",
@@ -304,6 +351,61 @@ pub(crate) async fn gift_impl(
     }
 }
 
+/// Returns the name the user must be called by in the refusal if gifts to them are restricted.
+/// The restrictions from the configuration take precedence over the ones set by the operators.
+async fn get_gift_restriction(repos: &repo::Repositories, config: &config::AppConfig, uid: UserId) -> anyhow::Result<Option<String>> {
+    match config.gift_restriction.restrictions.get(&uid.0) {
+        Some(custom_name) => Ok(Some(custom_name.clone())),
+        None => repos.gift_restrictions.get(uid).await,
+    }
+}
+
+/// Turns the reason of a rejected gift or fire into a message for the sender.
+pub(super) async fn explain_transfer_rejection(
+    repos: &repo::Repositories,
+    rejection: TransferRejection,
+    not_enough_key: &str,
+    required: u16,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<String> {
+    let text = match rejection {
+        TransferRejection::NotEnough { current } => {
+            t!(not_enough_key, locale = lang_code, current = current, required = required)
+        }
+        TransferRejection::Cooldown { minutes_left } => {
+            t!("errors.transfer_cooldown", locale = lang_code, minutes = minutes_left)
+        }
+        TransferRejection::DailyCap { left } => {
+            t!("errors.transfer_daily_cap", locale = lang_code, left = left)
+        }
+        TransferRejection::RecipientTooNew(uid) => {
+            let name = repos.users.get(uid).await?
+                .map(|user| user.name.escaped())
+                .unwrap_or_else(|| uid.to_string());
+            t!("errors.transfer_recipient_too_new", locale = lang_code, name = name)
+        }
+    };
+    Ok(text.to_string())
+}
+
+/// Tells about the burned and held parts of a transfer, the keys are `tax` and `held` under the prefix of the command.
+fn append_receipt_notes(text: String, key_prefix: &str, receipt: &TransferReceipt, lang_code: &LanguageCode) -> String {
+    let mut notes = Vec::new();
+    if receipt.tax > 0 {
+        let t_key = format!("{key_prefix}.tax");
+        notes.push(t!(&t_key, locale = lang_code, tax = receipt.tax));
+    }
+    if receipt.held > 0 {
+        let t_key = format!("{key_prefix}.held");
+        notes.push(t!(&t_key, locale = lang_code, amount = receipt.held));
+    }
+    if notes.is_empty() {
+        text
+    } else {
+        format!("{text}\n\n{}", notes.join("\n"))
+    }
+}
+
 async fn get_random_chat_users(
     repos: &repo::Repositories,
     chat_id: &repo::ChatIdKind,
@@ -350,6 +452,11 @@ pub(crate) async fn fire_impl(
     let (from, chat_id) = (from_refs.0, from_refs.1);
     let lang_code = LanguageCode::from_user(from);
 
+    let (targets, args) = utils::resolve_weighted_targets(repos, msg).await?;
+    if !targets.is_empty() {
+        return targeted_fire_impl(repos, FromRefs(from, chat_id), config, targets, args).await
+    }
+
     let text = msg.text().unwrap_or("");
     let parts: Vec<&str> = text.split_whitespace().collect();
 
@@ -372,34 +479,16 @@ pub(crate) async fn fire_impl(
     };
 
     let recipients_count = config.fire_recipients;
-    let amount_per_person = total_amount / recipients_count;
+    let amounts = utils::split_amount(total_amount, &vec![1; recipients_count as usize], config.fire_rounding);
 
-    if amount_per_person == 0 {
+    if amounts.contains(&0) {
         return Ok(format!(
             "{}",
             t!("commands.fire.error.too_small", locale = &lang_code)
         ));
     }
 
-    log::debug!("from: {from:?}, chat_id: {chat_id:?}, total_amount: {total_amount}, recipients: {recipients_count}, per_person: {amount_per_person}");
-
-    let sender_length: i32 = repos
-        .dicks
-        .fetch_length(from.id, &chat_id.kind())
-        .await
-        .unwrap_or_default();
-
-    if sender_length < total_amount as i32 {
-        return Ok(format!(
-            "{}",
-            t!(
-                "commands.fire.error.not_enough",
-                locale = &lang_code,
-                current = sender_length,
-                required = total_amount
-            )
-        ));
-    }
+    log::debug!("from: {from:?}, chat_id: {chat_id:?}, total_amount: {total_amount}, recipients: {recipients_count}, amounts: {amounts:?}");
 
     let random_users =
         match get_random_chat_users(repos, &chat_id.kind(), from.id, recipients_count).await
@@ -429,94 +518,209 @@ pub(crate) async fn fire_impl(
         ));
     }
 
-    let mut successful_transfers = Vec::new();
-    let mut remaining_amount = total_amount;
-
-    let mut futures = Vec::new();
-    for user in &random_users {
-        if remaining_amount < amount_per_person {
-            break;
+    let legs: Vec<TransferLeg> = random_users
+        .iter()
+        .zip(&amounts)
+        .map(|(user, &amount)| TransferLeg {
+            to: user.owner_uid.into(),
+            amount,
+        })
+        .collect();
+    let transferred_amount: u16 = amounts.iter().sum();
+    // the recipients are chosen randomly, so they cannot be alt accounts of the sender
+    let receipt = match repos
+        .transfers
+        .transfer(chat_id, from.id, &legs, TransferKind::Fire, &config.gifts, false)
+        .await
+    {
+        Ok(Ok(receipt)) => receipt,
+        Ok(Err(rejection)) => {
+            return explain_transfer_rejection(repos, rejection, "commands.fire.error.not_enough", transferred_amount, &lang_code).await
+        }
+        Err(e) => {
+            log::warn!("failed to fire {transferred_amount} cm from {} in {chat_id}: {e}", from.id);
+            return Ok(format!(
+                "{}",
+                t!("commands.fire.error.no_transfers", locale = &lang_code)
+            ))
         }
+    };
 
-        futures.push(repos.dicks.move_length(
-            chat_id,
-            from.id,
-            user.owner_uid.into(),
-            amount_per_person,
-        ));
-        remaining_amount -= amount_per_person;
-    }
-
-    let results = futures::future::join_all(futures).await;
-
-    for (i, result) in results.into_iter().enumerate() {
-        match result {
-            Ok((_, recipient_result)) => {
-                successful_transfers.push((&random_users[i], recipient_result.new_length));
-                if let Err(e) = repos
-                    .transfers
-                    .log(
-                        chat_id,
-                        from.id,
-                        random_users[i].owner_uid.into(),
-                        amount_per_person as i32,
-                        TransferKind::Fire,
-                    )
-                    .await
-                {
-                    log::warn!("failed to log fire transfer to {:?}: {}", random_users[i].owner_uid, e);
-                }
-            }
-            Err(e) => {
-                log::warn!(
-                    "Failed to transfer to user {:?}: {}",
-                    random_users[i].owner_uid,
-                    e
-                );
-                remaining_amount += amount_per_person;
+    let sender_name = utils::get_full_name(from);
+
+    // the shares differ only if the rest of the split is given to some of the recipients
+    let even = amounts.iter().all(|&amount| amount == amounts[0]);
+    let mut recipient_lines = Vec::new();
+    for ((user, amount), new_length) in random_users.iter().zip(&amounts).zip(&receipt.recipient_lengths) {
+        let line = if even {
+            t!("commands.fire.line", locale = &lang_code, name = user.owner_name.clone(), length = new_length)
+        } else {
+            t!("commands.fire.targeted.line", locale = &lang_code, name = user.owner_name.clone(), amount = amount, length = new_length)
+        };
+        recipient_lines.push(line);
+    }
+    let recipients_list = recipient_lines.join("\n");
+
+    let header = if even {
+        t!(
+            "commands.fire.result",
+            locale = &lang_code,
+            sender = sender_name.value_ref(),
+            total_amount = transferred_amount,
+            recipients_count = legs.len(),
+            amount_per_person = amounts[0],
+            sender_length = receipt.sender_length
+        )
+    } else {
+        t!(
+            "commands.fire.targeted.result",
+            locale = &lang_code,
+            sender = sender_name.value_ref(),
+            total_amount = transferred_amount,
+            recipients_count = legs.len(),
+            sender_length = receipt.sender_length
+        )
+    };
+    let mut result = format!("{header}\n\n{recipients_list}");
+    let kept = total_amount - transferred_amount;
+    if kept > 0 {
+        result.push_str("\n\n");
+        result.push_str(&t!("commands.fire.targeted.kept", locale = &lang_code, amount = kept));
+    }
+    Ok(append_receipt_notes(result, "commands.fire", &receipt, &lang_code))
+}
+
+/// `/fire 30 @a @b` splits the amount evenly among the mentioned users, `/fire 30 @a:2 @b:1` splits it by the weights,
+/// and `/fire @a:20 @b:10` gives exactly the weights. What's left after the split is handled according to the rounding setting.
+async fn targeted_fire_impl(
+    repos: &repo::Repositories,
+    from_refs: FromRefs<'_>,
+    config: &config::AppConfig,
+    targets: Vec<WeightedTarget>,
+    args: Vec<&str>,
+) -> anyhow::Result<String> {
+    let (from, chat_id) = (from_refs.0, from_refs.1);
+    let lang_code = LanguageCode::from_user(from);
+
+    let mut recipients = Vec::with_capacity(targets.len());
+    let mut weights = Vec::with_capacity(targets.len());
+    for WeightedTarget { target, weight } in targets {
+        match target {
+            Target::User(uid) => recipients.push(uid),
+            Target::UnknownUsername(username) => {
+                return Ok(t!("errors.username_not_found", locale = &lang_code,
+                    username = teloxide::utils::html::escape(&username)).to_string())
             }
+            Target::Missing => continue,
         }
+        weights.push(weight);
     }
 
-    if successful_transfers.is_empty() {
-        return Ok(format!(
-            "{}",
-            t!("commands.fire.error.no_transfers", locale = &lang_code)
-        ));
+    let total = match args[..] {
+        [] => None,
+        [total] => match total.parse::<u16>() {
+            Ok(total) if total > 0 => Some(total),
+            _ => return Ok(t!("commands.fire.error.invalid_amount", locale = &lang_code).to_string()),
+        },
+        _ => return Ok(t!("commands.fire.error.usage", locale = &lang_code).to_string()),
+    };
+    let given_weights: Vec<u16> = weights.iter().flatten().copied().collect();
+    let amounts = if given_weights.is_empty() {
+        let Some(total) = total else {
+            return Ok(t!("commands.fire.error.usage", locale = &lang_code).to_string())
+        };
+        utils::split_amount(total, &vec![1; recipients.len()], config.fire_rounding)
+    } else if given_weights.len() < weights.len() || given_weights.contains(&0) {
+        return Ok(t!("commands.fire.error.invalid_weights", locale = &lang_code).to_string())
+    } else {
+        match total {
+            Some(total) => utils::split_amount(total, &given_weights, config.fire_rounding),
+            None => given_weights,
+        }
+    };
+    if amounts.contains(&0) {
+        return Ok(t!("commands.fire.error.too_small", locale = &lang_code).to_string())
     }
-
-    let final_sender_length = match repos.dicks.fetch_length(from.id, &chat_id.kind()).await {
-        Ok(length) => length,
-        Err(_) => sender_length - (total_amount - remaining_amount) as i32,
+    let Some(transferred_amount) = amounts.iter().try_fold(0u16, |sum, &amount| sum.checked_add(amount)) else {
+        return Ok(t!("commands.fire.error.invalid_amount", locale = &lang_code).to_string())
     };
 
-    let sender_name = utils::get_full_name(from);
-    let transferred_amount = total_amount - remaining_amount;
+    if recipients.contains(&from.id) {
+        return Ok(t!("commands.fire.error.same_person", locale = &lang_code).to_string())
+    }
+    let unique_recipients: std::collections::HashSet<UserId> = recipients.iter().copied().collect();
+    if unique_recipients.len() < recipients.len() {
+        return Ok(t!("commands.fire.error.duplicate", locale = &lang_code).to_string())
+    }
 
-    let mut recipient_lines = Vec::new();
-    for (user, new_length) in &successful_transfers {
-        recipient_lines.push(t!(
-            "commands.fire.line",
-            locale = &lang_code,
-            name = user.owner_name.clone(),
-            length = new_length
-        ));
+    let chat_id_kind = chat_id.kind();
+    if repos.moderation.is_banned(&chat_id_kind, from.id).await? {
+        return Ok(t!("errors.banned", locale = &lang_code).to_string())
     }
-    let recipients_list = recipient_lines.join("\n");
+    let mut names = Vec::with_capacity(recipients.len());
+    for &recipient in &recipients {
+        let name = repos.users.get(recipient).await?
+            .map(|user| user.name.escaped())
+            .unwrap_or_else(|| recipient.to_string());
+        if repos.moderation.is_banned(&chat_id_kind, recipient).await? {
+            return Ok(t!("commands.fire.error.banned_recipient", locale = &lang_code, name = name).to_string())
+        }
+        if let Some(custom_name) = get_gift_restriction(repos, config, recipient).await? {
+            return Ok(t!("commands.gift.error.restricted_user", locale = &lang_code, name = custom_name).to_string())
+        }
+        if !repos.dicks.is_user_has_dick(recipient, &chat_id_kind).await? {
+            return Ok(t!("commands.fire.error.wrong_person", locale = &lang_code, name = name).to_string())
+        }
+        names.push(name);
+    }
+
+    log::debug!("from: {from:?}, chat_id: {chat_id:?}, recipients: {recipients:?}, amounts: {amounts:?}");
+
+    let legs: Vec<TransferLeg> = recipients.iter()
+        .zip(&amounts)
+        .map(|(&to, &amount)| TransferLeg { to, amount })
+        .collect();
+    let receipt = match repos
+        .transfers
+        .transfer(chat_id, from.id, &legs, TransferKind::Fire, &config.gifts, true)
+        .await
+    {
+        Ok(Ok(receipt)) => receipt,
+        Ok(Err(rejection)) => {
+            return explain_transfer_rejection(repos, rejection, "commands.fire.error.not_enough", transferred_amount, &lang_code).await
+        }
+        Err(e) => {
+            log::warn!("failed to fire {transferred_amount} cm from {} to {recipients:?} in {chat_id}: {e}", from.id);
+            return Ok(t!("commands.fire.error.no_transfers", locale = &lang_code).to_string())
+        }
+    };
 
-    Ok(format!(
+    let sender_name = utils::get_full_name(from);
+    let recipient_lines = names.iter()
+        .zip(&amounts)
+        .zip(&receipt.recipient_lengths)
+        .map(|((name, amount), length)| {
+            t!("commands.fire.targeted.line", locale = &lang_code, name = name, amount = amount, length = length).to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let mut result = format!(
         "{}\n\n{}",
         t!(
-            "commands.fire.result",
+            "commands.fire.targeted.result",
             locale = &lang_code,
             sender = sender_name.value_ref(),
             total_amount = transferred_amount,
-            recipients_count = successful_transfers.len(),
-            amount_per_person = amount_per_person,
-            sender_length = final_sender_length
+            recipients_count = legs.len(),
+            sender_length = receipt.sender_length
         ),
-        recipients_list
-    ))
+        recipient_lines
+    );
+    if let Some(kept) = total.map(|total| total - transferred_amount).filter(|kept| *kept > 0) {
+        result.push_str("\n\n");
+        result.push_str(&t!("commands.fire.targeted.kept", locale = &lang_code, amount = kept));
+    }
+    Ok(append_receipt_notes(result, "commands.fire", &receipt, &lang_code))
 }
 
 #[derive(Debug)]
@@ -548,12 +752,25 @@ pub(crate) async fn top_impl(
     page: Page,
 ) -> anyhow::Result<Top> {
     let (from, chat_id) = (from_refs.0, from_refs.1.kind());
-    let lang_code = LanguageCode::from_user(from);
+    let lang_code = utils::get_chat_language(repos, &chat_id, LanguageCode::from_user(from)).await;
     let top_limit = config.top_limit as u32;
     let offset = page * top_limit;
     let query_limit = config.top_limit + 1; // fetch +1 row to know whether more rows exist or not
     let dicks = repos.dicks.get_top(&chat_id, offset, query_limit).await?;
     let has_more_pages = dicks.len() as u32 > top_limit;
+    let utc_offset = utils::get_chat_utc_offset(repos, &chat_id).await;
+    let today = Utc::now().with_timezone(&utc_offset).date_naive();
+    let uids = dicks
+        .iter()
+        .take(config.top_limit as usize)
+        .map(|d| d.owner_uid)
+        .collect::<Vec<UID>>();
+    let newcomers = repos
+        .users
+        .filter_newcomers(&uids, config.newcomers.grace_days)
+        .await
+        .inspect_err(|e| log::error!("couldn't find the newcomers in the top of {chat_id}: {e}"))
+        .unwrap_or_default();
 
     let lines = dicks
         .into_iter()
@@ -566,7 +783,7 @@ pub(crate) async fn top_impl(
             } else {
                 escaped_name
             };
-            let can_grow = Utc::now().num_days_from_ce() > d.grown_at.num_days_from_ce();
+            let can_grow = today > d.grown_at.with_timezone(&utc_offset).date_naive();
             let pos = d.position.unwrap_or((i + 1) as i64);
             let mut line = t!(
                 "commands.top.line",
@@ -576,6 +793,9 @@ pub(crate) async fn top_impl(
                 length = d.length
             )
             .to_string();
+            if newcomers.contains(&d.owner_uid) {
+                line.push_str(" 🐣")
+            }
             if can_grow {
                 line.push_str(" [+]")
             };
@@ -587,7 +807,11 @@ pub(crate) async fn top_impl(
         Top::from(t!("commands.top.empty", locale = &lang_code))
     } else {
         let title = t!("commands.top.title", locale = &lang_code);
-        let ending = t!("commands.top.ending", locale = &lang_code);
+        let mut ending = t!("commands.top.ending", locale = &lang_code).to_string();
+        if !newcomers.is_empty() {
+            let newcomers_ending = t!("commands.top.newcomers_ending", locale = &lang_code);
+            ending = format!("{ending}\n{newcomers_ending}");
+        }
         let text = format!("{}\n\n{}\n\n{}", title, lines.join("\n"), ending);
         if has_more_pages {
             Top::with_more_pages(text)
@@ -628,7 +852,10 @@ pub async fn page_callback_handler(
         .and_then(|r| r.parse().map_err(|e| InvalidPage::for_value(&r, e)))
         .map(Page)
         .map_err(|e| anyhow!(e))?;
-    let chat_id_kind = edit_msg_req_params.clone().into();
+    let chat_id_kind: ChatIdKind = edit_msg_req_params.clone().into();
+    if repos.moderation.is_command_disabled(&chat_id_kind, "top").await? {
+        return send_command_disabled_callback_answer(bot, q, "top").await;
+    }
     let chat_id_partiality = ChatIdPartiality::Specific(chat_id_kind);
     let from_refs = FromRefs(&q.from, &chat_id_partiality);
     let top = top_impl(&repos, &config, from_refs, page).await?;
@@ -49,7 +49,7 @@ pub(crate) async fn dick_of_day_impl(
     from_refs: FromRefs<'_>,
 ) -> anyhow::Result<String> {
     let (from, chat_id) = (from_refs.0, from_refs.1);
-    let lang_code = LanguageCode::from_user(from);
+    let lang_code = utils::get_chat_language(repos, &chat_id.kind(), LanguageCode::from_user(from)).await;
     let winner = match cfg.features.dod_selection_mode {
         DickOfDaySelectionMode::WEIGHTS => {
             repos
@@ -118,7 +118,8 @@ pub(crate) async fn dick_of_day_impl(
                     e => Err(e)?,
                 },
             };
-            let time_left_part = utils::date::get_time_till_next_day_string(&lang_code);
+            let utc_offset = utils::get_chat_utc_offset(repos, &chat_id.kind()).await;
+            let time_left_part = utils::date::get_time_till_next_day_string(&lang_code, utc_offset);
             format!("{main_part}{time_left_part}")
         }
         None => t!("commands.dod.no_candidates", locale = &lang_code).to_string(),
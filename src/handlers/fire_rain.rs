@@ -0,0 +1,363 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use rust_i18n::t;
+use teloxide::requests::Requester;
+use teloxide::types::ParseMode::Html;
+use teloxide::types::{
+    CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId,
+    ReplyMarkup, UserId,
+};
+use teloxide::Bot;
+
+use crate::config::{AppConfig, ReloadableConfig};
+use crate::domain::LanguageCode;
+use crate::handlers::dick::explain_transfer_rejection;
+use crate::handlers::utils::callbacks;
+use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, InvalidCallbackDataBuilder};
+use crate::handlers::utils::locks::LockCallbackServiceFacade;
+use crate::handlers::{
+    get_topic_id, reply_html, send_command_disabled_callback_answer, send_error_callback_answer,
+    utils, CallbackResult, FromRefs, HandlerResult,
+};
+use crate::repo;
+use crate::repo::{ChatIdKind, ClaimRejection, FireRain, FireRainClaim};
+
+const RAIN_ARG: &str = "rain";
+const EXPIRED_RAINS_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub(crate) struct FireRainCallbackData {
+    rain_id: i64,
+}
+
+impl Display for FireRainCallbackData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.rain_id)
+    }
+}
+
+impl CallbackDataWithPrefix for FireRainCallbackData {
+    fn prefix() -> &'static str {
+        "rain"
+    }
+}
+
+impl TryFrom<String> for FireRainCallbackData {
+    type Error = callbacks::InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.split(':');
+        let rain_id = callbacks::parse_part(&mut parts, &err, "rain_id")?;
+        Ok(Self { rain_id })
+    }
+}
+
+/// The key to lock the claims of one user: the button is the same for everybody,
+/// but several clicks of the same user must not be processed at once.
+struct FireRainClaimLock {
+    rain_id: i64,
+    uid: UserId,
+}
+
+impl Display for FireRainClaimLock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.rain_id, self.uid)
+    }
+}
+
+impl CallbackDataWithPrefix for FireRainClaimLock {
+    fn prefix() -> &'static str {
+        "rain"
+    }
+}
+
+impl TryFrom<String> for FireRainClaimLock {
+    type Error = callbacks::InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.split(':');
+        let rain_id = callbacks::parse_part(&mut parts, &err, "rain_id")?;
+        let uid = callbacks::parse_part(&mut parts, &err, "uid").map(UserId)?;
+        Ok(Self { rain_id, uid })
+    }
+}
+
+/// `/fire rain <amount> <shares>` instead of the usual fire to random users.
+pub fn is_rain(msg: &Message) -> bool {
+    msg.text()
+        .and_then(|text| text.split_whitespace().nth(1))
+        .is_some_and(|arg| arg.eq_ignore_ascii_case(RAIN_ARG))
+}
+
+pub async fn cmd_handler(
+    bot: Bot,
+    msg: &Message,
+    from_refs: FromRefs<'_>,
+    repos: &repo::Repositories,
+    config: &AppConfig,
+) -> HandlerResult {
+    // everybody in the chat reads and clicks the message
+    let lang_code = LanguageCode::from_user(from_refs.0);
+    let lang_code = utils::get_chat_language(repos, &from_refs.1.kind(), lang_code).await;
+
+    let rain_id = match rain_impl(repos, msg, from_refs, config, &lang_code).await? {
+        Ok(rain_id) => rain_id,
+        Err(answer) => {
+            reply_html(bot, msg, answer).await?;
+            return Ok(());
+        }
+    };
+    let (text, keyboard) = render_rain(repos, rain_id, &lang_code).await?;
+    let mut request = reply_html(bot, msg, text);
+    request.reply_markup = keyboard.map(ReplyMarkup::InlineKeyboard);
+    let sent = request.await?;
+    repos.fire_rains.set_message(rain_id, sent.id).await?;
+    Ok(())
+}
+
+/// Returns the ID of the started rain or the answer explaining why it hasn't been started.
+async fn rain_impl(
+    repos: &repo::Repositories,
+    msg: &Message,
+    from_refs: FromRefs<'_>,
+    config: &AppConfig,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<Result<i64, String>> {
+    let (from, chat_id) = (from_refs.0, from_refs.1);
+    let args: Vec<&str> = msg
+        .text()
+        .unwrap_or_default()
+        .split_whitespace()
+        .skip(2)
+        .collect();
+    let (amount, shares) = match args[..] {
+        [amount, shares] => match (amount.parse::<u16>(), shares.parse::<u16>()) {
+            (Ok(amount), Ok(shares)) if shares > 0 && amount >= shares => (amount, shares),
+            _ => {
+                let answer = t!("commands.fire.rain.errors.invalid", locale = lang_code);
+                return Ok(Err(answer.to_string()));
+            }
+        },
+        _ => {
+            return Ok(Err(
+                t!("commands.fire.rain.usage", locale = lang_code).to_string()
+            ))
+        }
+    };
+
+    if repos.moderation.is_banned(&chat_id.kind(), from.id).await? {
+        return Ok(Err(t!("errors.banned", locale = lang_code).to_string()));
+    }
+
+    let started = repos
+        .fire_rains
+        .start(
+            chat_id,
+            from.id,
+            amount,
+            shares,
+            &config.gifts,
+            config.fire_rain_timeout_minutes,
+        )
+        .await?;
+    match started {
+        Ok(rain_id) => Ok(Ok(rain_id)),
+        Err(rejection) => {
+            let not_enough_key = "commands.fire.error.not_enough";
+            explain_transfer_rejection(repos, rejection, not_enough_key, amount, lang_code)
+                .await
+                .map(Err)
+        }
+    }
+}
+
+#[inline]
+pub fn callback_filter(query: CallbackQuery) -> bool {
+    FireRainCallbackData::check_prefix(query)
+}
+
+pub async fn callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    repos: repo::Repositories,
+    config: AppConfig,
+    mut battle_locker: LockCallbackServiceFacade,
+) -> HandlerResult {
+    let rain_id = FireRainCallbackData::parse(&query)?.rain_id;
+    let chat_id: ChatIdKind = query
+        .message
+        .as_ref()
+        .map(|msg| msg.chat().id)
+        .ok_or(anyhow!("no message of the fire rain {rain_id}"))?
+        .into();
+    let topic = query
+        .regular_message()
+        .and_then(get_topic_id)
+        .map(|thread_id| thread_id.0 .0);
+
+    if repos.moderation.is_frozen(&chat_id).await? {
+        return send_error_callback_answer(bot, query, "errors.game_paused").await;
+    }
+    if repos.moderation.is_command_disabled(&chat_id, "fire").await? {
+        return send_command_disabled_callback_answer(bot, query, "fire").await;
+    }
+    // the rains sent before the game was restricted to a topic
+    if repos.moderation.get_game_topic(&chat_id).await?.is_some_and(|game_topic| topic != Some(game_topic)) {
+        return send_error_callback_answer(bot, query, "commands.fire.rain.errors.outside_game_topic").await;
+    }
+    if repos.moderation.is_banned(&chat_id, query.from.id).await? {
+        return send_error_callback_answer(bot, query, "errors.banned").await;
+    }
+    let lock = FireRainClaimLock {
+        rain_id,
+        uid: query.from.id,
+    };
+    let _claim_guard = match battle_locker.try_lock(&lock) {
+        Some(guard) => guard,
+        None => {
+            return send_error_callback_answer(bot, query, "commands.fire.rain.errors.in_progress")
+                .await
+        }
+    };
+
+    let claim = repos
+        .fire_rains
+        .claim(rain_id, query.from.id, &config.gifts)
+        .await?;
+    if let Err(rejection) = claim {
+        let key = match rejection {
+            ClaimRejection::Over => "commands.fire.rain.errors.over",
+            ClaimRejection::AlreadyClaimed => "commands.fire.rain.errors.already_claimed",
+            ClaimRejection::OwnRain => "commands.fire.rain.errors.own_rain",
+            ClaimRejection::NoDick => "commands.fire.rain.errors.no_dick",
+            ClaimRejection::TooNew => "commands.fire.rain.errors.too_new",
+        };
+        return send_error_callback_answer(bot, query, key).await;
+    }
+
+    let lang_code = LanguageCode::from_user(&query.from);
+    let lang_code = utils::get_chat_language(&repos, &chat_id, lang_code).await;
+    let (text, keyboard) = render_rain(&repos, rain_id, &lang_code).await?;
+    CallbackResult::EditMessage(text, keyboard)
+        .apply(bot, query)
+        .await?;
+    Ok(())
+}
+
+/// Returns the unclaimed centimeters of the expired rains to the senders every minute
+/// and removes the buttons from the messages of the rains.
+pub fn spawn_expired_rains_refunder(bot: Bot, config: ReloadableConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRED_RAINS_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let repos = config.get().repos.clone();
+            let rains = match repos.fire_rains.refund_expired().await {
+                Ok(rains) => rains,
+                Err(e) => {
+                    log::error!("couldn't refund the expired fire rains: {e:?}");
+                    continue;
+                }
+            };
+            for rain in rains {
+                if let Err(e) = close_rain_message(&bot, &repos, &rain).await {
+                    log::error!(
+                        "couldn't edit the message of the fire rain {}: {e:?}",
+                        rain.id
+                    );
+                }
+            }
+        }
+    });
+}
+
+async fn close_rain_message(
+    bot: &Bot,
+    repos: &repo::Repositories,
+    rain: &FireRain,
+) -> anyhow::Result<()> {
+    let (Some(chat_id), Some(message_id)) = (rain.chat_id, rain.message_id) else {
+        return Ok(());
+    };
+    let chat_id = ChatId(chat_id);
+    let lang_code = LanguageCode::from_maybe_user(None);
+    let lang_code = utils::get_chat_language(repos, &chat_id.into(), lang_code).await;
+    let claims = repos.fire_rains.get_claims(rain.id).await?;
+    let mut edit_req = bot.edit_message_text(
+        chat_id,
+        MessageId(message_id),
+        format_rain(rain, &claims, &lang_code),
+    );
+    edit_req.parse_mode.replace(Html);
+    edit_req.await?;
+    Ok(())
+}
+
+/// The text of the message of the rain and its button, if the rain is still going on.
+async fn render_rain(
+    repos: &repo::Repositories,
+    rain_id: i64,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<(String, Option<InlineKeyboardMarkup>)> {
+    let (rain, claims) = futures::try_join!(
+        repos.fire_rains.get(rain_id),
+        repos.fire_rains.get_claims(rain_id),
+    )?;
+    let rain = rain.ok_or(anyhow!("the fire rain {rain_id} is missing"))?;
+    let keyboard = (!rain.finished).then(|| {
+        let data = FireRainCallbackData { rain_id };
+        let button_text = t!("commands.fire.rain.button", locale = lang_code).to_string();
+        let button = InlineKeyboardButton::callback(button_text, data.to_data_string());
+        InlineKeyboardMarkup::new(vec![vec![button]])
+    });
+    Ok((format_rain(&rain, &claims, lang_code), keyboard))
+}
+
+fn format_rain(rain: &FireRain, claims: &[FireRainClaim], lang_code: &LanguageCode) -> String {
+    let sender_name = rain.sender_name.escaped();
+    let header = t!(
+        "commands.fire.rain.start",
+        locale = lang_code,
+        name = sender_name,
+        amount = rain.amount,
+        shares = rain.shares
+    );
+    let lines = claims
+        .iter()
+        .map(|claim| {
+            t!(
+                "commands.fire.rain.line",
+                locale = lang_code,
+                name = claim.name.escaped(),
+                amount = claim.amount
+            )
+            .to_string()
+        })
+        .collect::<Vec<String>>();
+    let footer = if rain.refunded > 0 {
+        t!(
+            "commands.fire.rain.refunded",
+            locale = lang_code,
+            name = sender_name,
+            amount = rain.refunded
+        )
+    } else if rain.finished {
+        t!("commands.fire.rain.finished", locale = lang_code)
+    } else {
+        t!(
+            "commands.fire.rain.left",
+            locale = lang_code,
+            amount = rain.remaining,
+            shares = rain.shares - claims.len() as i32
+        )
+    };
+
+    if lines.is_empty() {
+        format!("{header}\n\n{footer}")
+    } else {
+        format!("{header}\n\n{}\n\n{footer}", lines.join("\n"))
+    }
+}
@@ -1,7 +1,8 @@
 use crate::domain::{LanguageCode, Username};
+use crate::handlers::utils::ChatAdminsCache;
 use crate::handlers::{reply_html, HandlerResult};
 use crate::reply_html;
-use crate::{metrics, repo};
+use crate::{config, metrics, repo};
 use anyhow::{anyhow, bail};
 use once_cell::sync::Lazy;
 use regex::{Captures, Regex};
@@ -9,7 +10,6 @@ use rust_i18n::t;
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use teloxide::macros::BotCommands;
-use teloxide::requests::Requester;
 use teloxide::types::{ChatId, Message, UserId};
 use teloxide::Bot;
 
@@ -107,10 +107,12 @@ pub async fn import_cmd_handler(
     bot: Bot,
     msg: Message,
     repos: repo::Repositories,
+    config: config::AppConfig,
+    admins: ChatAdminsCache,
 ) -> HandlerResult {
     metrics::CMD_IMPORT.invoked();
     let lang_code = LanguageCode::from_maybe_user(msg.from.as_ref());
-    let answer = match check_and_parse_message(&bot, &msg).await {
+    let answer = match check_and_parse_message(&bot, &msg, &config, &admins).await {
         Ok(parsed) => match import_impl(&repos, msg.chat.id, parsed).await {
             Ok(r) => {
                 metrics::CMD_IMPORT.finished();
@@ -222,19 +224,10 @@ pub async fn import_cmd_handler(
 async fn check_and_parse_message(
     bot: &Bot,
     msg: &Message,
+    config: &config::AppConfig,
+    admins: &ChatAdminsCache,
 ) -> Result<ParseResult, BeforeImportCheckErrors> {
-    let admin_ids = bot
-        .get_chat_administrators(msg.chat.id)
-        .await?
-        .into_iter()
-        .map(|m| m.user.id);
-    let from_id = msg
-        .from
-        .as_ref()
-        .ok_or(BeforeImportCheckErrors::Other(anyhow!("not from a user")))?
-        .id;
-    let invoked_by_admin = admin_ids.into_iter().any(|id| id == from_id);
-    if !invoked_by_admin {
+    if admins.authorize(bot, msg, config).await?.is_none() {
         return Err(BeforeImportCheckErrors::NotAdmin);
     }
 
@@ -58,6 +58,19 @@ impl InlineResult {
 }
 
 impl InlineCommand {
+    /// Read-only commands are still available while the game is paused in the chat.
+    fn changes_game_state(&self) -> bool {
+        matches!(self, InlineCommand::Grow | InlineCommand::DickOfDay | InlineCommand::Loan)
+    }
+
+    /// The name of the command used by the per-chat toggles.
+    fn chat_toggle_key(&self) -> String {
+        match self {
+            InlineCommand::DickOfDay => "dod".to_owned(),
+            cmd => cmd.to_string(),
+        }
+    }
+
     async fn execute(
         &self,
         repos: &Repositories,
@@ -65,10 +78,19 @@ impl InlineCommand {
         incr: Incrementor,
         from_refs: FromRefs<'_>,
     ) -> anyhow::Result<InlineResult> {
+        if self.changes_game_state() && repos.moderation.is_frozen(&from_refs.1.kind()).await? {
+            let lang_code = LanguageCode::from_user(from_refs.0);
+            return Ok(InlineResult::text(t!("errors.game_paused", locale = &lang_code).to_string()));
+        }
+        let command = self.chat_toggle_key();
+        if repos.moderation.is_command_disabled(&from_refs.1.kind(), &command).await? {
+            let lang_code = LanguageCode::from_user(from_refs.0);
+            return Ok(InlineResult::text(t!("errors.command_disabled", locale = &lang_code, command = command).to_string()));
+        }
         match self {
             InlineCommand::Grow => {
                 metrics::CMD_GROW_COUNTER.inline.inc();
-                dick::grow_impl(repos, incr, from_refs)
+                dick::grow_impl(repos, incr, &config, from_refs)
                     .await
                     .map(InlineResult::text)
             }
@@ -157,7 +179,7 @@ pub async fn inline_handler(
     metrics::INLINE_COUNTER.invoked();
 
     let name = utils::get_full_name(&query.from);
-    repos.users.create_or_update(query.from.id, &name).await?;
+    repos.users.create_or_update(query.from.id, &name, query.from.username.as_deref()).await?;
 
     let uid = query.from.id.0;
     let lang_code = LanguageCode::from_user(&query.from);
@@ -1,16 +1,20 @@
 mod dick;
 mod dod;
+pub mod fire_rain;
 mod admin;
+pub mod chats;
 mod help;
 mod import;
 mod inline;
 pub mod loan;
+pub mod operator;
 pub mod perks;
 mod privacy;
 mod promo;
 pub mod pvp;
 mod start;
 pub mod stats;
+pub mod users;
 pub mod utils;
 
 use derive_more::Constructor;
@@ -20,7 +24,7 @@ use teloxide::requests::{JsonRequest, Requester};
 use teloxide::sugar::request::RequestLinkPreviewExt;
 use teloxide::types::ParseMode::Html;
 use teloxide::types::{
-    CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message, ReplyParameters,
+    CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message, ReplyParameters, ThreadId,
 };
 use teloxide::Bot;
 
@@ -133,9 +137,23 @@ pub fn reply_html<T: Into<String>>(bot: Bot, msg: &Message, answer: T) -> JsonRe
             .reply_parameters
             .replace(ReplyParameters::new(msg.id));
     }
+    answer.message_thread_id = get_topic_id(msg);
     answer
 }
 
+/// Returns the forum topic the message was sent into. Replies in ordinary supergroups have a thread too,
+/// but the messages must not be sent into it explicitly.
+pub fn get_topic_id(msg: &Message) -> Option<ThreadId> {
+    msg.thread_id.filter(|_| msg.is_topic_message)
+}
+
+/// A link to the forum topic for members of the chat; General has no link.
+pub fn get_topic_link(chat_id: teloxide::types::ChatId, thread_id: i32) -> String {
+    // the IDs of supergroups are prefixed with -100
+    let bare_chat_id = -chat_id.0 - 1_000_000_000_000;
+    format!("https://t.me/c/{bare_chat_id}/{thread_id}")
+}
+
 #[macro_export]
 macro_rules! reply_html {
     ($bot:ident, $msg:ident, $answer:expr) => {
@@ -159,13 +177,152 @@ pub async fn send_error_callback_answer(
     Ok(())
 }
 
+/// For the buttons of the messages sent before the command was disabled in the chat.
+pub async fn send_command_disabled_callback_answer(
+    bot: Bot,
+    query: CallbackQuery,
+    command: &str,
+) -> HandlerResult {
+    let lang_code = LanguageCode::from_user(&query.from);
+    bot.answer_callback_query(query.id)
+        .show_alert(true)
+        .text(t!("errors.command_disabled", locale = &lang_code, command = command))
+        .await?;
+    Ok(())
+}
+
 pub mod checks {
-    use super::{reply_html, HandlerResult};
+    use super::{get_topic_id, get_topic_link, reply_html, utils, HandlerResult};
     use crate::domain::LanguageCode;
+    use crate::repo::Repositories;
     use rust_i18n::t;
-    use teloxide::types::Message;
+    use std::collections::BTreeSet;
+    use teloxide::types::{Me, Message};
     use teloxide::Bot;
 
+    /// Commands which change the state of the game, mapped from their aliases to the main names.
+    const GAME_COMMANDS: &[(&str, &str)] = &[
+        ("grow", "grow"),
+        ("gift", "gift"),
+        ("fire", "fire"),
+        ("dick_of_day", "dod"),
+        ("dod", "dod"),
+        ("pvp", "pvp"),
+        ("battle", "pvp"),
+        ("attack", "pvp"),
+        ("fight", "pvp"),
+        ("loan", "loan"),
+        ("borrow", "loan"),
+    ];
+
+    /// Read-only commands which may be disabled in a chat in addition to the game ones.
+    const READ_ONLY_COMMANDS: &[(&str, &str)] = &[
+        ("top", "top"),
+        ("stats", "stats"),
+    ];
+
+    /// Returns the main names of the commands which may be disabled in a chat by its administrators.
+    pub fn get_toggleable_commands() -> BTreeSet<&'static str> {
+        GAME_COMMANDS.iter()
+            .chain(READ_ONLY_COMMANDS)
+            .map(|(_, key)| *key)
+            .collect()
+    }
+
+    /// Returns the main name of a game command if the message contains it and the command is addressed to this bot.
+    pub fn get_game_command(msg: &Message, me: &Me) -> Option<&'static str> {
+        find_command(msg, me, GAME_COMMANDS.iter())
+    }
+
+    /// The same as [`get_game_command`] but for all the commands which may be disabled in a chat.
+    pub fn get_toggleable_command(msg: &Message, me: &Me) -> Option<&'static str> {
+        find_command(msg, me, GAME_COMMANDS.iter().chain(READ_ONLY_COMMANDS))
+    }
+
+    fn find_command<'a>(
+        msg: &Message,
+        me: &Me,
+        mut commands: impl Iterator<Item = &'a (&'static str, &'static str)>,
+    ) -> Option<&'static str> {
+        let cmd = msg.text()?.split_whitespace().next()?.strip_prefix('/')?;
+        let cmd = match cmd.split_once('@') {
+            Some((cmd, username)) if username.eq_ignore_ascii_case(me.username()) => cmd,
+            Some(_) => return None,
+            None => cmd,
+        };
+        commands
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(cmd))
+            .map(|(_, key)| *key)
+    }
+
+    pub async fn is_game_paused(msg: Message, me: Me, repos: Repositories) -> bool {
+        if get_game_command(&msg, &me).is_none() {
+            return false;
+        }
+        repos
+            .moderation
+            .is_frozen(&msg.chat.id.into())
+            .await
+            .inspect_err(|e| log::error!("couldn't check whether the chat {} is frozen: {e}", msg.chat.id))
+            .unwrap_or(false)
+    }
+
+    pub async fn handle_game_paused(bot: Bot, msg: Message, repos: Repositories) -> HandlerResult {
+        let lang_code = LanguageCode::from_maybe_user(msg.from.as_ref());
+        let lang_code = utils::get_chat_language(&repos, &msg.chat.id.into(), lang_code).await;
+        let answer = t!("errors.game_paused", locale = &lang_code);
+        reply_html(bot, &msg, answer).await?;
+        Ok(())
+    }
+
+    /// Administrators may restrict the game to one forum topic, the commands are rejected in other topics.
+    pub async fn is_outside_game_topic(msg: Message, me: Me, repos: Repositories) -> bool {
+        if get_toggleable_command(&msg, &me).is_none() {
+            return false;
+        }
+        let topic = get_topic_id(&msg).map(|thread_id| thread_id.0 .0);
+        repos
+            .moderation
+            .get_game_topic(&msg.chat.id.into())
+            .await
+            .inspect_err(|e| log::error!("couldn't get the game topic of the chat {}: {e}", msg.chat.id))
+            .ok()
+            .flatten()
+            .is_some_and(|game_topic| topic != Some(game_topic))
+    }
+
+    pub async fn handle_outside_game_topic(bot: Bot, msg: Message, repos: Repositories) -> HandlerResult {
+        let lang_code = LanguageCode::from_maybe_user(msg.from.as_ref());
+        let lang_code = utils::get_chat_language(&repos, &msg.chat.id.into(), lang_code).await;
+        let link = repos.moderation.get_game_topic(&msg.chat.id.into()).await?
+            .map(|topic| get_topic_link(msg.chat.id, topic))
+            .unwrap_or_default();
+        let answer = t!("errors.outside_game_topic", locale = &lang_code, link = link);
+        reply_html(bot, &msg, answer).await?;
+        Ok(())
+    }
+
+    pub async fn is_command_disabled(msg: Message, me: Me, repos: Repositories) -> bool {
+        let Some(command) = get_toggleable_command(&msg, &me) else {
+            return false;
+        };
+        repos
+            .moderation
+            .is_command_disabled(&msg.chat.id.into(), command)
+            .await
+            .inspect_err(|e| log::error!("couldn't check whether /{command} is disabled in the chat {}: {e}", msg.chat.id))
+            .unwrap_or(false)
+    }
+
+    pub async fn handle_command_disabled(bot: Bot, msg: Message, me: Me, repos: Repositories) -> HandlerResult {
+        let lang_code = LanguageCode::from_maybe_user(msg.from.as_ref());
+        let lang_code = utils::get_chat_language(&repos, &msg.chat.id.into(), lang_code).await;
+        let command = get_toggleable_command(&msg, &me).unwrap_or_default();
+        let answer = t!("errors.command_disabled", locale = &lang_code, command = command);
+        reply_html(bot, &msg, answer).await?;
+        Ok(())
+    }
+
     pub fn is_group_chat(msg: Message) -> bool {
         if msg.chat.is_private() || msg.chat.is_channel() {
             return false;
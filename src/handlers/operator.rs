@@ -0,0 +1,454 @@
+use crate::domain::{LanguageCode, SupportedLanguage};
+use crate::handlers::promo::PROMO_CODE_FORMAT_REGEXP;
+use crate::handlers::{reply_html, HandlerResult};
+use crate::repo::{CollusionFlag, PromoCodeParams, ReviewVerdict};
+use crate::{config, reply_html, repo};
+use anyhow::anyhow;
+use rust_i18n::t;
+use std::str::FromStr;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+use teloxide::macros::BotCommands;
+use teloxide::types::{Message, UserId};
+use teloxide::utils::command::BotCommands as _;
+use teloxide::utils::html;
+use teloxide::Bot;
+
+const ANNOUNCEMENT_DISABLE_KEYWORD: &str = "off";
+const ANNOUNCEMENT_RESET_KEYWORD: &str = "reset";
+const COLLUSION_DETECTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const SUSPICIOUS_REPORT_LIMIT: u16 = 20;
+
+/// Commands for the maintainers of the bot. They are accepted only in private chats
+/// from the users listed in `BOT_OPERATORS` and are never advertised in the command lists.
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum OperatorCommands {
+    #[command(description = "operator")]
+    Operator,
+    #[command(description = "newpromo")]
+    NewPromo(String),
+    #[command(description = "disablepromo")]
+    DisablePromo(String),
+    #[command(description = "announce")]
+    Announce(String),
+    #[command(description = "restrict")]
+    Restrict(String),
+    #[command(description = "unrestrict")]
+    Unrestrict(String),
+    #[command(description = "counters")]
+    Counters,
+    #[command(description = "suspicious")]
+    Suspicious,
+    #[command(description = "review")]
+    Review(String),
+}
+
+pub fn is_operator(msg: Message, config: config::AppConfig) -> bool {
+    msg.chat.is_private()
+        && msg
+            .from
+            .as_ref()
+            .is_some_and(|user| config.is_operator(user.id))
+}
+
+pub async fn cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: OperatorCommands,
+    repos: repo::Repositories,
+    config: config::AppConfig,
+) -> HandlerResult {
+    let operator = msg
+        .from
+        .as_ref()
+        .ok_or(anyhow!("unexpected absence of a FROM field"))?;
+    let lang_code = LanguageCode::from_user(operator);
+    log::info!("the operator {} invoked {:?}", operator.id, msg.text());
+
+    let answer = match cmd {
+        OperatorCommands::Operator => help_impl(&lang_code),
+        OperatorCommands::NewPromo(args) => new_promo_impl(&repos, &lang_code, &args).await?,
+        OperatorCommands::DisablePromo(code) => {
+            disable_promo_impl(&repos, &lang_code, code.trim()).await?
+        }
+        OperatorCommands::Announce(args) => {
+            announce_impl(&repos, &config, &lang_code, operator.id, &args).await?
+        }
+        OperatorCommands::Restrict(args) => {
+            restrict_impl(&repos, &lang_code, operator.id, &args).await?
+        }
+        OperatorCommands::Unrestrict(uid) => {
+            unrestrict_impl(&repos, &config, &lang_code, uid.trim()).await?
+        }
+        OperatorCommands::Counters => counters_impl(&repos, &lang_code).await?,
+        OperatorCommands::Suspicious => suspicious_impl(&repos, &config, &lang_code).await?,
+        OperatorCommands::Review(args) => {
+            review_impl(&repos, &lang_code, operator.id, &args).await?
+        }
+    };
+    reply_html!(bot, msg, answer);
+    Ok(())
+}
+
+fn help_impl(lang_code: &LanguageCode) -> String {
+    let commands = OperatorCommands::bot_commands()
+        .into_iter()
+        .map(|cmd| {
+            let t_key = format!("commands.{}.description", cmd.description);
+            let command = cmd.command.trim_start_matches('/');
+            format!("/{command} — {}", t!(&t_key, locale = lang_code))
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    t!(
+        "commands.operator.help",
+        locale = lang_code,
+        commands = commands
+    )
+    .to_string()
+}
+
+async fn new_promo_impl(
+    repos: &repo::Repositories,
+    lang_code: &LanguageCode,
+    args: &str,
+) -> anyhow::Result<String> {
+    let params = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [code, bonus_length, capacity] if PROMO_CODE_FORMAT_REGEXP.is_match(code) => {
+            match (bonus_length.parse::<u32>(), capacity.parse::<u32>()) {
+                (Ok(bonus_length), Ok(capacity)) if bonus_length > 0 && capacity > 0 => {
+                    Some(PromoCodeParams {
+                        code: code.to_owned(),
+                        bonus_length,
+                        capacity,
+                    })
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    let Some(params) = params else {
+        return Ok(t!("commands.newpromo.usage", locale = lang_code).to_string());
+    };
+
+    let (code, bonus, capacity) = (params.code.clone(), params.bonus_length, params.capacity);
+    let answer = if repos.promo.create(params).await? {
+        t!(
+            "commands.newpromo.success",
+            locale = lang_code,
+            code = code,
+            bonus = bonus,
+            capacity = capacity
+        )
+    } else {
+        t!(
+            "commands.newpromo.already_exists",
+            locale = lang_code,
+            code = code
+        )
+    };
+    Ok(answer.to_string())
+}
+
+async fn disable_promo_impl(
+    repos: &repo::Repositories,
+    lang_code: &LanguageCode,
+    code: &str,
+) -> anyhow::Result<String> {
+    if !PROMO_CODE_FORMAT_REGEXP.is_match(code) {
+        return Ok(t!("commands.disablepromo.usage", locale = lang_code).to_string());
+    }
+    let answer = if repos.promo.disable(code).await? {
+        t!(
+            "commands.disablepromo.success",
+            locale = lang_code,
+            code = code
+        )
+    } else {
+        t!(
+            "commands.disablepromo.not_found",
+            locale = lang_code,
+            code = code
+        )
+    };
+    Ok(answer.to_string())
+}
+
+async fn announce_impl(
+    repos: &repo::Repositories,
+    config: &config::AppConfig,
+    lang_code: &LanguageCode,
+    operator: UserId,
+    args: &str,
+) -> anyhow::Result<String> {
+    let (language, text) = args
+        .trim()
+        .split_once(char::is_whitespace)
+        .map(|(language, text)| (language, text.trim()))
+        .unwrap_or((args.trim(), ""));
+    let Ok(language) = SupportedLanguage::from_str(language) else {
+        let languages = SupportedLanguage::iter()
+            .map(|lang| format!("<code>{}</code>", lang.as_ref()))
+            .collect::<Vec<String>>()
+            .join(", ");
+        return Ok(t!(
+            "commands.announce.usage",
+            locale = lang_code,
+            languages = languages
+        )
+        .to_string());
+    };
+    let lang = language.as_ref();
+
+    let answer = match text {
+        "" => {
+            let current = match repos.announcements.get_text(language).await? {
+                Some(text) => Some(text).filter(|text| !text.is_empty()),
+                None => config
+                    .announcements
+                    .get(&language.into())
+                    .map(|announcement| (*announcement.text).clone()),
+            };
+            match current {
+                Some(text) => t!(
+                    "commands.announce.current",
+                    locale = lang_code,
+                    language = lang,
+                    text = text
+                ),
+                None => t!(
+                    "commands.announce.none",
+                    locale = lang_code,
+                    language = lang
+                ),
+            }
+        }
+        ANNOUNCEMENT_DISABLE_KEYWORD => {
+            repos.announcements.set_text(language, "", operator).await?;
+            t!(
+                "commands.announce.disabled",
+                locale = lang_code,
+                language = lang
+            )
+        }
+        ANNOUNCEMENT_RESET_KEYWORD => {
+            repos.announcements.reset_text(language).await?;
+            t!(
+                "commands.announce.reset",
+                locale = lang_code,
+                language = lang
+            )
+        }
+        text => {
+            repos
+                .announcements
+                .set_text(language, text, operator)
+                .await?;
+            t!(
+                "commands.announce.success",
+                locale = lang_code,
+                language = lang
+            )
+        }
+    };
+    Ok(answer.to_string())
+}
+
+async fn restrict_impl(
+    repos: &repo::Repositories,
+    lang_code: &LanguageCode,
+    operator: UserId,
+    args: &str,
+) -> anyhow::Result<String> {
+    let parsed = args
+        .trim()
+        .split_once(char::is_whitespace)
+        .and_then(|(uid, name)| {
+            uid.parse::<u64>()
+                .ok()
+                .map(|uid| (UserId(uid), name.trim()))
+        })
+        .filter(|(_, name)| !name.is_empty());
+    let Some((uid, name)) = parsed else {
+        return Ok(t!("commands.restrict.usage", locale = lang_code).to_string());
+    };
+    repos.gift_restrictions.set(uid, name, operator).await?;
+    Ok(t!(
+        "commands.restrict.success",
+        locale = lang_code,
+        uid = uid,
+        name = html::escape(name)
+    )
+    .to_string())
+}
+
+async fn unrestrict_impl(
+    repos: &repo::Repositories,
+    config: &config::AppConfig,
+    lang_code: &LanguageCode,
+    uid: &str,
+) -> anyhow::Result<String> {
+    let Ok(uid) = uid.parse::<u64>().map(UserId) else {
+        return Ok(t!("commands.unrestrict.usage", locale = lang_code).to_string());
+    };
+    let answer = if repos.gift_restrictions.remove(uid).await? {
+        t!("commands.unrestrict.success", locale = lang_code, uid = uid)
+    } else if config.gift_restriction.restrictions.contains_key(&uid.0) {
+        t!("commands.unrestrict.in_file", locale = lang_code, uid = uid)
+    } else {
+        t!(
+            "commands.unrestrict.not_found",
+            locale = lang_code,
+            uid = uid
+        )
+    };
+    Ok(answer.to_string())
+}
+
+async fn counters_impl(
+    repos: &repo::Repositories,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<String> {
+    let stats = repos.global_stats.get().await?;
+    Ok(t!(
+        "commands.counters.result",
+        locale = lang_code,
+        users = stats.users,
+        chats = stats.chats,
+        dicks = stats.dicks,
+        active_today = stats.active_today,
+        battles = stats.battles,
+        active_loans = stats.active_loans,
+        promo_activations = stats.promo_activations
+    )
+    .to_string())
+}
+
+/// Runs the detector of alt accounts periodically so that the flags are there to freeze transfers
+/// before an operator asks for the report.
+pub fn spawn_collusion_detector(config: config::ReloadableConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(COLLUSION_DETECTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            let snapshot = config.get();
+            let collusion_config = snapshot.app_config.collusion;
+            if collusion_config.window_days == 0 {
+                continue;
+            }
+            match snapshot.repos.collusion.detect(&collusion_config).await {
+                Ok(0) => {}
+                Ok(flagged) => {
+                    log::info!("{flagged} collusion flag(s) have been raised or changed")
+                }
+                Err(e) => log::error!("couldn't detect collusion: {e:?}"),
+            }
+        }
+    });
+}
+
+async fn suspicious_impl(
+    repos: &repo::Repositories,
+    config: &config::AppConfig,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<String> {
+    if config.collusion.window_days > 0 {
+        repos.collusion.detect(&config.collusion).await?;
+    }
+    let flags = repos
+        .collusion
+        .get_pending_flags(SUSPICIOUS_REPORT_LIMIT)
+        .await?;
+    if flags.is_empty() {
+        return Ok(t!("commands.suspicious.none", locale = lang_code).to_string());
+    }
+    let lines = flags
+        .iter()
+        .map(|flag| format_flag(flag, lang_code))
+        .collect::<Vec<String>>()
+        .join("\n");
+    Ok(t!(
+        "commands.suspicious.result",
+        locale = lang_code,
+        flags = lines
+    )
+    .to_string())
+}
+
+fn format_flag(flag: &CollusionFlag, lang_code: &LanguageCode) -> String {
+    let chat = flag
+        .chat_id
+        .map(|id| id.to_string())
+        .or_else(|| flag.chat_instance.clone())
+        .unwrap_or_default();
+    let suspect = format!("{} (<code>{}</code>)", flag.name.escaped(), flag.uid);
+    let counterpart = match (&flag.counterpart_name, flag.counterpart_uid) {
+        (Some(name), Some(uid)) => format!("{} (<code>{uid}</code>)", name.escaped()),
+        _ => String::new(),
+    };
+    let t_key = format!("commands.suspicious.kinds.{}", flag.kind);
+    let mut line = t!(
+        &t_key,
+        locale = lang_code,
+        id = flag.id,
+        chat = chat,
+        suspect = suspect,
+        counterpart = counterpart,
+        amount = flag.amount,
+        count = flag.occurrences
+    )
+    .to_string();
+    if flag.confirmed {
+        line.push_str(&t!("commands.suspicious.confirmed", locale = lang_code));
+    }
+    if flag.held > 0 {
+        line.push_str(&t!(
+            "commands.suspicious.held",
+            locale = lang_code,
+            amount = flag.held
+        ));
+    }
+    line
+}
+
+async fn review_impl(
+    repos: &repo::Repositories,
+    lang_code: &LanguageCode,
+    operator: UserId,
+    args: &str,
+) -> anyhow::Result<String> {
+    let parsed = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [id, "confirm"] => id
+            .parse::<i64>()
+            .ok()
+            .map(|id| (id, ReviewVerdict::Confirm)),
+        [id, "dismiss"] => id
+            .parse::<i64>()
+            .ok()
+            .map(|id| (id, ReviewVerdict::Dismiss)),
+        _ => None,
+    };
+    let Some((id, verdict)) = parsed else {
+        return Ok(t!("commands.review.usage", locale = lang_code).to_string());
+    };
+    let answer = match repos.collusion.review(id, verdict, operator).await? {
+        Some(outcome) if verdict == ReviewVerdict::Confirm => t!(
+            "commands.review.confirmed",
+            locale = lang_code,
+            id = id,
+            amount = outcome.amount,
+            count = outcome.transfers
+        ),
+        Some(outcome) => t!(
+            "commands.review.dismissed",
+            locale = lang_code,
+            id = id,
+            amount = outcome.amount,
+            count = outcome.transfers
+        ),
+        None => t!("commands.review.not_found", locale = lang_code, id = id),
+    };
+    Ok(answer.to_string())
+}
@@ -1,18 +1,28 @@
-use crate::handlers::utils::{AdditionalChange, ChangeIntent, ConfigurablePerk, DickId, Perk};
+use crate::handlers::utils::{
+    AdditionalChange, ChangeIntent, ConfigurablePerk, DickId, IncrementKind, Perk,
+};
 use crate::{config, repo};
 use async_trait::async_trait;
 use num_traits::ToPrimitive;
 use sqlx::{Pool, Postgres};
 
 pub fn all(pool: &Pool<Postgres>, cfg: &config::AppConfig) -> Vec<Box<dyn Perk>> {
-    let help_pussies_coef = config::get_env_value_or_default("HELP_PUSSIES_COEF", 0.0);
+    let help_pussies_coef = config::get_setting("HELP_PUSSIES_COEF");
     let loans = repo::Loans::new(pool.clone(), cfg);
+    let dicks = repo::Dicks::new(pool.clone(), cfg.features);
 
     vec![
         Box::new(HelpPussiesPerk {
             coefficient: help_pussies_coef,
         }),
         Box::new(LoanPayoutPerk { loans }),
+        Box::new(GrowStreakPerk {
+            dicks,
+            config: GrowStreakConfig {
+                days_per_cm: config::get_setting("GROW_STREAK_DAYS_PER_CM"),
+                max_bonus: config::get_setting("GROW_STREAK_MAX_BONUS"),
+            },
+        }),
     ]
 }
 
@@ -94,10 +104,60 @@ impl Perk for LoanPayoutPerk {
     }
 }
 
+/// Grants an extra centimeter for every `days_per_cm` consecutive days of growing.
+pub struct GrowStreakPerk {
+    dicks: repo::Dicks,
+    config: GrowStreakConfig,
+}
+
+#[derive(Copy, Clone)]
+pub struct GrowStreakConfig {
+    pub days_per_cm: u16,
+    pub max_bonus: u16,
+}
+
+#[async_trait]
+impl Perk for GrowStreakPerk {
+    fn name(&self) -> &str {
+        "grow-streak"
+    }
+
+    async fn apply(&self, dick_id: &DickId, _: ChangeIntent) -> AdditionalChange {
+        let streak = match self.dicks.fetch_grow_streak(dick_id.0, &dick_id.1).await {
+            Ok(streak) => streak,
+            Err(e) => {
+                log::error!("couldn't fetch the grow streak ({dick_id}): {e}");
+                return AdditionalChange(0);
+            }
+        };
+        // the streak is increased by the growth being calculated
+        let next_streak = streak.current + 1;
+        let bonus = (next_streak / i32::from(self.config.days_per_cm))
+            .min(i32::from(self.config.max_bonus));
+        AdditionalChange(bonus)
+    }
+
+    fn applicable_to(&self, kind: IncrementKind) -> bool {
+        kind == IncrementKind::Growth
+    }
+
+    fn enabled(&self) -> bool {
+        self.config.days_per_cm > 0 && self.config.max_bonus > 0
+    }
+}
+
+impl ConfigurablePerk for GrowStreakPerk {
+    type Config = GrowStreakConfig;
+
+    fn get_config(&self) -> Self::Config {
+        self.config
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::handlers::perks::{HelpPussiesPerk, LoanPayoutPerk};
-    use crate::handlers::utils::{ChangeIntent, DickId, Perk};
+    use crate::handlers::perks::{GrowStreakConfig, GrowStreakPerk, HelpPussiesPerk, LoanPayoutPerk};
+    use crate::handlers::utils::{ChangeIntent, DickId, IncrementKind, Perk};
     use crate::repo::test::{start_postgres, CHAT_ID_KIND, USER_ID};
     use crate::{config, repo};
 
@@ -156,7 +216,7 @@ mod test {
         {
             let users = repo::Users::new(db.clone());
             users
-                .create_or_update(USER_ID, "")
+                .create_or_update(USER_ID, "", None)
                 .await
                 .expect("couldn't create a user");
 
@@ -231,4 +291,66 @@ mod test {
             .debt;
         assert_eq!(debt, 9);
     }
+
+    #[tokio::test]
+    async fn test_grow_streak() {
+        let (_container, db) = start_postgres().await;
+        let dicks = repo::Dicks::new(db.clone(), Default::default());
+        let perk = GrowStreakPerk {
+            dicks: dicks.clone(),
+            config: GrowStreakConfig {
+                days_per_cm: 2,
+                max_bonus: 2,
+            },
+        };
+        let dick_id = DickId(USER_ID, CHAT_ID_KIND);
+        let change_intent = ChangeIntent {
+            current_length: 0,
+            base_increment: 1,
+        };
+
+        assert!(perk.enabled());
+        assert!(perk.applicable_to(IncrementKind::Growth));
+        assert!(!perk.applicable_to(IncrementKind::DickOfDay));
+
+        // the first day of the streak
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0, 0);
+
+        repo::Users::new(db.clone())
+            .create_or_update(USER_ID, "", None)
+            .await
+            .expect("couldn't create a user");
+        dicks
+            .create_or_grow(USER_ID, &CHAT_ID_KIND.into(), 1)
+            .await
+            .expect("couldn't create a dick");
+        let streak = dicks
+            .fetch_grow_streak(USER_ID, &CHAT_ID_KIND)
+            .await
+            .expect("couldn't fetch the streak");
+        assert_eq!((streak.current, streak.max), (1, 1));
+
+        // the second day
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0, 1);
+
+        set_grow_streak(&db, 10, 1).await;
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0, 2, "the bonus must be limited");
+
+        set_grow_streak(&db, 10, 2).await;
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0, 0, "the streak must be broken");
+        let streak = dicks
+            .fetch_grow_streak(USER_ID, &CHAT_ID_KIND)
+            .await
+            .expect("couldn't fetch the streak");
+        assert_eq!((streak.current, streak.max), (0, 10));
+    }
+
+    async fn set_grow_streak(db: &sqlx::Pool<sqlx::Postgres>, streak: i32, days_ago: i32) {
+        sqlx::query!("UPDATE Dicks SET grow_streak_current = $1, grow_streak_max = $1,
+                    grown_on = current_date - $2::integer, bonus_attempts = bonus_attempts + 1 WHERE uid = $3",
+            streak, days_ago, USER_ID.0 as i64)
+            .execute(db)
+            .await
+            .expect("couldn't set the grow streak");
+    }
 }
@@ -1,13 +1,27 @@
 use crate::domain::LanguageCode;
-use crate::domain::SupportedLanguage::{EN, RU};
-use crate::handlers::{reply_html, HandlerResult};
-use crate::{metrics, reply_html};
+use crate::domain::SupportedLanguage::{EN, FA, RU, ZH};
+use crate::handlers::utils::callbacks;
+use crate::handlers::utils::callbacks::{
+    CallbackDataWithPrefix, EditMessageReqParamsKind, InvalidCallbackData,
+    InvalidCallbackDataBuilder,
+};
+use crate::handlers::{reply_html, CallbackButton, HandlerImplResult, HandlerResult};
+use crate::{check_invoked_by_owner_and_get_answer_params, metrics, reply_html, repo};
+use anyhow::anyhow;
+use derive_more::Display;
+use rust_i18n::t;
 use teloxide::macros::BotCommands;
-use teloxide::prelude::Message;
+use teloxide::payloads::SendDocumentSetters;
+use teloxide::prelude::{CallbackQuery, Message, Requester, UserId};
+use teloxide::types::{InputFile, ReplyMarkup};
 use teloxide::Bot;
 
+const PERSONAL_DATA_FILE_NAME: &str = "personal_data.json";
+
 static EN_POLICY: &str = include_str!("en.html");
 static RU_POLICY: &str = include_str!("ru.html");
+static FA_POLICY: &str = include_str!("fa.html");
+static ZH_POLICY: &str = include_str!("zh.html");
 
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
@@ -22,7 +36,153 @@ pub async fn privacy_cmd_handler(bot: Bot, msg: Message) -> HandlerResult {
     let policy = match lang_code.to_supported_language() {
         RU => RU_POLICY,
         EN => EN_POLICY,
+        FA => FA_POLICY,
+        ZH => ZH_POLICY,
     };
     reply_html!(bot, msg, policy);
     Ok(())
 }
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum PersonalDataCommands {
+    #[command(description = "mydata")]
+    MyData,
+    #[command(description = "forgetme")]
+    ForgetMe,
+}
+
+pub async fn personal_data_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: PersonalDataCommands,
+    repos: repo::Repositories,
+) -> HandlerResult {
+    let from = msg
+        .from
+        .as_ref()
+        .ok_or(anyhow!("unexpected absence of a FROM field"))?;
+    let lang_code = LanguageCode::from_user(from);
+
+    // the data must never be exposed to other members of a group
+    if !msg.chat.is_private() {
+        let answer = t!("errors.private_chat_only", locale = &lang_code).to_string();
+        reply_html!(bot, msg, answer);
+        return Ok(());
+    }
+
+    match cmd {
+        PersonalDataCommands::MyData => match repos.personal_data.export(from.id).await? {
+            Some(data) => {
+                let json = serde_json::to_vec_pretty(&data)?;
+                let file = InputFile::memory(json).file_name(PERSONAL_DATA_FILE_NAME);
+                bot.send_document(msg.chat.id, file)
+                    .caption(t!("commands.mydata.caption", locale = &lang_code))
+                    .await?;
+            }
+            None => {
+                let answer = t!("commands.mydata.no_data", locale = &lang_code).to_string();
+                reply_html!(bot, msg, answer);
+            }
+        },
+        PersonalDataCommands::ForgetMe => {
+            let result = forget_me_impl(from.id, &lang_code);
+            let mut request = reply_html(bot, &msg, result.text());
+            request.reply_markup = result.keyboard().map(ReplyMarkup::InlineKeyboard);
+            request.await?;
+        }
+    }
+    Ok(())
+}
+
+fn forget_me_impl(uid: UserId, lang_code: &LanguageCode) -> HandlerImplResult<ForgetMeCallbackData> {
+    let btn_confirm = CallbackButton::new(
+        t!("commands.forgetme.confirmation.buttons.confirm", locale = lang_code).to_string(),
+        ForgetMeCallbackData {
+            uid,
+            action: ForgetMeCallbackAction::Confirmed,
+        },
+    );
+    let btn_cancel = CallbackButton::new(
+        t!("commands.forgetme.confirmation.buttons.cancel", locale = lang_code).to_string(),
+        ForgetMeCallbackData {
+            uid,
+            action: ForgetMeCallbackAction::Cancelled,
+        },
+    );
+    HandlerImplResult::WithKeyboard {
+        text: t!("commands.forgetme.confirmation.text", locale = lang_code).to_string(),
+        buttons: vec![btn_confirm, btn_cancel],
+    }
+}
+
+#[inline]
+pub fn forget_me_callback_filter(query: CallbackQuery) -> bool {
+    ForgetMeCallbackData::check_prefix(query)
+}
+
+pub async fn forget_me_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    repos: repo::Repositories,
+) -> HandlerResult {
+    let data = ForgetMeCallbackData::parse(&query)?;
+    let (answer, lang_code) = check_invoked_by_owner_and_get_answer_params!(bot, query, data.uid);
+
+    let text = match data.action {
+        ForgetMeCallbackAction::Confirmed => {
+            repos.users.delete_everything(data.uid).await?;
+            t!("commands.forgetme.success", locale = &lang_code)
+        }
+        ForgetMeCallbackAction::Cancelled => t!("commands.forgetme.cancelled", locale = &lang_code),
+    };
+    match callbacks::get_params_for_message_edit(&query)? {
+        EditMessageReqParamsKind::Chat(chat_id, message_id) => {
+            bot.edit_message_text(chat_id, message_id, text).await?;
+        }
+        EditMessageReqParamsKind::Inline { inline_message_id, .. } => {
+            bot.edit_message_text_inline(inline_message_id, text).await?;
+        }
+    }
+
+    answer.await?;
+    Ok(())
+}
+
+#[derive(Display)]
+#[display("{uid}:{action}")]
+pub(crate) struct ForgetMeCallbackData {
+    uid: UserId,
+    action: ForgetMeCallbackAction,
+}
+
+#[derive(Display)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub(crate) enum ForgetMeCallbackAction {
+    #[display("confirmed")]
+    Confirmed,
+    #[display("cancelled")]
+    Cancelled,
+}
+
+impl CallbackDataWithPrefix for ForgetMeCallbackData {
+    fn prefix() -> &'static str {
+        "forgetme"
+    }
+}
+
+impl TryFrom<String> for ForgetMeCallbackData {
+    type Error = InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.as_str().split(':');
+        let uid = callbacks::parse_part(&mut parts, &err, "uid").map(UserId)?;
+        let action = match parts.next().ok_or_else(|| err.missing_part("action"))? {
+            "confirmed" => ForgetMeCallbackAction::Confirmed,
+            "cancelled" => ForgetMeCallbackAction::Cancelled,
+            _ => return Err(err.split_err()),
+        };
+        Ok(Self { uid, action })
+    }
+}
@@ -15,7 +15,7 @@ use teloxide::Bot;
 
 pub(crate) const PROMO_START_PARAM_PREFIX: &str = "promo-";
 
-static PROMO_CODE_FORMAT_REGEXP: Lazy<regex::Regex> = Lazy::new(|| {
+pub(crate) static PROMO_CODE_FORMAT_REGEXP: Lazy<regex::Regex> = Lazy::new(|| {
     regex::Regex::new("^[a-zA-Z0-9_\\-]{4,16}$")
         .expect("promo code format regular expression must be valid")
 });
@@ -5,8 +5,10 @@ use crate::handlers::utils::callbacks::{
     CallbackDataWithPrefix, InvalidCallbackDataBuilder, NewLayoutValue,
 };
 use crate::handlers::utils::locks::LockCallbackServiceFacade;
+use crate::handlers::utils::Target;
 use crate::handlers::{
-    reply_html, send_error_callback_answer, utils, CallbackResult, HandlerResult,
+    reply_html, send_command_disabled_callback_answer, send_error_callback_answer, utils,
+    CallbackResult, HandlerResult,
 };
 use crate::repo::{BattleStats, ChatIdPartiality, GrowthResult, Repositories, WinRateAware};
 use crate::{metrics, reply_html, repo};
@@ -15,14 +17,16 @@ use futures::join;
 use rand::rngs::OsRng;
 use rand::Rng;
 use rust_i18n::t;
+use std::fmt::{Display, Formatter};
 use teloxide::macros::BotCommands;
 use teloxide::payloads::AnswerInlineQuerySetters;
 use teloxide::requests::Requester;
 use teloxide::types::{
     CallbackQuery, ChatId, ChosenInlineResult, InlineKeyboardButton, InlineKeyboardMarkup,
     InlineQuery, InlineQueryResult, InlineQueryResultArticle, InputMessageContent,
-    InputMessageContentText, Message, ParseMode, ReplyMarkup, User, UserId,
+    InputMessageContentText, Message, MessageEntityKind, ParseMode, ReplyMarkup, User, UserId,
 };
+use teloxide::utils::html;
 use teloxide::Bot;
 
 // let's calculate time offsets from 22.06.2024
@@ -58,26 +62,38 @@ impl BattleCommands {
     }
 }
 
-#[derive(derive_more::Display)]
-#[display("{initiator}:{bet}:{timestamp}")]
 pub(crate) struct BattleCallbackData {
     initiator: UserId,
     bet: u16,
 
     // used to prevent repeated clicks on the same button
     timestamp: NewLayoutValue<i64>,
+
+    // the only user who may accept a targeted battle
+    target: Option<UserId>,
 }
 
 impl BattleCallbackData {
-    fn new(initiator: UserId, bet: u16) -> Self {
+    fn new(initiator: UserId, bet: u16, target: Option<UserId>) -> Self {
         Self {
             initiator,
             bet,
             timestamp: new_short_timestamp(),
+            target,
         }
     }
 }
 
+impl Display for BattleCallbackData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.initiator, self.bet, self.timestamp)?;
+        if let Some(target) = self.target {
+            write!(f, ":{target}")?;
+        }
+        Ok(())
+    }
+}
+
 impl CallbackDataWithPrefix for BattleCallbackData {
     fn prefix() -> &'static str {
         "pvp"
@@ -93,10 +109,15 @@ impl TryFrom<String> for BattleCallbackData {
         let initiator = callbacks::parse_part(&mut parts, &err, "uid").map(UserId)?;
         let bet: u16 = callbacks::parse_part(&mut parts, &err, "bet")?;
         let timestamp = callbacks::parse_optional_part(&mut parts, &err)?;
+        let target = match callbacks::parse_optional_part(&mut parts, &err)? {
+            NewLayoutValue::Some(uid) => Some(UserId(uid)),
+            NewLayoutValue::None => None,
+        };
         Ok(Self {
             initiator,
             bet,
             timestamp,
+            target,
         })
     }
 }
@@ -115,16 +136,79 @@ pub async fn cmd_handler(
         .as_ref()
         .ok_or(anyhow!("no FROM field in the PVP command handler"))?
         .into();
+    start_battle(bot, &msg, repos, config, user, None, cmd.bet()).await
+}
+
+/// A battle may be aimed at a particular user by mentioning them: `/pvp @username 10`.
+/// Nobody else is able to accept such a challenge.
+pub fn is_targeted(msg: Message) -> bool {
+    msg.entities()
+        .unwrap_or_default()
+        .iter()
+        .any(|entity| matches!(entity.kind, MessageEntityKind::Mention | MessageEntityKind::TextMention { .. }))
+}
+
+pub async fn targeted_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    repos: Repositories,
+    config: AppConfig,
+) -> HandlerResult {
+    metrics::CMD_PVP_COUNTER.chat.inc();
+
+    let from = msg
+        .from
+        .as_ref()
+        .ok_or(anyhow!("no FROM field in the targeted PVP command handler"))?;
+    let lang_code = LanguageCode::from_user(from);
+    let (target, args) = utils::resolve_target(&repos, &msg).await?;
+    let bet = args.first().and_then(|arg| arg.parse::<u16>().ok());
+    let target_and_bet = match (target, bet) {
+        (Target::User(uid), _) if uid == from.id => {
+            Err(t!("commands.pvp.errors.same_person", locale = &lang_code))
+        }
+        (Target::User(uid), Some(bet)) => match repos.users.get(uid).await? {
+            Some(user) => Ok((UserInfo::from(user), bet)),
+            None => Err(t!("errors.user_not_found", locale = &lang_code, uid = uid.0)),
+        },
+        (Target::UnknownUsername(username), _) => Err(t!(
+            "errors.username_not_found",
+            locale = &lang_code,
+            username = html::escape(&username)
+        )),
+        _ => Err(t!("commands.pvp.errors.no_args", locale = &lang_code)),
+    };
+    match target_and_bet {
+        Ok((target, bet)) => start_battle(bot, &msg, repos, config, from.into(), Some(target), bet).await,
+        Err(text) => {
+            reply_html!(bot, msg, text);
+            Ok(())
+        }
+    }
+}
+
+async fn start_battle(
+    bot: Bot,
+    msg: &Message,
+    repos: Repositories,
+    config: AppConfig,
+    initiator: UserInfo,
+    target: Option<UserInfo>,
+    bet: u16,
+) -> HandlerResult {
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
     let lang_code = LanguageCode::from_maybe_user(msg.from.as_ref());
+    let lang_code = utils::get_chat_language(&repos, &chat_id.kind(), lang_code).await;
     let params = BattleParams {
         repos,
         features: config.features.pvp,
-        chat_id: msg.chat.id.into(),
+        log_battles: config.collusion.window_days > 0,
+        chat_id,
         lang_code,
     };
-    let (text, keyboard) = pvp_impl_start(params, user, cmd.bet()).await?;
+    let (text, keyboard) = pvp_impl_start(params, initiator, target, bet).await?;
 
-    let mut answer = reply_html(bot, &msg, text);
+    let mut answer = reply_html(bot, msg, text);
     answer.reply_markup = keyboard.map(ReplyMarkup::InlineKeyboard);
     answer.await?;
     Ok(())
@@ -190,7 +274,7 @@ pub(super) fn build_inline_keyboard_article_result(
     let content =
         InputMessageContent::Text(InputMessageContentText::new(text).parse_mode(ParseMode::Html));
     let btn_label = t!("commands.pvp.button", locale = lang_code);
-    let btn_data = BattleCallbackData::new(uid, bet).to_data_string();
+    let btn_data = BattleCallbackData::new(uid, bet, None).to_data_string();
     InlineQueryResultArticle::new("pvp", title, content)
         .reply_markup(InlineKeyboardMarkup::new(vec![vec![
             InlineKeyboardButton::callback(btn_label, btn_data),
@@ -239,6 +323,15 @@ pub async fn callback_handler(
     if callback_data.initiator == query.from.id {
         return send_error_callback_answer(bot, query, "commands.pvp.errors.same_person").await;
     }
+    if callback_data.target.is_some_and(|target| target != query.from.id) {
+        return send_error_callback_answer(bot, query, "commands.pvp.errors.not_target").await;
+    }
+    if repos.moderation.is_frozen(&chat_id.kind()).await? {
+        return send_error_callback_answer(bot, query, "errors.game_paused").await;
+    }
+    if repos.moderation.is_command_disabled(&chat_id.kind(), "pvp").await? {
+        return send_command_disabled_callback_answer(bot, query, "pvp").await;
+    }
     let _battle_guard = match battle_locker.try_lock(&callback_data) {
         Some(lock) => lock,
         None => {
@@ -251,10 +344,13 @@ pub async fn callback_handler(
         }
     };
 
+    let lang_code = LanguageCode::from_user(&query.from);
+    let lang_code = utils::get_chat_language(&repos, &chat_id.kind(), lang_code).await;
     let params = BattleParams {
         repos,
         features: config.features.pvp,
-        lang_code: LanguageCode::from_user(&query.from),
+        log_battles: config.collusion.window_days > 0,
+        lang_code,
         chat_id: chat_id.clone(),
     };
     let attack_result = pvp_impl_attack(
@@ -273,6 +369,8 @@ pub async fn callback_handler(
 pub(crate) struct BattleParams {
     repos: Repositories,
     features: BattlesFeatureToggles,
+    /// Battles are logged for the detector of alt accounts
+    log_battles: bool,
     chat_id: ChatIdPartiality,
     lang_code: LanguageCode,
 }
@@ -317,8 +415,12 @@ impl Into<UserId> for UserInfo {
 pub(crate) async fn pvp_impl_start(
     p: BattleParams,
     initiator: UserInfo,
+    target: Option<UserInfo>,
     bet: u16,
 ) -> anyhow::Result<(String, Option<InlineKeyboardMarkup>)> {
+    if p.repos.moderation.is_banned(&p.chat_id.kind(), initiator.uid).await? {
+        return Ok((t!("errors.banned", locale = &p.lang_code).to_string(), None));
+    }
     let enough = p
         .repos
         .dicks
@@ -331,15 +433,25 @@ pub(crate) async fn pvp_impl_start(
     );
 
     let data = if enough {
-        let text = t!(
-            "commands.pvp.results.start",
-            locale = &p.lang_code,
-            name = initiator.name.escaped(),
-            bet = bet
-        )
+        let text = match &target {
+            Some(target) => t!(
+                "commands.pvp.results.start_targeted",
+                locale = &p.lang_code,
+                name = initiator.name.escaped(),
+                target = target.name.escaped(),
+                bet = bet
+            ),
+            None => t!(
+                "commands.pvp.results.start",
+                locale = &p.lang_code,
+                name = initiator.name.escaped(),
+                bet = bet
+            ),
+        }
         .to_string();
         let btn_label = t!("commands.pvp.button", locale = &p.lang_code);
-        let btn_data = BattleCallbackData::new(initiator.uid, bet).to_data_string();
+        let btn_data = BattleCallbackData::new(initiator.uid, bet, target.map(|target| target.uid))
+            .to_data_string();
         let keyboard = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
             btn_label, btn_data,
         )]]);
@@ -364,6 +476,14 @@ async fn pvp_impl_attack(
     bet: u16,
 ) -> anyhow::Result<CallbackResult> {
     let chat_id_kind = p.chat_id.kind();
+    let (initiator_banned, acceptor_banned) = join!(
+        p.repos.moderation.is_banned(&chat_id_kind, initiator),
+        p.repos.moderation.is_banned(&chat_id_kind, acceptor.uid),
+    );
+    if initiator_banned? || acceptor_banned? {
+        let text = t!("commands.pvp.errors.banned", locale = &p.lang_code).to_string();
+        return Ok(CallbackResult::ShowError(text));
+    }
     let (enough_initiator, enough_acceptor) = join!(
         p.repos.dicks.check_dick(&chat_id_kind, initiator, bet),
         p.repos.dicks.check_dick(
@@ -390,6 +510,17 @@ async fn pvp_impl_attack(
             .move_length(&p.chat_id, loser, winner, bet)
             .await?;
 
+        if p.log_battles {
+            if let Err(e) = p
+                .repos
+                .collusion
+                .log_battle(&chat_id_kind, winner, loser, bet)
+                .await
+            {
+                log::error!("couldn't log the battle of {winner} and {loser}: {e:?}");
+            }
+        }
+
         let battle_stats = p
             .repos
             .pvp_stats
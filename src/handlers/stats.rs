@@ -57,7 +57,8 @@ async fn personal_stats_impl(
             locale = &lang_code,
             chats = stats.chats,
             max_length = stats.max_length,
-            total_length = stats.total_length
+            total_length = stats.total_length,
+            max_grow_streak = stats.max_grow_streak
         )
         .to_string()
     })
@@ -89,6 +90,16 @@ pub(crate) async fn chat_stats_impl(
         given_cm = personal.given_cm,
         received_cm = personal.received_cm
     );
+    let grow_streak = repos
+        .dicks
+        .fetch_grow_streak(from_refs.0.id, &from_refs.1.kind())
+        .await?;
+    let grow_streak_stats = t!(
+        "commands.stats.grow_streak",
+        locale = &lang_code,
+        current = grow_streak.current,
+        max = grow_streak.max
+    );
     let pvp_stats = repos
         .pvp_stats
         .get_stats(&from_refs.1.kind(), from_refs.0.id)
@@ -141,6 +152,6 @@ pub(crate) async fn chat_stats_impl(
 
     Ok(format!(
         // "{length_stats}\n\n{pvp_stats}\n\n{casino_stats}{notice}"
-        "{length_stats}\n\n{pvp_stats}{notice}"
+        "{length_stats}\n\n{grow_streak_stats}\n\n{pvp_stats}{notice}"
     ))
 }
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use teloxide::types::{Update, UserId};
+
+use crate::repo;
+
+/// The cache is dropped entirely once it grows that large, not to keep everyone the bot has ever seen.
+const MAX_CACHED_USERNAMES: usize = 100_000;
+
+/// Remembers the last @usernames written to the database, since `track_username` is called for every update.
+#[derive(Clone, Default)]
+pub struct UsernamesCache {
+    usernames: Arc<Mutex<HashMap<UserId, Option<String>>>>,
+}
+
+impl UsernamesCache {
+    fn is_known(&self, uid: UserId, username: Option<&str>) -> bool {
+        self.usernames.lock()
+            .expect("the cache of usernames is poisoned")
+            .get(&uid)
+            .is_some_and(|cached| cached.as_deref() == username)
+    }
+
+    fn remember(&self, uid: UserId, username: Option<&str>) {
+        let mut usernames = self.usernames.lock().expect("the cache of usernames is poisoned");
+        if usernames.len() >= MAX_CACHED_USERNAMES {
+            usernames.clear();
+        }
+        usernames.insert(uid, username.map(ToOwned::to_owned));
+    }
+}
+
+/// Keeps the @usernames of known users up to date, so that they could be targeted by mentions.
+/// Users unknown to the bot are not created here: it happens on their first command.
+pub async fn track_username(update: Update, repos: repo::Repositories, cache: UsernamesCache) {
+    let Some(user) = update.from().filter(|user| !user.is_bot) else {
+        return
+    };
+    let username = user.username.as_deref();
+    if cache.is_known(user.id, username) {
+        return
+    }
+    match repos.users.update_username(user.id, username).await {
+        Ok(()) => cache.remember(user.id, username),
+        Err(e) => log::error!("couldn't track the username of {}: {e}", user.id),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UsernamesCache;
+    use teloxide::types::UserId;
+
+    #[test]
+    fn test_cache() {
+        let cache = UsernamesCache::default();
+        let uid = UserId(1);
+        assert!(!cache.is_known(uid, None));
+
+        cache.remember(uid, Some("grower"));
+        assert!(cache.is_known(uid, Some("grower")));
+        assert!(!cache.is_known(uid, Some("another")));
+        assert!(!cache.is_known(uid, None));
+        assert!(!cache.is_known(UserId(2), Some("grower")));
+
+        cache.remember(uid, None);
+        assert!(cache.is_known(uid, None));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::config;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use teloxide::requests::Requester;
+use teloxide::types::{ChatId, Message, UserId};
+use teloxide::Bot;
+
+/// Decides whether a command is sent by an administrator of the chat. Lists of administrators are cached
+/// per chat for `CHAT_ADMINS_CACHE_TTL` seconds not to call the API on every command; 0 disables the cache.
+#[derive(Clone, Default)]
+pub struct ChatAdminsCache {
+    admins: Arc<Mutex<HashMap<ChatId, CachedAdmins>>>,
+}
+
+struct CachedAdmins {
+    fetched_at: Instant,
+    ids: Arc<HashSet<UserId>>,
+}
+
+impl ChatAdminsCache {
+    /// Returns the ID the action must be attributed to if the message is sent by an administrator, or None otherwise.
+    /// Besides administrators themselves, the following senders are authorized:
+    /// - anonymous administrators, who post on behalf of the group;
+    /// - posts of the channel linked to the group, since only administrators of the channel may publish them;
+    /// - operators of the bot.
+    ///
+    /// For the first two, the ID is the one of the Telegram's service account the message is sent by.
+    pub async fn authorize(&self, bot: &Bot, msg: &Message, config: &config::AppConfig) -> anyhow::Result<Option<UserId>> {
+        let Some(from) = msg.from.as_ref() else {
+            return Ok(None)
+        };
+        let authorized = match msg.sender_chat.as_ref() {
+            Some(sender_chat) if sender_chat.id == msg.chat.id => true,
+            Some(sender_chat) if sender_chat.is_channel() && msg.is_automatic_forward() => true,
+            // users may write on behalf of their own channels, which grants nothing
+            Some(_) => false,
+            None => config.is_operator(from.id)
+                || self.is_admin(bot, msg.chat.id, from.id, config.chat_admins_cache_ttl).await?,
+        };
+        Ok(authorized.then_some(from.id))
+    }
+
+    /// A zero `ttl` bypasses the cache.
+    pub async fn is_admin(&self, bot: &Bot, chat_id: ChatId, uid: UserId, ttl: Duration) -> anyhow::Result<bool> {
+        let admins = self.get_or_fetch(chat_id, ttl, async {
+            let ids: HashSet<UserId> = bot.get_chat_administrators(chat_id)
+                .await?
+                .into_iter()
+                .map(|member| member.user.id)
+                .collect();
+            Ok::<_, anyhow::Error>(ids)
+        }).await?;
+        Ok(admins.contains(&uid))
+    }
+
+    async fn get_or_fetch(
+        &self,
+        chat_id: ChatId,
+        ttl: Duration,
+        fetch: impl Future<Output = anyhow::Result<HashSet<UserId>>>,
+    ) -> anyhow::Result<Arc<HashSet<UserId>>> {
+        let cached = self.admins.lock()
+            .expect("the cache of administrators is poisoned")
+            .get(&chat_id)
+            .filter(|cached| cached.fetched_at.elapsed() < ttl)
+            .map(|cached| cached.ids.clone());
+        if let Some(ids) = cached {
+            return Ok(ids)
+        }
+
+        let ids = Arc::new(fetch.await?);
+        if !ttl.is_zero() {
+            let mut admins = self.admins.lock().expect("the cache of administrators is poisoned");
+            admins.retain(|_, cached| cached.fetched_at.elapsed() < ttl);
+            admins.insert(chat_id, CachedAdmins {
+                fetched_at: Instant::now(),
+                ids: ids.clone(),
+            });
+        }
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChatAdminsCache;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use teloxide::types::{ChatId, UserId};
+
+    #[tokio::test]
+    async fn test_cache() {
+        let cache = ChatAdminsCache::default();
+        let counter = AtomicUsize::new(0);
+        let fetches = &counter;
+        let fetch = || async move {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, anyhow::Error>(HashSet::from([UserId(1)]))
+        };
+        let (chat_id, ttl) = (ChatId(-1), Duration::from_secs(60));
+
+        for _ in 0..3 {
+            let admins = cache.get_or_fetch(chat_id, ttl, fetch()).await.expect("couldn't fetch the admins");
+            assert!(admins.contains(&UserId(1)));
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        cache.get_or_fetch(ChatId(-2), ttl, fetch()).await.expect("couldn't fetch the admins of another chat");
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+
+        // expired
+        cache.get_or_fetch(chat_id, Duration::ZERO, fetch()).await.expect("couldn't fetch the admins again");
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+}
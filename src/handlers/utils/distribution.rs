@@ -0,0 +1,182 @@
+use crate::config;
+use rand::{Rng, RngCore};
+use std::f64::consts::PI;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+#[derive(Copy, Clone, Default, derive_more::FromStr, derive_more::Display)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum GrowthDistributionKind {
+    #[default]
+    UNIFORM,
+    NORMAL,
+    EXPONENTIAL,
+    DIMINISHING,
+}
+
+/// Decides how big the base increment of a growth is. The sign is chosen by the incrementor beforehand,
+/// so a distribution samples the absolute value only.
+pub trait GrowthDistribution: Send + Sync {
+    /// Used as a part of the key of the description in the help message.
+    fn name(&self) -> &'static str;
+
+    /// Returns a value within the range whose bounds are both positive.
+    fn sample(&self, rng: &mut dyn RngCore, range: RangeInclusive<i32>, current_length: i32, growing: bool) -> i32;
+}
+
+pub fn distribution_from_env() -> Arc<dyn GrowthDistribution> {
+    match config::get_setting("GROWTH_DISTRIBUTION") {
+        GrowthDistributionKind::UNIFORM => Arc::new(UniformDistribution),
+        GrowthDistributionKind::NORMAL => Arc::new(NormalDistribution),
+        GrowthDistributionKind::EXPONENTIAL => Arc::new(ExponentialDistribution),
+        GrowthDistributionKind::DIMINISHING => Arc::new(DiminishingDistribution {
+            scale: config::get_setting("GROWTH_DIMINISHING_SCALE"),
+        }),
+    }
+}
+
+/// Every value of the range is equally likely.
+pub struct UniformDistribution;
+
+impl GrowthDistribution for UniformDistribution {
+    fn name(&self) -> &'static str {
+        "uniform"
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore, range: RangeInclusive<i32>, _: i32, _: bool) -> i32 {
+        rng.gen_range(range)
+    }
+}
+
+/// Values near the middle of the range are the most likely ones, the bounds are rare.
+pub struct NormalDistribution;
+
+impl GrowthDistribution for NormalDistribution {
+    fn name(&self) -> &'static str {
+        "normal"
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore, range: RangeInclusive<i32>, _: i32, _: bool) -> i32 {
+        let (start, end) = (*range.start(), *range.end());
+        let mean = f64::from(start + end) / 2.0;
+        // ~95% of the values are within the range without clamping
+        let std_dev = f64::from(end - start) / 4.0;
+        // the Box-Muller transform; the first value must not be zero to take a logarithm of it
+        let (u1, u2) = (1.0 - rng.gen::<f64>(), rng.gen::<f64>());
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+        ((mean + z * std_dev).round() as i32).clamp(start, end)
+    }
+}
+
+/// Small values are frequent, big ones are rare.
+pub struct ExponentialDistribution;
+
+impl GrowthDistribution for ExponentialDistribution {
+    fn name(&self) -> &'static str {
+        "exponential"
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore, range: RangeInclusive<i32>, _: i32, _: bool) -> i32 {
+        let (start, end) = (*range.start(), *range.end());
+        let span = f64::from(end - start + 1);
+        let mean = span / 3.0;
+        // the inverse of the CDF truncated to the span, so no values are piled up at the upper bound
+        let u = rng.gen::<f64>();
+        let offset = -mean * (1.0 - u * (1.0 - (-span / mean).exp())).ln();
+        (start + offset.floor() as i32).clamp(start, end)
+    }
+}
+
+/// Uniform, but the growth of long dicks is divided by `1 + length / scale`.
+/// Shrinking is not affected.
+pub struct DiminishingDistribution {
+    pub scale: u16,
+}
+
+impl GrowthDistribution for DiminishingDistribution {
+    fn name(&self) -> &'static str {
+        "diminishing"
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore, range: RangeInclusive<i32>, current_length: i32, growing: bool) -> i32 {
+        let start = *range.start();
+        let value = rng.gen_range(range);
+        if !growing || current_length <= 0 {
+            return value;
+        }
+        let divisor = 1.0 + f64::from(current_length) / f64::from(self.scale.max(1));
+        ((f64::from(value) / divisor).round() as i32).max(start)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        DiminishingDistribution, ExponentialDistribution, GrowthDistribution,
+        NormalDistribution, UniformDistribution,
+    };
+    use rand::rngs::OsRng;
+
+    const SAMPLES: usize = 10_000;
+
+    fn sample_many(distribution: &dyn GrowthDistribution, current_length: i32) -> Vec<i32> {
+        (0..SAMPLES)
+            .map(|_| distribution.sample(&mut OsRng, 1..=10, current_length, true))
+            .collect()
+    }
+
+    fn count(values: &[i32], range: std::ops::RangeInclusive<i32>) -> usize {
+        values.iter().filter(|v| range.contains(v)).count()
+    }
+
+    #[test]
+    fn test_all_within_range() {
+        let distributions: [&dyn GrowthDistribution; 4] = [
+            &UniformDistribution,
+            &NormalDistribution,
+            &ExponentialDistribution,
+            &DiminishingDistribution { scale: 10 },
+        ];
+        for distribution in distributions {
+            let values = sample_many(distribution, 100);
+            assert!(values.iter().all(|v| (1..=10).contains(v)), "{}", distribution.name());
+            assert_eq!(distribution.sample(&mut OsRng, 3..=3, 100, true), 3, "{}", distribution.name());
+        }
+    }
+
+    #[test]
+    fn test_uniform() {
+        let values = sample_many(&UniformDistribution, 0);
+        assert!((1..=10).all(|n| count(&values, n..=n) > SAMPLES / 20));
+    }
+
+    #[test]
+    fn test_normal() {
+        let values = sample_many(&NormalDistribution, 0);
+        let middle = count(&values, 5..=6);
+        let edges = count(&values, 1..=1) + count(&values, 10..=10);
+        assert!(middle > edges * 2, "middle={middle}, edges={edges}");
+    }
+
+    #[test]
+    fn test_exponential() {
+        let values = sample_many(&ExponentialDistribution, 0);
+        let small = count(&values, 1..=3);
+        let big = count(&values, 8..=10);
+        assert!(small > big * 3, "small={small}, big={big}");
+        assert!(big > 0);
+    }
+
+    #[test]
+    fn test_diminishing() {
+        let distribution = DiminishingDistribution { scale: 10 };
+        let short: i32 = sample_many(&distribution, 0).iter().sum();
+        let long: i32 = sample_many(&distribution, 90).iter().sum();
+        assert!(long * 4 < short, "short={short}, long={long}");
+
+        let shrinking: i32 = (0..SAMPLES)
+            .map(|_| distribution.sample(&mut OsRng, 1..=10, 90, false))
+            .sum();
+        assert!(shrinking > long * 4, "shrinking must not be affected");
+    }
+}
@@ -1,10 +1,10 @@
+use crate::handlers::utils::distribution::{distribution_from_env, GrowthDistribution};
 use crate::repo::ChatIdKind;
-use crate::{config, repo};
+use crate::{config, metrics, repo};
 use async_trait::async_trait;
 use derive_more::Display;
 use downcast_rs::{impl_downcast, Downcast};
 use num_traits::{PrimInt, Zero};
-use rand::distributions::uniform::SampleUniform;
 use rand::rngs::OsRng;
 use rand::Rng;
 use rust_i18n::t;
@@ -24,8 +24,33 @@ pub struct Incrementor {
 pub struct Config {
     growth_range: RangeInclusive<i16>,
     grow_shrink_ratio: f32,
+    distribution: Arc<dyn GrowthDistribution>,
     newcomers_grace_days: u32,
     dod_bonus_range: RangeInclusive<u8>,
+    events: GrowthEventsConfig,
+}
+
+/// Rare events replacing the base increment of a growth. Only one event may happen at once,
+/// so the sum of their chances must not exceed 1.
+#[derive(Copy, Clone, Default)]
+pub struct GrowthEventsConfig {
+    pub critical_chance: f64,
+    pub critical_multiplier: u16,
+    pub catastrophe_chance: f64,
+    pub catastrophe_loss_ratio: f64,
+    pub jackpot_chance: f64,
+    pub jackpot_amount: u16,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, strum_macros::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum GrowthEvent {
+    /// The base increment is multiplied and always positive.
+    Critical,
+    /// A part of the current length is lost.
+    Catastrophe,
+    /// A fixed large amount is granted.
+    Jackpot,
 }
 
 #[async_trait]
@@ -33,6 +58,10 @@ pub trait Perk: Send + Sync + Downcast {
     fn name(&self) -> &str;
     async fn apply(&self, dick_id: &DickId, change_intent: ChangeIntent) -> AdditionalChange;
 
+    fn applicable_to(&self, _kind: IncrementKind) -> bool {
+        true
+    }
+
     fn enabled(&self) -> bool {
         let env_key = format!("DISABLE_{}", self.name().to_uppercase().replace('-', "_"));
         !config::get_env_value_or_default(&env_key, false)
@@ -50,6 +79,12 @@ pub trait ConfigurablePerk: Perk {
 #[display("(user_id={_0}, chat_id={_1}")]
 pub struct DickId(pub(crate) UserId, pub(crate) ChatIdKind);
 
+#[derive(Copy, Clone, PartialEq)]
+pub enum IncrementKind {
+    Growth,
+    DickOfDay,
+}
+
 #[derive(Copy, Clone)]
 pub struct ChangeIntent {
     pub current_length: i32,
@@ -63,6 +98,7 @@ pub struct Increment<T: PrimInt + std::fmt::Display> {
     pub base: T,
     pub by_perks: HashMap<String, i32>,
     pub total: T,
+    pub event: Option<GrowthEvent>,
 }
 
 pub type SignedIncrement = Increment<i32>;
@@ -76,13 +112,17 @@ impl Config {
     pub fn growth_range_max(&self) -> i16 {
         self.growth_range.clone().max().unwrap_or(0)
     }
+
+    pub fn growth_distribution_name(&self) -> &'static str {
+        self.distribution.name()
+    }
 }
 
 impl Incrementor {
     pub fn from_env(dicks: &repo::Dicks, perks: Vec<Box<dyn Perk>>) -> Self {
-        let growth_range_min = config::get_env_value_or_default("GROWTH_MIN", -5);
-        let growth_range_max = config::get_env_value_or_default("GROWTH_MAX", 10);
-        let dod_max_bonus = config::get_env_value_or_default("GROWTH_DOD_BONUS_MAX", 5);
+        let growth_range_min = config::get_setting("GROWTH_MIN");
+        let growth_range_max = config::get_setting("GROWTH_MAX");
+        let dod_max_bonus = config::get_setting("GROWTH_DOD_BONUS_MAX");
 
         let perks = perks
             .into_iter()
@@ -93,9 +133,18 @@ impl Incrementor {
         Self {
             config: Config {
                 growth_range: growth_range_min..=growth_range_max,
-                grow_shrink_ratio: config::get_env_value_or_default("GROW_SHRINK_RATIO", 0.5),
-                newcomers_grace_days: config::get_env_value_or_default("NEWCOMERS_GRACE_DAYS", 7),
+                grow_shrink_ratio: config::get_setting("GROW_SHRINK_RATIO"),
+                distribution: distribution_from_env(),
+                newcomers_grace_days: config::get_setting("NEWCOMERS_GRACE_DAYS"),
                 dod_bonus_range: 1..=dod_max_bonus,
+                events: GrowthEventsConfig {
+                    critical_chance: config::get_setting("GROWTH_CRITICAL_CHANCE"),
+                    critical_multiplier: config::get_setting("GROWTH_CRITICAL_MULTIPLIER"),
+                    catastrophe_chance: config::get_setting("GROWTH_CATASTROPHE_CHANCE"),
+                    catastrophe_loss_ratio: config::get_setting("GROWTH_CATASTROPHE_LOSS_RATIO"),
+                    jackpot_chance: config::get_setting("GROWTH_JACKPOT_CHANCE"),
+                    jackpot_amount: config::get_setting("GROWTH_JACKPOT_AMOUNT"),
+                },
             },
             perks,
             dicks: dicks.clone(),
@@ -126,30 +175,36 @@ impl Incrementor {
         days_since_registration: u32,
     ) -> SignedIncrement {
         let dick_id = DickId(user_id, chat_id);
-        let grow_shrink_ratio = if days_since_registration > self.config.newcomers_grace_days {
-            self.config.grow_shrink_ratio
-        } else {
+        let newcomer = days_since_registration <= self.config.newcomers_grace_days;
+        let grow_shrink_ratio = if newcomer {
             1.0
+        } else {
+            self.config.grow_shrink_ratio
         };
-        let base_incr = get_base_increment(self.config.growth_range.clone(), grow_shrink_ratio);
-        self.add_additional_incr(dick_id, BaseIncrement(base_incr))
+        let range = i32::from(self.config.growth_range_min())..=i32::from(self.config.growth_range_max());
+        let get_base_incr = |current_length| {
+            get_base_increment(self.config.distribution.as_ref(), range, grow_shrink_ratio, current_length)
+        };
+        self.add_additional_incr(dick_id, get_base_incr, IncrementKind::Growth, newcomer)
             .await
     }
 
     pub async fn dod_increment(&self, user_id: UserId, chat_id: ChatIdKind) -> UnsignedIncrement {
         let dick_id = DickId(user_id, chat_id);
-        let base_incr = OsRng.gen_range(self.config.dod_bonus_range.clone());
-        self.add_additional_incr(dick_id, BaseIncrement(base_incr))
+        let get_base_incr = |_| OsRng.gen_range(self.config.dod_bonus_range.clone());
+        self.add_additional_incr(dick_id, get_base_incr, IncrementKind::DickOfDay, false)
             .await
     }
 
     async fn add_additional_incr<T, R>(
         &self,
         dick: DickId,
-        base_increment: BaseIncrement<T>,
+        get_base_increment: impl FnOnce(i32) -> T,
+        kind: IncrementKind,
+        newcomer: bool,
     ) -> Increment<R>
     where
-        T: PrimInt + std::fmt::Display + Into<i16>,
+        T: PrimInt + std::fmt::Display + Into<i32>,
         R: PrimInt + std::fmt::Display + From<T> + TryFrom<i32>,
         <R as TryFrom<i32>>::Error: std::fmt::Display,
     {
@@ -157,17 +212,34 @@ impl Incrementor {
             Ok(length) => length,
             Err(e) => {
                 log::error!("couldn't fetch the length of a dick: {e}");
-                return base_increment.only();
+                return BaseIncrement(get_base_increment(0)).only();
             }
         };
+        let base_increment = BaseIncrement(get_base_increment(current_length));
+        let event = match kind {
+            IncrementKind::Growth => self.config.events.roll(&mut OsRng).and_then(|event| {
+                self.config.events
+                    .apply(event, base_increment.i32(), current_length, newcomer)
+                    .map(|value| (event, value))
+            }),
+            IncrementKind::DickOfDay => None,
+        };
+        if let Some((event, value)) = event {
+            log::info!("the growth event {event} has happened for {dick}: {value}");
+            match event {
+                GrowthEvent::Critical => metrics::GROWTH_EVENT_COUNTER.critical.inc(),
+                GrowthEvent::Catastrophe => metrics::GROWTH_EVENT_COUNTER.catastrophe.inc(),
+                GrowthEvent::Jackpot => metrics::GROWTH_EVENT_COUNTER.jackpot.inc(),
+            }
+        }
         let change_intent = ChangeIntent {
-            base_increment: base_increment.i32(),
+            base_increment: event.map_or(base_increment.i32(), |(_, value)| value),
             current_length,
         };
 
         let mut additional_change = 0;
         let mut by_perks = HashMap::new();
-        for perk in self.perks.iter() {
+        for perk in self.perks.iter().filter(|perk| perk.applicable_to(kind)) {
             let AdditionalChange(ac) = perk.apply(&dick, change_intent).await;
             if !ac.is_zero() {
                 by_perks.insert(perk.name().to_owned(), ac);
@@ -175,7 +247,8 @@ impl Incrementor {
             additional_change += ac
         }
 
-        let base = <R as From<T>>::from(base_increment.0);
+        let base = R::try_from(change_intent.base_increment)
+            .unwrap_or_else(|_| <R as From<T>>::from(base_increment.0));
         let total = change_intent.base_increment.checked_add(additional_change)
             .map(R::try_from)
             .and_then(Result::ok)
@@ -193,14 +266,49 @@ impl Incrementor {
             base,
             by_perks,
             total,
+            event: event.map(|(event, _)| event),
+        }
+    }
+}
+
+impl GrowthEventsConfig {
+    fn roll<R: Rng>(&self, rng: &mut R) -> Option<GrowthEvent> {
+        let roll: f64 = rng.gen();
+        [
+            (GrowthEvent::Jackpot, self.jackpot_chance),
+            (GrowthEvent::Critical, self.critical_chance),
+            (GrowthEvent::Catastrophe, self.catastrophe_chance),
+        ]
+        .into_iter()
+        .scan(0.0, |threshold, (event, chance)| {
+            *threshold += chance;
+            Some((event, *threshold))
+        })
+        .find(|(_, threshold)| roll < *threshold)
+        .map(|(event, _)| event)
+    }
+
+    /// Returns the new base increment or `None` if the event cannot happen to the dick.
+    /// Newcomers are promised a grace period without shrinks, so catastrophes spare them.
+    fn apply(&self, event: GrowthEvent, base_increment: i32, current_length: i32, newcomer: bool) -> Option<i32> {
+        match event {
+            GrowthEvent::Critical => {
+                Some(base_increment.abs().saturating_mul(i32::from(self.critical_multiplier)))
+            }
+            GrowthEvent::Catastrophe if current_length > 0 && !newcomer => {
+                let loss = (f64::from(current_length) * self.catastrophe_loss_ratio).round() as i32;
+                Some(-loss.max(1))
+            }
+            GrowthEvent::Catastrophe => None,
+            GrowthEvent::Jackpot => Some(i32::from(self.jackpot_amount)),
         }
     }
 }
 
 #[derive(Copy, Clone)]
-struct BaseIncrement<T: PrimInt + Copy + Into<i16>>(T);
+struct BaseIncrement<T: PrimInt + Copy + Into<i32>>(T);
 
-impl<T: PrimInt + Into<i16>> BaseIncrement<T> {
+impl<T: PrimInt + Into<i32>> BaseIncrement<T> {
     fn only<R>(self) -> Increment<R>
     where
         R: PrimInt + std::fmt::Display + From<T>,
@@ -210,11 +318,12 @@ impl<T: PrimInt + Into<i16>> BaseIncrement<T> {
             base: value,
             by_perks: HashMap::default(),
             total: value,
+            event: None,
         }
     }
 
     fn i32(self) -> i32 {
-        self.0.into() as i32
+        self.0.into()
     }
 }
 
@@ -237,41 +346,52 @@ impl<T: PrimInt + std::fmt::Display + Into<i32>> Increment<T> {
             String::default()
         }
     }
+
+    pub fn event_part_of_answer(&self, lang_code: &str) -> String {
+        match self.event {
+            Some(event) => {
+                let t_key = format!("commands.grow.events.{event}");
+                format!("{}\n", t!(&t_key, locale = lang_code))
+            }
+            None => String::default(),
+        }
+    }
 }
 
-fn get_base_increment<T>(range: RangeInclusive<T>, sign_ratio: f32) -> T
-where
-    T: PrimInt + PartialOrd + SampleUniform + From<i8>,
-{
+fn get_base_increment(
+    distribution: &dyn GrowthDistribution,
+    range: RangeInclusive<i32>,
+    sign_ratio: f32,
+    current_length: i32,
+) -> i32 {
     let sign_ratio_percent = match (sign_ratio * 100.0).round() as u32 {
         ..=0 => 0,
         100.. => 100,
         x => x,
     };
     let mut rng = OsRng;
-    let zero = <T as From<i8>>::from(0);
-    if range.start() > &zero {
-        return rng.gen_range(range);
+    if *range.start() > 0 {
+        return distribution.sample(&mut rng, range, current_length, true);
     }
     let positive = rng.gen_ratio(sign_ratio_percent, 100);
     if positive {
         let end = *range.end();
-        let one = <T as From<i8>>::from(1);
-        rng.gen_range(one..=end)
+        distribution.sample(&mut rng, 1..=end, current_length, true)
     } else {
         let start = *range.start();
-        let minus_one = <T as From<i8>>::from(-1);
-        rng.gen_range(start..=minus_one)
+        -distribution.sample(&mut rng, 1..=-start, current_length, false)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::get_base_increment;
+    use super::{get_base_increment, GrowthEvent, GrowthEventsConfig};
+    use crate::handlers::utils::UniformDistribution;
+    use rand::rngs::OsRng;
 
     #[test]
     fn test_gen_increment() {
-        let increments: Vec<i32> = (0..100).map(|_| get_base_increment(-5..=10, 0.5)).collect();
+        let increments: Vec<i32> = (0..100).map(|_| get_base_increment(&UniformDistribution, -5..=10, 0.5, 0)).collect();
         assert!(increments.iter().any(|n| n > &0));
         assert!(increments.iter().any(|n| n < &0));
         assert!(increments.iter().all(|n| n != &0));
@@ -281,24 +401,81 @@ mod test {
 
     #[test]
     fn test_gen_increment_with_positive_range() {
-        let increments: Vec<i32> = (0..100).map(|_| get_base_increment(5..=10, 0.5)).collect();
+        let increments: Vec<i32> = (0..100).map(|_| get_base_increment(&UniformDistribution, 5..=10, 0.5, 0)).collect();
         assert!(increments.iter().all(|n| n <= &10));
         assert!(increments.iter().all(|n| n >= &5));
     }
+
+    #[test]
+    fn test_roll_growth_events() {
+        let never = GrowthEventsConfig::default();
+        assert!((0..1000).all(|_| never.roll(&mut OsRng).is_none()));
+
+        let always_critical = GrowthEventsConfig {
+            critical_chance: 1.0,
+            ..Default::default()
+        };
+        assert!((0..1000).all(|_| always_critical.roll(&mut OsRng) == Some(GrowthEvent::Critical)));
+
+        let config = GrowthEventsConfig {
+            critical_chance: 0.1,
+            catastrophe_chance: 0.05,
+            jackpot_chance: 0.01,
+            ..Default::default()
+        };
+        let events: Vec<Option<GrowthEvent>> = (0..100_000).map(|_| config.roll(&mut OsRng)).collect();
+        let count = |event| events.iter().filter(|e| **e == Some(event)).count();
+        assert!((9_000..=11_000).contains(&count(GrowthEvent::Critical)));
+        assert!((4_000..=6_000).contains(&count(GrowthEvent::Catastrophe)));
+        assert!((500..=1_500).contains(&count(GrowthEvent::Jackpot)));
+    }
+
+    #[test]
+    fn test_apply_growth_events() {
+        let config = GrowthEventsConfig {
+            critical_multiplier: 3,
+            catastrophe_loss_ratio: 0.2,
+            jackpot_amount: 100,
+            ..Default::default()
+        };
+        assert_eq!(config.apply(GrowthEvent::Critical, 4, 0, false), Some(12));
+        assert_eq!(config.apply(GrowthEvent::Critical, -4, 0, false), Some(12));
+        assert_eq!(config.apply(GrowthEvent::Catastrophe, 4, 50, false), Some(-10));
+        assert_eq!(config.apply(GrowthEvent::Catastrophe, 4, 2, false), Some(-1), "at least 1 cm must be lost");
+        assert_eq!(config.apply(GrowthEvent::Catastrophe, 4, 0, false), None);
+        assert_eq!(config.apply(GrowthEvent::Catastrophe, 4, -10, false), None);
+        assert_eq!(config.apply(GrowthEvent::Jackpot, -4, 10, false), Some(100));
+    }
+
+    #[test]
+    fn test_apply_growth_events_to_newcomers() {
+        let config = GrowthEventsConfig {
+            critical_multiplier: 3,
+            catastrophe_loss_ratio: 0.2,
+            jackpot_amount: 100,
+            ..Default::default()
+        };
+        assert_eq!(config.apply(GrowthEvent::Catastrophe, 4, 50, true), None, "newcomers must not shrink");
+        assert_eq!(config.apply(GrowthEvent::Critical, -4, 50, true), Some(12));
+        assert_eq!(config.apply(GrowthEvent::Jackpot, 4, 50, true), Some(100));
+    }
 }
 
 #[cfg(test)]
 mod test_incrementor {
     use std::iter::zip;
+    use std::sync::Arc;
 
     use async_trait::async_trait;
     use futures::future::join_all;
+    use sqlx::{Pool, Postgres};
 
     use crate::handlers::utils::{
-        AdditionalChange, ChangeIntent, Config, DickId, Incrementor, Perk,
+        AdditionalChange, ChangeIntent, Config, DickId, GrowthEvent, GrowthEventsConfig,
+        Incrementor, Perk, UniformDistribution,
     };
     use crate::repo;
-    use crate::repo::test::{start_postgres, CHAT_ID_KIND, USER_ID};
+    use crate::repo::test::{start_postgres, CHAT_ID_KIND, NAME, USER_ID};
 
     #[tokio::test]
     async fn test_incrementor() {
@@ -308,8 +485,10 @@ mod test_incrementor {
             config: Config {
                 growth_range: -1..=1,
                 grow_shrink_ratio: 0.5,
+                distribution: Arc::new(UniformDistribution),
                 newcomers_grace_days: 1,
                 dod_bonus_range: 1..=2,
+                events: GrowthEventsConfig::default(),
             },
             dicks,
             perks: Vec::default(),
@@ -319,6 +498,7 @@ mod test_incrementor {
         test_dod_increment_base(&incr).await;
         test_with_perks(&incr).await;
         test_perk_with_overflow(&incr).await;
+        test_growth_events(&incr, &db).await;
     }
 
     async fn test_growth_increment_base(incr: &Incrementor) {
@@ -336,6 +516,7 @@ mod test_incrementor {
             let val = fut.await;
             assert_eq!(val.base, val.total);
             assert!(val.base > 0);
+            assert_eq!(val.event, None);
         }
     }
 
@@ -409,4 +590,41 @@ mod test_incrementor {
         assert_eq!(increment.base, increment.total);
         assert!(increment.by_perks.is_empty());
     }
+
+    async fn test_growth_events(incr: &Incrementor, db: &Pool<Postgres>) {
+        let mut incr = incr.clone();
+        incr.config.events = GrowthEventsConfig {
+            jackpot_chance: 1.0,
+            jackpot_amount: 100,
+            ..Default::default()
+        };
+        let growth = incr.growth_increment(USER_ID, CHAT_ID_KIND, 1).await;
+        assert_eq!(growth.event, Some(GrowthEvent::Jackpot));
+        assert_eq!((growth.base, growth.total), (100, 100));
+
+        let dod = incr.dod_increment(USER_ID, CHAT_ID_KIND).await;
+        assert_eq!(dod.event, None, "events must not affect Dick of the Day");
+
+        incr.config.events = GrowthEventsConfig {
+            catastrophe_chance: 1.0,
+            catastrophe_loss_ratio: 0.5,
+            ..Default::default()
+        };
+        let growth = incr.growth_increment(USER_ID, CHAT_ID_KIND, 30).await;
+        assert_eq!(growth.event, None, "a catastrophe must not happen to a missing dick");
+
+        repo::Users::new(db.clone())
+            .create_or_update(USER_ID, NAME, None)
+            .await
+            .expect("couldn't create a user");
+        incr.dicks
+            .create_or_grow(USER_ID, &CHAT_ID_KIND.into(), 10)
+            .await
+            .expect("couldn't create a dick");
+        let growth = incr.growth_increment(USER_ID, CHAT_ID_KIND, 1).await;
+        assert_eq!(growth.event, None, "a newcomer must be spared");
+        let growth = incr.growth_increment(USER_ID, CHAT_ID_KIND, 30).await;
+        assert_eq!(growth.event, Some(GrowthEvent::Catastrophe));
+        assert_eq!(growth.base, -5);
+    }
 }
@@ -24,12 +24,12 @@ pub enum LockCallbackServiceFacade {
 }
 
 impl LockCallbackServiceFacade {
-    pub fn from_config(features: FeatureToggles) -> Self {
+    /// Called for every update, so the toggle follows the reloaded configuration,
+    /// while the locks themselves live in the shared `in_memory` service.
+    pub fn from_config(features: FeatureToggles, in_memory: &InMemoryLockCallbackService) -> Self {
         if features.pvp.callback_locks {
-            log::info!("LockCallbackService: in-memory");
-            Self::InMemory(InMemoryLockCallbackService::default())
+            Self::InMemory(in_memory.clone())
         } else {
-            log::info!("LockCallbackService: none");
             Self::NoOp
         }
     }
@@ -1,13 +1,23 @@
+mod admins;
 pub mod callbacks;
+mod distribution;
 mod incrementor;
 pub mod locks;
 pub mod page;
+mod split;
+mod targets;
 mod tghack;
 
+pub use admins::*;
+pub use distribution::*;
 pub use incrementor::*;
+pub use split::*;
+pub use targets::*;
 pub use tghack::*;
 
-use crate::domain::Username;
+use crate::domain::{LanguageCode, Username};
+use crate::repo;
+use chrono::FixedOffset;
 use teloxide::types::User;
 
 pub fn get_full_name(user: &User) -> Username {
@@ -19,19 +29,46 @@ pub fn get_full_name(user: &User) -> Username {
     Username::new(name)
 }
 
+/// Returns the language set by administrators of the chat if any.
+/// It must be used for public replies instead of the language of a user.
+pub async fn get_chat_language(
+    repos: &repo::Repositories,
+    chat_id: &repo::ChatIdKind,
+    user_lang_code: LanguageCode,
+) -> LanguageCode {
+    match repos.chats.get_language(chat_id).await {
+        Ok(Some(lang)) => lang.into(),
+        Ok(None) => user_lang_code,
+        Err(e) => {
+            log::error!("couldn't get the language of the chat {chat_id}: {e}");
+            user_lang_code
+        }
+    }
+}
+
+/// Returns the offset of the time zone set by administrators of the chat, which the day starts in.
+pub async fn get_chat_utc_offset(repos: &repo::Repositories, chat_id: &repo::ChatIdKind) -> FixedOffset {
+    repos.chats.get_utc_offset(chat_id).await.unwrap_or_else(|e| {
+        log::error!("couldn't get the UTC offset of the chat {chat_id}: {e}");
+        FixedOffset::east_opt(0).expect("zero offset must be valid")
+    })
+}
+
 pub mod date {
-    use chrono::{DateTime, Duration, Timelike, Utc};
+    use chrono::{DateTime, Duration, FixedOffset, Timelike, Utc};
     use rust_i18n::t;
     use std::borrow::Cow;
 
-    pub fn get_time_till_next_day_string(lang_code: &str) -> Cow<str> {
-        let now = if cfg!(test) {
+    /// The day starts at midnight in the time zone of the chat, which is given by its offset from UTC.
+    pub fn get_time_till_next_day_string(lang_code: &str, utc_offset: FixedOffset) -> Cow<str> {
+        let now: DateTime<Utc> = if cfg!(test) {
             DateTime::parse_from_rfc3339("2023-10-21T22:10:57+00:00")
                 .expect("invalid datetime string")
                 .into()
         } else {
             Utc::now()
         };
+        let now = now.with_timezone(&utc_offset);
         Some(now + Duration::days(1))
             .and_then(|d| d.with_hour(0))
             .and_then(|d| d.with_minute(0))
@@ -58,7 +95,13 @@ mod tests {
     #[test]
     fn get_time_till_next_day_string() {
         let expected = "<b>1</b>h <b>49</b>m.";
-        let actual = date::get_time_till_next_day_string("en");
+        let actual = date::get_time_till_next_day_string("en", FixedOffset::east_opt(0).unwrap());
+        let actual = &actual[actual.len() - expected.len()..];
+        assert_eq!(expected, actual);
+
+        // the next day has already begun in UTC+3
+        let expected = "<b>22</b>h <b>49</b>m.";
+        let actual = date::get_time_till_next_day_string("en", FixedOffset::east_opt(3 * 3600).unwrap());
         let actual = &actual[actual.len() - expected.len()..];
         assert_eq!(expected, actual)
     }
@@ -0,0 +1,61 @@
+/// What to do with the centimeters left after a total is split into whole shares.
+#[derive(Copy, Clone, Default, Debug, PartialEq, derive_more::FromStr, derive_more::Display)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum FireRounding {
+    /// Every share is rounded down, the sender keeps the rest.
+    DOWN,
+    /// The rest goes to the first recipient.
+    FIRST,
+    /// The rest is handed out a centimeter at a time to the recipients with the largest fractional parts.
+    #[default]
+    LARGEST,
+}
+
+/// Splits the total into shares proportional to the weights. The sum of the shares is less than
+/// the total only if the rounding is `DOWN`.
+pub fn split_amount(total: u16, weights: &[u16], rounding: FireRounding) -> Vec<u16> {
+    let weights_sum: u64 = weights.iter().copied().map(u64::from).sum();
+    if weights_sum == 0 {
+        return vec![0; weights.len()];
+    }
+    let exact: Vec<(u64, u64)> = weights.iter()
+        .map(|&weight| {
+            let numerator = u64::from(total) * u64::from(weight);
+            (numerator / weights_sum, numerator % weights_sum)
+        })
+        .collect();
+    let mut shares: Vec<u16> = exact.iter().map(|&(share, _)| share as u16).collect();
+    let rest = total - shares.iter().sum::<u16>();
+
+    match rounding {
+        FireRounding::DOWN => {}
+        FireRounding::FIRST => shares[0] += rest,
+        FireRounding::LARGEST => {
+            let mut order: Vec<usize> = (0..shares.len()).collect();
+            // the sort is stable, so the earlier recipients win the ties
+            order.sort_by_key(|&i| std::cmp::Reverse(exact[i].1));
+            for &i in order.iter().take(rest as usize) {
+                shares[i] += 1;
+            }
+        }
+    }
+    shares
+}
+
+#[cfg(test)]
+mod test {
+    use super::{split_amount, FireRounding};
+
+    #[test]
+    fn test_split_amount() {
+        assert_eq!(split_amount(30, &[1, 1, 1], FireRounding::LARGEST), vec![10, 10, 10]);
+        assert_eq!(split_amount(31, &[1, 1, 1], FireRounding::DOWN), vec![10, 10, 10]);
+        assert_eq!(split_amount(32, &[1, 1, 1], FireRounding::FIRST), vec![12, 10, 10]);
+        assert_eq!(split_amount(32, &[1, 1, 1], FireRounding::LARGEST), vec![11, 11, 10]);
+        assert_eq!(split_amount(10, &[1, 2, 2], FireRounding::LARGEST), vec![2, 4, 4]);
+        assert_eq!(split_amount(11, &[1, 1, 3], FireRounding::LARGEST), vec![2, 2, 7]);
+        assert_eq!(split_amount(11, &[1, 3, 3], FireRounding::LARGEST), vec![1, 5, 5]);
+        assert_eq!(split_amount(30, &[20, 10], FireRounding::DOWN), vec![20, 10]);
+        assert_eq!(split_amount(2, &[1, 1, 1], FireRounding::DOWN), vec![0, 0, 0]);
+    }
+}
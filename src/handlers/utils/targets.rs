@@ -0,0 +1,149 @@
+use crate::repo;
+use std::ops::Range;
+use teloxide::types::{Message, MessageEntityKind, UserId};
+
+/// The user a command is aimed at.
+pub enum Target {
+    User(UserId),
+    /// The user is mentioned by a username nobody of the known users has.
+    UnknownUsername(String),
+    Missing,
+}
+
+/// Finds the user a command is aimed at, in the following order:
+/// - the author of the replied message;
+/// - a mentioned user, either by @username or by a text mention for those who don't have one;
+/// - a user ID passed as the first argument.
+///
+/// The rest of the arguments are returned too, without the mention.
+pub async fn resolve_target<'a>(repos: &repo::Repositories, msg: &'a Message) -> anyhow::Result<(Target, Vec<&'a str>)> {
+    let text = msg.text().unwrap_or_default();
+    if let Some(author) = get_replied_user(msg) {
+        return Ok((Target::User(author), text.split_whitespace().skip(1).collect()))
+    }
+
+    let mention = msg.parse_entities()
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|entity| match entity.kind() {
+            MessageEntityKind::TextMention { user } => Some((Mention::User(user.id), entity.range())),
+            MessageEntityKind::Mention => Some((Mention::Username(entity.text()), entity.range())),
+            _ => None,
+        });
+    if let Some((mention, range)) = mention {
+        let target = match mention {
+            Mention::User(uid) => Target::User(uid),
+            Mention::Username(username) => match repos.users.get_by_username(username).await? {
+                Some(user) => Target::User(UserId(user.uid as u64)),
+                None => Target::UnknownUsername(username.trim_start_matches('@').to_owned()),
+            },
+        };
+        return Ok((target, split_args_without(text, &[range])))
+    }
+
+    let mut args: Vec<&str> = text.split_whitespace().skip(1).collect();
+    let target = match args.first().and_then(|arg| arg.parse::<u64>().ok()) {
+        Some(uid) => {
+            args.remove(0);
+            Target::User(UserId(uid))
+        }
+        None => Target::Missing,
+    };
+    Ok((target, args))
+}
+
+/// A mentioned user with an optional weight written right after the mention: `@user:2`.
+pub struct WeightedTarget {
+    pub target: Target,
+    pub weight: Option<u16>,
+}
+
+/// Finds all the users mentioned in the command, in the order of the mentions. The rest of the arguments
+/// are returned too, without the mentions and their weights. A weight which is not a number is left among them.
+pub async fn resolve_weighted_targets<'a>(repos: &repo::Repositories, msg: &'a Message) -> anyhow::Result<(Vec<WeightedTarget>, Vec<&'a str>)> {
+    let text = msg.text().unwrap_or_default();
+    let mut targets = Vec::new();
+    let mut ranges = Vec::new();
+    for entity in msg.parse_entities().unwrap_or_default() {
+        let mention = match entity.kind() {
+            MessageEntityKind::TextMention { user } => Mention::User(user.id),
+            MessageEntityKind::Mention => Mention::Username(entity.text()),
+            _ => continue,
+        };
+        let mut range = entity.range();
+        let (weight, weight_len) = parse_weight(&text[range.end..]);
+        range.end += weight_len;
+        let target = match mention {
+            Mention::User(uid) => Target::User(uid),
+            Mention::Username(username) => match repos.users.get_by_username(username).await? {
+                Some(user) => Target::User(UserId(user.uid as u64)),
+                None => Target::UnknownUsername(username.trim_start_matches('@').to_owned()),
+            },
+        };
+        targets.push(WeightedTarget { target, weight });
+        ranges.push(range);
+    }
+    Ok((targets, split_args_without(text, &ranges)))
+}
+
+enum Mention<'a> {
+    User(UserId),
+    Username(&'a str),
+}
+
+/// Messages in forum topics are replies to the service message about the creation of the topic
+/// if they don't reply to anything else, so such replies are ignored.
+fn get_replied_user(msg: &Message) -> Option<UserId> {
+    msg.reply_to_message()
+        .filter(|reply| reply.forum_topic_created().is_none())
+        .and_then(|reply| reply.from.as_ref())
+        .map(|user| user.id)
+}
+
+/// Returns the weight following a mention and the length of its text.
+fn parse_weight(text_after_mention: &str) -> (Option<u16>, usize) {
+    let Some(rest) = text_after_mention.strip_prefix(':') else {
+        return (None, 0)
+    };
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    match rest[..digits].parse() {
+        Ok(weight) => (Some(weight), digits + 1),
+        Err(_) => (None, 0),
+    }
+}
+
+/// The ranges must be sorted and must not overlap.
+fn split_args_without<'a>(text: &'a str, ranges: &[Range<usize>]) -> Vec<&'a str> {
+    let mut args = Vec::new();
+    let mut start = 0;
+    for range in ranges {
+        args.extend(text[start..range.start].split_whitespace());
+        start = range.end;
+    }
+    args.extend(text[start..].split_whitespace());
+    args.into_iter().skip(1).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_weight, split_args_without};
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_split_args_without() {
+        let text = "/gift @user 10";
+        assert_eq!(split_args_without(text, &[6..11]), vec!["10"]);
+        let text = "/gift 10 John Smith";
+        assert_eq!(split_args_without(text, &[9..19]), vec!["10"]);
+        let text = "/fire 30 @a:2 @b";
+        assert_eq!(split_args_without(text, &[9..13, 14..16]), vec!["30"]);
+    }
+
+    #[test]
+    fn test_parse_weight() {
+        assert_eq!(parse_weight(":20 @b"), (Some(20), 3));
+        assert_eq!(parse_weight(" @b:10"), (None, 0));
+        assert_eq!(parse_weight(":x"), (None, 0));
+        assert_eq!(parse_weight(":99999"), (None, 0));
+    }
+}
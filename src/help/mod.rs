@@ -1,16 +1,18 @@
-use crate::domain::SupportedLanguage::{EN, RU};
-use crate::domain::{LanguageCode, Username};
+use crate::domain::{LanguageCode, SupportedLanguage, Username};
 use rust_i18n::t;
 use serde::Serialize;
+use std::collections::HashMap;
+use strum::IntoEnumIterator;
 use tinytemplate::TinyTemplate;
 
 static EN_HELP: &str = include_str!("en.html");
 static RU_HELP: &str = include_str!("ru.html");
+static FA_HELP: &str = include_str!("fa.html");
+static ZH_HELP: &str = include_str!("zh.html");
 
 #[derive(Clone)]
 pub struct HelpContainer {
-    en: String,
-    ru: String,
+    messages: HashMap<SupportedLanguage, String>,
 }
 
 impl HelpContainer {
@@ -25,10 +27,11 @@ impl HelpContainer {
     }
 
     pub fn get_help_message(&self, lang_code: LanguageCode) -> String {
-        match lang_code.to_supported_language() {
-            RU => self.ru.clone(),
-            EN => self.en.clone(),
-        }
+        self.messages
+            .get(&lang_code.to_supported_language())
+            .or_else(|| self.messages.get(&SupportedLanguage::EN))
+            .cloned()
+            .unwrap_or_default()
     }
 }
 
@@ -37,6 +40,8 @@ pub struct Context {
     pub bot_name: String,
     pub grow_min: String,
     pub grow_max: String,
+    /// The name of the active growth distribution, it's replaced with its description for every language.
+    pub growth_distribution: String,
     pub other_bots: String,
     pub admin_channel_ru: String,
     pub admin_channel_en: String,
@@ -46,12 +51,29 @@ pub struct Context {
     pub help_pussies_percentage: f64,
 }
 
+fn get_template(lang: SupportedLanguage) -> &'static str {
+    match lang {
+        SupportedLanguage::EN => EN_HELP,
+        SupportedLanguage::RU => RU_HELP,
+        SupportedLanguage::FA => FA_HELP,
+        SupportedLanguage::ZH => ZH_HELP,
+    }
+}
+
 pub fn render_help_messages(context: Context) -> Result<HelpContainer, tinytemplate::error::Error> {
     let mut tt = TinyTemplate::new();
-    tt.add_template("en", EN_HELP)?;
-    tt.add_template("ru", RU_HELP)?;
-    Ok(HelpContainer {
-        en: tt.render("en", &context)?,
-        ru: tt.render("ru", &context)?,
-    })
+    for lang in SupportedLanguage::iter() {
+        tt.add_template(lang.into(), get_template(lang))?;
+    }
+    let messages = SupportedLanguage::iter()
+        .map(|lang| {
+            let t_key = format!("titles.growth_distribution.{}", context.growth_distribution);
+            let localized_context = Context {
+                growth_distribution: t!(&t_key, locale = lang.as_ref()).to_string(),
+                ..context.clone()
+            };
+            tt.render(lang.as_ref(), &localized_context).map(|msg| (lang, msg))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(HelpContainer { messages })
 }
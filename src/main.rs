@@ -7,11 +7,13 @@ mod metrics;
 mod repo;
 mod api;
 
+use crate::handlers::operator::OperatorCommands;
 use crate::handlers::pvp::{BattleCommands, BattleCommandsNoArgs};
 use crate::handlers::stats::StatsCommands;
-use crate::handlers::utils::locks::LockCallbackServiceFacade;
+use crate::handlers::utils::locks::{InMemoryLockCallbackService, LockCallbackServiceFacade};
 use crate::handlers::{
-    checks, HelpCommands, LoanCommands, PrivacyCommands, PromoCommandState, StartCommands,
+    checks, HelpCommands, LoanCommands, PersonalDataCommands, PrivacyCommands, PromoCommandState,
+    StartCommands,
 };
 use crate::handlers::{AdminCommands, DickCommands, DickOfDayCommands, ImportCommands, PromoCommands};
 use futures::future::join_all;
@@ -19,6 +21,7 @@ use reqwest::Url;
 use rust_i18n::i18n;
 use std::env::VarError;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use teloxide::dispatching::dialogue::InMemStorage;
 use teloxide::dptree::deps;
 use teloxide::prelude::*;
@@ -26,8 +29,9 @@ use teloxide::update_listeners::webhooks::{axum_to_router, Options};
 use teloxide::update_listeners::UpdateListener;
 
 const ENV_WEBHOOK_URL: &str = "WEBHOOK_URL";
+const CHECK_CONFIG_ARG: &str = "--check-config";
 
-i18n!(fallback = "ru"); // load localizations with default parameters
+i18n!(fallback = "en"); // load localizations with default parameters; missing keys are taken from English
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -36,11 +40,38 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     pretty_env_logger::init();
 
-    let app_config = config::AppConfig::from_env();
+    let config_file = config::load_config_file()?;
+    if std::env::args().any(|arg| arg == CHECK_CONFIG_ARG) {
+        return config::check_config(config_file.as_deref()).map_err(Into::into);
+    }
+    config::validate()?;
+
     let database_config = config::DatabaseConfig::from_env()?;
     let db_conn = repo::establish_database_connection(&database_config).await?;
 
     let handler = dptree::entry()
+        // take a consistent snapshot of the configuration for the whole processing of an update
+        .map(|config: config::ReloadableConfig| config.get())
+        .map(|snapshot: Arc<config::ConfigSnapshot>| snapshot.app_config.clone())
+        .map(|snapshot: Arc<config::ConfigSnapshot>| snapshot.repos.clone())
+        .map(|snapshot: Arc<config::ConfigSnapshot>| snapshot.incrementor.clone())
+        .map(|snapshot: Arc<config::ConfigSnapshot>| snapshot.help_container.clone())
+        .map(|app_config: config::AppConfig, locks: InMemoryLockCallbackService| {
+            LockCallbackServiceFacade::from_config(app_config.features, &locks)
+        })
+        .inspect_async(handlers::users::track_username)
+        .branch(Update::filter_my_chat_member().endpoint(handlers::chats::my_chat_member_handler))
+        .branch(Update::filter_chat_member().endpoint(handlers::chats::chat_member_handler))
+        .branch(
+            Update::filter_message()
+                .filter(handlers::chats::is_chat_migration)
+                .endpoint(handlers::chats::chat_migration_handler),
+        )
+        .branch(
+            Update::filter_message()
+                .filter(handlers::chats::is_membership_message)
+                .endpoint(handlers::chats::membership_message_handler),
+        )
         .branch(
             Update::filter_message()
                 .filter_command::<StartCommands>()
@@ -56,6 +87,35 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .filter_command::<PrivacyCommands>()
                 .endpoint(handlers::privacy_cmd_handler),
         )
+        .branch(
+            Update::filter_message()
+                .filter_command::<PersonalDataCommands>()
+                .endpoint(handlers::personal_data_cmd_handler),
+        )
+        .branch(
+            Update::filter_message()
+                .filter_command::<OperatorCommands>()
+                .filter(handlers::operator::is_operator)
+                .endpoint(handlers::operator::cmd_handler),
+        )
+        .branch(
+            Update::filter_message()
+                .filter(checks::is_group_chat)
+                .filter_async(checks::is_game_paused)
+                .endpoint(checks::handle_game_paused),
+        )
+        .branch(
+            Update::filter_message()
+                .filter(checks::is_group_chat)
+                .filter_async(checks::is_command_disabled)
+                .endpoint(checks::handle_command_disabled),
+        )
+        .branch(
+            Update::filter_message()
+                .filter(checks::is_group_chat)
+                .filter_async(checks::is_outside_game_topic)
+                .endpoint(checks::handle_outside_game_topic),
+        )
         .branch(
             Update::filter_message()
                 .filter_command::<DickCommands>()
@@ -74,6 +134,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .filter(checks::is_group_chat)
                 .endpoint(handlers::pvp::cmd_handler),
         )
+        .branch(
+            Update::filter_message()
+                .filter_command::<BattleCommandsNoArgs>()
+                .filter(checks::is_group_chat)
+                .filter(handlers::pvp::is_targeted)
+                .endpoint(handlers::pvp::targeted_cmd_handler),
+        )
         .branch(
             Update::filter_message()
                 .filter_command::<BattleCommandsNoArgs>()
@@ -162,19 +229,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .filter(handlers::pvp::callback_filter)
                 .endpoint(handlers::pvp::callback_handler),
         )
+        .branch(
+            Update::filter_callback_query()
+                .filter(handlers::fire_rain::callback_filter)
+                .endpoint(handlers::fire_rain::callback_handler),
+        )
         .branch(
             Update::filter_callback_query()
                 .filter(handlers::loan::callback_filter)
                 .endpoint(handlers::loan::callback_handler),
         )
+        .branch(
+            Update::filter_callback_query()
+                .filter(handlers::reset_chat_callback_filter)
+                .endpoint(handlers::reset_chat_callback_handler),
+        )
+        .branch(
+            Update::filter_callback_query()
+                .filter(handlers::forget_me_callback_filter)
+                .endpoint(handlers::forget_me_callback_handler),
+        )
         .branch(Update::filter_callback_query().endpoint(handlers::callback_handler));
 
     let bot = Bot::from_env();
     bot.delete_webhook().await?;
 
+    let me = bot.get_me().await?;
+    let reloadable_config = config::ReloadableConfig::new(config_file, db_conn, me)?;
+    reloadable_config.spawn_watchers();
+    handlers::chats::spawn_inactive_chats_purger(reloadable_config.clone());
+    handlers::operator::spawn_collusion_detector(reloadable_config.clone());
+    handlers::fire_rain::spawn_expired_rains_refunder(bot.clone(), reloadable_config.clone());
+    let snapshot = reloadable_config.get();
+
     let set_my_commands_requests = _rust_i18n_available_locales()
         .into_iter()
-        .map(|locale| commands::set_my_commands(&bot, locale, &app_config.command_toggles));
+        .map(|locale| commands::set_my_commands(&bot, locale, &snapshot.app_config.command_toggles));
     let set_my_commands_failed = join_all(set_my_commands_requests)
         .await
         .into_iter()
@@ -183,18 +273,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Err("couldn't set the bot's commands")?
     }
 
-    let me = bot.get_me().await?;
-    let repos = repo::Repositories::new(&db_conn, &app_config);
-    let perks = handlers::perks::all(&db_conn, &app_config);
-    let incrementor = handlers::utils::Incrementor::from_env(&repos.dicks, perks);
-    let help_context = config::build_context_for_help_messages(
-        me,
-        &incrementor,
-        &handlers::ORIGINAL_BOT_USERNAMES,
-    )?;
-    let help_container = help::render_help_messages(help_context)?;
-    let battle_locker = LockCallbackServiceFacade::from_config(app_config.features);
-
     let webhook_url: Option<Url> = match std::env::var(ENV_WEBHOOK_URL) {
         Ok(env_url) if !env_url.is_empty() => Some(env_url.parse()?),
         Ok(env_url) if env_url.is_empty() => None,
@@ -209,18 +287,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         log::warn!("API_KEY is empty; API endpoint will reject all requests");
     }
     let api_router = api::router(api::ApiState {
-        repos: repos.clone(),
+        config: reloadable_config.clone(),
         bot: bot.clone(),
         api_key,
     });
 
     let ignore_unknown_updates = |_| Box::pin(async {});
     let deps = deps![
-        repos,
-        incrementor,
-        app_config,
-        help_container,
-        battle_locker,
+        reloadable_config,
+        InMemoryLockCallbackService::default(),
+        handlers::utils::ChatAdminsCache::default(),
+        handlers::users::UsernamesCache::default(),
         InMemStorage::<PromoCommandState>::new()
     ];
 
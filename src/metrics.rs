@@ -172,6 +172,71 @@ pub static CMD_PROMO: Lazy<DeepLinkedCommandsCounters> = Lazy::new(|| {
     }
 });
 
+pub static GROW_STREAK_MILESTONE_COUNTER: Lazy<Counter> = Lazy::new(|| {
+    Counter::new(
+        "grow_streak_milestones",
+        Opts::new(
+            "grow_streak_milestones_total",
+            "count of grow streaks reached a milestone",
+        ),
+    )
+});
+
+pub static GROWTH_EVENT_COUNTER: Lazy<GrowthEventCounters> = Lazy::new(|| {
+    let opts = Opts::new(
+        "growth_events_total",
+        "count of rare events happened during growth",
+    );
+    GrowthEventCounters {
+        critical: Counter::new(
+            "growth_events (critical)",
+            opts.clone().const_label("event", "critical"),
+        ),
+        catastrophe: Counter::new(
+            "growth_events (catastrophe)",
+            opts.clone().const_label("event", "catastrophe"),
+        ),
+        jackpot: Counter::new(
+            "growth_events (jackpot)",
+            opts.const_label("event", "jackpot"),
+        ),
+    }
+});
+
+pub static CHAT_MEMBERSHIP_COUNTER: Lazy<ChatMembershipCounters> = Lazy::new(|| {
+    let opts = Opts::new(
+        "chat_membership_changes_total",
+        "count of times the bot has been added to or removed from chats",
+    );
+    ChatMembershipCounters {
+        added: Counter::new(
+            "chat_membership_changes (added)",
+            opts.clone().const_label("event", "added"),
+        ),
+        removed: Counter::new(
+            "chat_membership_changes (removed)",
+            opts.const_label("event", "removed"),
+        ),
+    }
+});
+
+pub static CONFIG_RELOAD_COUNTER: Lazy<ReloadCounters> = Lazy::new(|| {
+    let opts = Opts::new(
+        "config_reloads_total",
+        "count of attempts to reload the configuration",
+    );
+    ReloadCounters {
+        succeeded: Counter::new(
+            "config_reloads (succeeded)",
+            opts.clone().const_label("result", "succeeded"),
+        ),
+        failed: Counter::new(
+            "config_reloads (failed)",
+            opts.const_label("result", "failed"),
+        ),
+    }
+});
+
 pub fn init() -> axum::Router {
     let prometheus = REGISTRY
         .register(&INLINE_COUNTER.invoked)
@@ -201,6 +266,14 @@ pub fn init() -> axum::Router {
         .register(&CMD_PROMO.invoked_by_command)
         .register(&CMD_PROMO.invoked_by_deeplink)
         .register(&CMD_PROMO.finished)
+        .register(&GROW_STREAK_MILESTONE_COUNTER)
+        .register(&GROWTH_EVENT_COUNTER.critical)
+        .register(&GROWTH_EVENT_COUNTER.catastrophe)
+        .register(&GROWTH_EVENT_COUNTER.jackpot)
+        .register(&CHAT_MEMBERSHIP_COUNTER.added)
+        .register(&CHAT_MEMBERSHIP_COUNTER.removed)
+        .register(&CONFIG_RELOAD_COUNTER.succeeded)
+        .register(&CONFIG_RELOAD_COUNTER.failed)
         .unwrap();
 
     let (prometheus_layer, metric_handle) = PrometheusMetricLayer::pair();
@@ -240,6 +313,19 @@ pub struct DeepLinkedCommandsCounters {
     pub invoked_by_deeplink: Counter,
     pub finished: Counter,
 }
+pub struct GrowthEventCounters {
+    pub critical: Counter,
+    pub catastrophe: Counter,
+    pub jackpot: Counter,
+}
+pub struct ChatMembershipCounters {
+    pub added: Counter,
+    pub removed: Counter,
+}
+pub struct ReloadCounters {
+    pub succeeded: Counter,
+    pub failed: Counter,
+}
 struct Registry(prometheus::Registry);
 
 impl Counter {
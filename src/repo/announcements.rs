@@ -4,6 +4,7 @@ use crate::repo::{ensure_only_one_row_updated, ChatIdInternal, ChatIdKind};
 use anyhow::Context;
 use derive_more::Constructor;
 use sqlx::{Pool, Postgres};
+use teloxide::types::UserId;
 
 #[derive(sqlx::FromRow)]
 struct AnnouncementEntity {
@@ -40,10 +41,14 @@ impl Announcements {
         chat_id: &ChatIdKind,
         lang_code: &LanguageCode,
     ) -> anyhow::Result<Option<String>> {
-        let maybe_announcement = match self.announcements.get(lang_code) {
+        let announcement = match self.get_text(lang_code.to_supported_language()).await? {
+            Some(text) => config::Announcement::new(text),
+            None => self.announcements.get(lang_code).cloned(),
+        };
+        let maybe_announcement = match announcement {
             Some(announcement)
                 if self
-                    .check_conditions(chat_id, announcement, lang_code)
+                    .check_conditions(chat_id, &announcement, lang_code)
                     .await? =>
             {
                 Some((*announcement.text).clone())
@@ -53,14 +58,58 @@ impl Announcements {
         Ok(maybe_announcement)
     }
 
+    /// Returns the announcement set by an operator. An empty string means the announcement is disabled,
+    /// while None means the one from the application properties is used.
+    pub async fn get_text(&self, language: SupportedLanguage) -> anyhow::Result<Option<String>> {
+        sqlx::query_scalar!("SELECT text FROM Announcement_Texts WHERE language = $1",
+            language as SupportedLanguage)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't get the announcement text for {}", language.as_ref()))
+    }
+
+    /// Overrides the announcement from the application properties. Pass an empty string to disable it.
+    pub async fn set_text(
+        &self,
+        language: SupportedLanguage,
+        text: &str,
+        operator: UserId,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "INSERT INTO Announcement_Texts (language, text, updated_by) VALUES ($1, $2, $3)
+                ON CONFLICT (language) DO UPDATE SET text = EXCLUDED.text,
+                                                     updated_by = EXCLUDED.updated_by,
+                                                     updated_at = current_timestamp",
+            language as SupportedLanguage,
+            text,
+            operator.0 as i64
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("couldn't set the announcement text for {}", language.as_ref()))?;
+        Ok(())
+    }
+
+    /// Returns to the announcement from the application properties.
+    pub async fn reset_text(&self, language: SupportedLanguage) -> anyhow::Result<bool> {
+        sqlx::query!("DELETE FROM Announcement_Texts WHERE language = $1",
+            language as SupportedLanguage)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't reset the announcement text for {}", language.as_ref()))
+    }
+
     async fn check_conditions(
         &self,
         chat_id_kind: &ChatIdKind,
         announcement: &config::Announcement,
         lang_code: &LanguageCode,
     ) -> anyhow::Result<bool> {
+        if self.announcements.max_shows == 0 || !self.is_chat_active(chat_id_kind).await? {
+            return Ok(false)
+        }
         let res = match self.get(chat_id_kind, lang_code).await? {
-            _ if self.announcements.max_shows == 0 => false,
             Some(entity) if entity.hash[..] != announcement.hash[..] => {
                 self.update(entity.chat_id, lang_code, &announcement.hash)
                     .await?;
@@ -81,6 +130,16 @@ impl Announcements {
         Ok(res)
     }
 
+    /// Chats the bot has been removed from must not get announcements. Unknown chats are considered active.
+    async fn is_chat_active(&self, chat_id_kind: &ChatIdKind) -> anyhow::Result<bool> {
+        sqlx::query_scalar!("SELECT active FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text",
+            chat_id_kind.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|active| active.unwrap_or(true))
+            .context(format!("couldn't check if the chat {chat_id_kind} is active"))
+    }
+
     async fn get(
         &self,
         chat_id_kind: &ChatIdKind,
@@ -1,10 +1,32 @@
 use super::{ensure_only_one_row_updated, ChatIdFull, ChatIdKind, ChatIdPartiality, ChatIdSource};
+use crate::domain::SupportedLanguage;
 use crate::repository;
 use anyhow::{bail, Context};
+use chrono::FixedOffset;
 use sqlx::{Postgres, Transaction};
 use std::fmt::Formatter;
 use teloxide::types::ChatId;
 
+/// Tables moved to the group when it's merged with its supergroup, and their columns that must be unique within a chat.
+/// NULLs in such columns are considered equal, as `coalesce` does in the unique indexes.
+/// Dicks are summed up separately, Dick_of_Day can't be updated and is dropped.
+/// All the tables referencing a chat must be listed here to purge the data of inactive chats.
+const MIGRATED_CHAT_TABLES: [(&str, &[&str]); 13] = [
+    ("Loans", &["uid"]),
+    ("Battle_Stats", &["uid"]),
+    ("Announcements", &["language"]),
+    ("transfers", &[]),
+    ("Imports", &["uid"]),
+    ("Chat_Bans", &["uid"]),
+    ("Admin_Audit", &[]),
+    ("Chat_Disabled_Commands", &["command"]),
+    ("Starter_Bonuses", &["uid"]),
+    ("Chat_Members", &["uid"]),
+    ("Battle_Log", &[]),
+    ("Collusion_Flags", &["kind", "uid", "counterpart_uid"]),
+    ("Fire_Rains", &[]),
+];
+
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct Chat {
     pub internal_id: i64,
@@ -69,6 +91,70 @@ repository!(
             .map(|chat| chat.internal_id)
             .ok_or(SearchError::NotFound(chat_id.clone()))
     },
+    pub async fn get_language(&self, chat_id: &ChatIdKind) -> anyhow::Result<Option<SupportedLanguage>> {
+        let internal_id = match self.get_chat(chat_id.clone()).await? {
+            Some(chat) => chat.internal_id,
+            None => return Ok(None),
+        };
+        sqlx::query_scalar!(r#"SELECT language AS "language: SupportedLanguage" FROM Chats WHERE id = $1"#,
+            internal_id)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("couldn't get the language of the chat with id = {chat_id}"))
+    },
+    pub async fn set_language(&self, chat_id: &ChatIdPartiality, language: Option<SupportedLanguage>) -> anyhow::Result<()> {
+        let internal_id = self.upsert_chat(chat_id).await?;
+        sqlx::query!("UPDATE Chats SET language = $2 WHERE id = $1",
+            internal_id, language as Option<SupportedLanguage>)
+            .execute(&self.pool)
+            .await
+            .map_err(Into::into)
+            .and_then(ensure_only_one_row_updated)
+            .context(format!("couldn't set the language of the chat with id = {chat_id} to {:?}", language.map(|lang| lang.as_ref().to_owned())))
+    },
+    pub async fn get_timezone(&self, chat_id: &ChatIdKind) -> anyhow::Result<Option<String>> {
+        sqlx::query_scalar!(
+            "SELECT timezone FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text",
+            chat_id.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .map(Option::flatten)
+            .context(format!("couldn't get the timezone of the chat with id = {chat_id}"))
+    },
+    pub async fn set_timezone(&self, chat_id: &ChatIdPartiality, timezone: Option<&str>) -> anyhow::Result<()> {
+        let internal_id = self.upsert_chat(chat_id).await?;
+        sqlx::query!("UPDATE Chats SET timezone = $2 WHERE id = $1",
+            internal_id, timezone)
+            .execute(&self.pool)
+            .await
+            .map_err(Into::into)
+            .and_then(ensure_only_one_row_updated)
+            .context(format!("couldn't set the timezone of the chat with id = {chat_id} to {timezone:?}"))
+    },
+    /// Returns the canonical name of a time zone known to PostgreSQL, the case is ignored.
+    pub async fn find_timezone(&self, name: &str) -> anyhow::Result<Option<String>> {
+        sqlx::query_scalar!(
+            r#"SELECT name AS "name!" FROM pg_timezone_names WHERE lower(name) = lower($1) ORDER BY name LIMIT 1"#,
+            name)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't find the timezone {name}"))
+    },
+    /// Returns the current offset of the chat's time zone from UTC, the time zone of the database is used by default.
+    pub async fn get_utc_offset(&self, chat_id: &ChatIdKind) -> anyhow::Result<FixedOffset> {
+        let seconds = sqlx::query_scalar!(
+            r#"SELECT extract(epoch FROM
+                (current_timestamp AT TIME ZONE coalesce(
+                    (SELECT timezone FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text LIMIT 1),
+                    current_setting('TimeZone')))
+                - (current_timestamp AT TIME ZONE 'UTC'))::integer AS "seconds!""#,
+            chat_id.value() as String)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("couldn't get the UTC offset of the chat with id = {chat_id}"))?;
+        FixedOffset::east_opt(seconds)
+            .ok_or_else(|| anyhow::anyhow!("invalid UTC offset of the chat with id = {chat_id}: {seconds}"))
+    },
     pub async fn upsert_chat(&self, chat_id: &ChatIdPartiality) -> anyhow::Result<i64> {
         let (id, instance) = match chat_id {
             ChatIdPartiality::Both(full, _) if self.features.chats_merging => {
@@ -134,6 +220,143 @@ repository!(
             .map(|_| internal_id)
             .context(format!("couldn't update the chat with id = {internal_id} to chat_id = {chat_id:?}, chat_instance = {chat_instance:?}))"))
     },
+    /// Moves the progress of a group to the supergroup it has been upgraded to by re-pointing the row of the group.
+    /// If the supergroup has been already used by the bot, it's merged into the group. Returns false if the group is unknown.
+    pub async fn migrate_chat(&self, from: ChatId, to: ChatId) -> anyhow::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let find_chat = |chat_id: ChatId| {
+            sqlx::query_scalar!("SELECT id FROM Chats WHERE chat_id = $1 FOR UPDATE",
+                chat_id.0)
+        };
+        let group_id = find_chat(from)
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("couldn't find the migrated group {from}"))?;
+        let group_id = match group_id {
+            Some(id) => id,
+            None => return Ok(false),
+        };
+        // the messages of the rains stay in the group and can't be edited by the ID of the supergroup
+        sqlx::query!("UPDATE Fire_Rains SET message_id = NULL WHERE chat_id = $1",
+            group_id)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't detach the fire rains of the migrated group {from} from their messages"))?;
+        let supergroup_id = find_chat(to)
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("couldn't find the supergroup {to}"))?;
+        if let Some(supergroup_id) = supergroup_id {
+            Self::merge_migrated_chat(&mut tx, group_id, supergroup_id).await?;
+        }
+        sqlx::query!("UPDATE Chats SET chat_id = $2 WHERE id = $1",
+            group_id, to.0)
+            .execute(&mut *tx)
+            .await
+            .map_err(Into::into)
+            .and_then(ensure_only_one_row_updated)
+            .context(format!("couldn't change the id of the chat {from} to {to}"))?;
+        tx.commit().await?;
+        log::info!("the chat {from} has been migrated to {to} (internal id = {group_id}, merged with {supergroup_id:?})");
+        Ok(true)
+    },
+    /// Marks the chat as left by the bot or clears the mark when it's added back. Returns false if the chat is unknown.
+    pub async fn set_active(&self, chat_id: ChatId, active: bool) -> anyhow::Result<bool> {
+        sqlx::query!("UPDATE Chats SET active = $2,
+                            left_at = CASE WHEN $2 THEN NULL ELSE coalesce(left_at, current_timestamp) END
+                        WHERE chat_id = $1",
+            chat_id.0, active)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't set active = {active} for the chat {chat_id}"))
+    },
+    /// Deletes all the data of the chats the bot has been removed from more than `retention_days` ago.
+    /// Returns the amount of deleted chats.
+    pub async fn purge_inactive(&self, retention_days: u16) -> anyhow::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let chat_ids = sqlx::query_scalar!(
+            "SELECT id FROM Chats WHERE NOT active AND left_at < current_timestamp - make_interval(days => $1) FOR UPDATE",
+            i32::from(retention_days))
+            .fetch_all(&mut *tx)
+            .await
+            .context("couldn't find the inactive chats to purge")?;
+        if chat_ids.is_empty() {
+            return Ok(0)
+        }
+        for table in ["Dicks", "Dick_of_Day"].into_iter().chain(MIGRATED_CHAT_TABLES.map(|(table, _)| table)) {
+            sqlx::query(&format!("DELETE FROM {table} WHERE chat_id = ANY($1)"))
+                .bind(&chat_ids)
+                .execute(&mut *tx)
+                .await
+                .context(format!("couldn't delete {table} of the inactive chats {chat_ids:?}"))?;
+        }
+        let deleted = sqlx::query!("DELETE FROM Chats WHERE id = ANY($1)",
+            &chat_ids)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete the inactive chats {chat_ids:?}"))?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted)
+    },
+    /// The same as merge_chats, but the rest of the data of the supergroup is moved too unless it conflicts with the group's one.
+    async fn merge_migrated_chat(
+        tx: &mut Transaction<'_, Postgres>,
+        main_id: i64,
+        merged_id: i64,
+    ) -> anyhow::Result<()> {
+        let updated_dicks = sqlx::query!(
+            "WITH sum_dicks AS (SELECT uid, sum(length) as length FROM Dicks WHERE chat_id IN ($1, $2) GROUP BY uid)
+                    UPDATE Dicks d SET length = sum_dicks.length, bonus_attempts = (bonus_attempts + 1)
+                    FROM sum_dicks WHERE chat_id = $1 AND d.uid = sum_dicks.uid",
+            main_id, merged_id)
+            .execute(&mut **tx)
+            .await
+            .context(format!("couldn't sum up dicks while merging the chat {merged_id} into {main_id}"))?
+            .rows_affected();
+        let moved_dicks = sqlx::query!(
+            "UPDATE Dicks SET chat_id = $1, bonus_attempts = (bonus_attempts + 1)
+                    WHERE chat_id = $2 AND uid NOT IN (SELECT uid FROM Dicks WHERE chat_id = $1)",
+            main_id, merged_id)
+            .execute(&mut **tx)
+            .await
+            .context(format!("couldn't move dicks while merging the chat {merged_id} into {main_id}"))?
+            .rows_affected();
+        log::info!("merging the chat {merged_id} into {main_id}, updated dicks: {updated_dicks}, moved: {moved_dicks}");
+
+        for (table, unique_columns) in MIGRATED_CHAT_TABLES {
+            let condition = if unique_columns.is_empty() {
+                String::new()
+            } else {
+                let key = unique_columns.iter()
+                    .map(|column| format!("m.{column} IS NOT DISTINCT FROM t.{column}"))
+                    .collect::<Vec<_>>()
+                    .join(" AND ");
+                format!(" AND NOT EXISTS (SELECT 1 FROM {table} m WHERE m.chat_id = $1 AND {key})")
+            };
+            sqlx::query(&format!("UPDATE {table} t SET chat_id = $1 WHERE chat_id = $2{condition}"))
+                .bind(main_id)
+                .bind(merged_id)
+                .execute(&mut **tx)
+                .await
+                .context(format!("couldn't move {table} while merging the chat {merged_id} into {main_id}"))?;
+        }
+        for table in ["Dicks", "Dick_of_Day"].into_iter().chain(MIGRATED_CHAT_TABLES.map(|(table, _)| table)) {
+            sqlx::query(&format!("DELETE FROM {table} WHERE chat_id = $1"))
+                .bind(merged_id)
+                .execute(&mut **tx)
+                .await
+                .context(format!("couldn't delete the conflicting {table} of the chat {merged_id}"))?;
+        }
+        sqlx::query!("DELETE FROM Chats WHERE id = $1",
+            merged_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(Into::into)
+            .and_then(ensure_only_one_row_updated)
+            .context(format!("couldn't delete the merged chat {merged_id}"))
+    },
     async fn merge_chats(
         tx: &mut Transaction<'_, Postgres>,
         chats: [&Chat; 2],
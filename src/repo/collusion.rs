@@ -0,0 +1,223 @@
+use super::ChatIdKind;
+use crate::config;
+use crate::domain::Username;
+use crate::repository;
+use anyhow::Context;
+use sqlx::{Postgres, Transaction};
+use teloxide::types::UserId;
+
+/// A suspicious pattern found by the detector. `uid` is the suspected alt account, the one who gives
+/// the centimeters away, `counterpart_uid` is the one who receives them if there is such a single user.
+#[derive(Debug)]
+pub struct CollusionFlag {
+    pub id: i64,
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    /// `funnelling`, `pvp_pair` or `gift_only`
+    pub kind: String,
+    pub uid: i64,
+    pub name: Username,
+    pub counterpart_uid: Option<i64>,
+    pub counterpart_name: Option<Username>,
+    pub amount: i64,
+    pub occurrences: i32,
+    pub confirmed: bool,
+    /// How many centimeters of the frozen transfers are waiting for the review
+    pub held: i64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReviewVerdict {
+    /// The held centimeters are returned to the sender, further transfers remain frozen.
+    Confirm,
+    /// The held centimeters are delivered to the recipients, the pattern is not flagged again.
+    Dismiss,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ReviewOutcome {
+    pub transfers: usize,
+    pub amount: i64,
+}
+
+repository!(
+    Collusion,
+    pub async fn log_battle(&self, chat_id: &ChatIdKind, winner: UserId, loser: UserId, bet: u16) -> anyhow::Result<()> {
+        sqlx::query!("INSERT INTO Battle_Log (chat_id, winner_uid, loser_uid, bet)
+                SELECT id, $2, $3, $4 FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text",
+            chat_id.value() as String, winner.0 as i64, loser.0 as i64, bet as i32)
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't log the battle of {winner} and {loser} in {chat_id}"))?;
+        Ok(())
+    },
+    /// Drops the battles older than the window and flags the patterns found within it.
+    /// The existing flags are updated unless they've been reviewed already, so a dismissed pattern is never flagged again.
+    /// Returns the number of raised or changed flags.
+    pub async fn detect(&self, cfg: &config::CollusionConfig) -> anyhow::Result<u64> {
+        let window_days = i32::from(cfg.window_days);
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM Battle_Log WHERE created_at < current_timestamp - make_interval(days => $1)",
+            window_days)
+            .execute(&mut *tx)
+            .await
+            .context("couldn't delete the old battles")?;
+
+        // fresh accounts giving a lot to one user who has never given anything back
+        let funnelling = sqlx::query!(
+            "INSERT INTO Collusion_Flags (chat_id, kind, uid, counterpart_uid, amount, occurrences)
+                SELECT t.chat_id, 'funnelling', t.from_uid, t.to_uid, sum(t.amount), count(*) FROM transfers t
+                    JOIN Users u ON u.uid = t.from_uid
+                    WHERE t.created_at > current_timestamp - make_interval(days => $1)
+                        AND t.created_at < u.created_at + make_interval(days => $2)
+                        AND NOT EXISTS (SELECT 1 FROM transfers r WHERE r.chat_id = t.chat_id AND r.from_uid = t.to_uid AND r.to_uid = t.from_uid)
+                    GROUP BY t.chat_id, t.from_uid, t.to_uid
+                    HAVING sum(t.amount) >= $3
+                ON CONFLICT (chat_id, kind, uid, (coalesce(counterpart_uid, 0))) DO UPDATE
+                SET amount = EXCLUDED.amount, occurrences = EXCLUDED.occurrences
+                WHERE Collusion_Flags.status = 'open'
+                    AND (Collusion_Flags.amount, Collusion_Flags.occurrences) IS DISTINCT FROM (EXCLUDED.amount, EXCLUDED.occurrences)",
+            window_days, i32::from(cfg.fresh_account_days), i64::from(cfg.min_funnelled))
+            .execute(&mut *tx)
+            .await
+            .context("couldn't flag the funnelling from fresh accounts")?
+            .rows_affected();
+
+        // pairs fighting each other again and again with one side losing almost always
+        let pvp_pairs = sqlx::query!(
+            "INSERT INTO Collusion_Flags (chat_id, kind, uid, counterpart_uid, amount, occurrences)
+                SELECT chat_id, 'pvp_pair', loser_uid, winner_uid, lost, losses FROM (
+                    SELECT chat_id, loser_uid, winner_uid, sum(bet) AS lost, count(*) AS losses,
+                            sum(count(*)) OVER (PARTITION BY chat_id, least(loser_uid, winner_uid), greatest(loser_uid, winner_uid)) AS battles
+                        FROM Battle_Log
+                        GROUP BY chat_id, loser_uid, winner_uid
+                ) pairs
+                WHERE battles >= $1::bigint AND losses >= battles::float8 * $2
+                ON CONFLICT (chat_id, kind, uid, (coalesce(counterpart_uid, 0))) DO UPDATE
+                SET amount = EXCLUDED.amount, occurrences = EXCLUDED.occurrences
+                WHERE Collusion_Flags.status = 'open'
+                    AND (Collusion_Flags.amount, Collusion_Flags.occurrences) IS DISTINCT FROM (EXCLUDED.amount, EXCLUDED.occurrences)",
+            i64::from(cfg.min_pvp_battles), cfg.pvp_loss_ratio)
+            .execute(&mut *tx)
+            .await
+            .context("couldn't flag the pairs of users losing battles to each other")?
+            .rows_affected();
+
+        // users who give a lot but have never received anything nor fought anybody
+        let gift_only = sqlx::query!(
+            "INSERT INTO Collusion_Flags (chat_id, kind, uid, counterpart_uid, amount, occurrences)
+                SELECT t.chat_id, 'gift_only', t.from_uid, NULL::bigint, sum(t.amount), count(*) FROM transfers t
+                    WHERE t.kind = 'gift' AND t.created_at > current_timestamp - make_interval(days => $1)
+                        AND NOT EXISTS (SELECT 1 FROM transfers r WHERE r.chat_id = t.chat_id AND r.to_uid = t.from_uid)
+                        AND NOT EXISTS (SELECT 1 FROM Battle_Log b WHERE b.chat_id = t.chat_id AND t.from_uid IN (b.winner_uid, b.loser_uid))
+                    GROUP BY t.chat_id, t.from_uid
+                    HAVING count(*) >= $2
+                ON CONFLICT (chat_id, kind, uid, (coalesce(counterpart_uid, 0))) DO UPDATE
+                SET amount = EXCLUDED.amount, occurrences = EXCLUDED.occurrences
+                WHERE Collusion_Flags.status = 'open'
+                    AND (Collusion_Flags.amount, Collusion_Flags.occurrences) IS DISTINCT FROM (EXCLUDED.amount, EXCLUDED.occurrences)",
+            window_days, i64::from(cfg.min_gifts_only))
+            .execute(&mut *tx)
+            .await
+            .context("couldn't flag the users who only give gifts")?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(funnelling + pvp_pairs + gift_only)
+    },
+    /// Returns the flags waiting for a review: the open ones and the confirmed ones with new frozen transfers.
+    pub async fn get_pending_flags(&self, limit: u16) -> anyhow::Result<Vec<CollusionFlag>> {
+        sqlx::query_as!(
+            CollusionFlag,
+            r#"SELECT id AS "id!", chat_id, chat_instance, kind AS "kind!", uid AS "uid!", name AS "name!",
+                    counterpart_uid, counterpart_name AS "counterpart_name: Username", amount AS "amount!",
+                    occurrences AS "occurrences!", confirmed AS "confirmed!", held AS "held!"
+            FROM (
+                SELECT f.id, c.chat_id, c.chat_instance, f.kind, f.uid, u.name, f.counterpart_uid, cu.name AS counterpart_name,
+                        f.amount, f.occurrences, f.status = 'confirmed' AS confirmed,
+                        (SELECT coalesce(sum(t.amount - t.tax), 0) FROM transfers t
+                            WHERE t.chat_id = f.chat_id AND t.from_uid = f.uid AND t.status = 'frozen'
+                                AND t.to_uid = coalesce(f.counterpart_uid, t.to_uid)) AS held
+                    FROM Collusion_Flags f
+                    JOIN Chats c ON c.id = f.chat_id
+                    JOIN Users u ON u.uid = f.uid
+                    LEFT JOIN Users cu ON cu.uid = f.counterpart_uid
+                    WHERE f.status <> 'dismissed'
+            ) flags
+            WHERE NOT confirmed OR held > 0
+            ORDER BY id
+            LIMIT $1"#,
+            i64::from(limit))
+            .fetch_all(&self.pool)
+            .await
+            .context("couldn't get the pending collusion flags")
+    },
+    /// Closes the flag and settles the transfers frozen because of it.
+    /// Returns `None` if there is no such flag or it has been dismissed already.
+    pub async fn review(&self, flag_id: i64, verdict: ReviewVerdict, operator: UserId) -> anyhow::Result<Option<ReviewOutcome>> {
+        let mut tx = self.pool.begin().await?;
+        let status = match verdict {
+            ReviewVerdict::Confirm => "confirmed",
+            ReviewVerdict::Dismiss => "dismissed",
+        };
+        let flag = sqlx::query!(
+            "UPDATE Collusion_Flags SET status = $2, reviewed_by = $3, reviewed_at = current_timestamp
+                WHERE id = $1 AND status <> 'dismissed'
+                RETURNING chat_id, uid, counterpart_uid",
+            flag_id, status, operator.0 as i64)
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("couldn't update the status of the collusion flag {flag_id}"))?;
+        let Some(flag) = flag else {
+            return Ok(None)
+        };
+        let (chat_id, uid, counterpart_uid) = (flag.chat_id, flag.uid, flag.counterpart_uid);
+
+        // a dismissed flag releases only the transfers no other flag holds
+        let held: Vec<(i64, i64, i64, i32)> = sqlx::query!(
+            r#"SELECT t.id, t.from_uid, t.to_uid, t.amount - t.tax AS "amount!" FROM transfers t
+                WHERE t.chat_id = $1 AND t.from_uid = $2 AND t.to_uid = coalesce($3, t.to_uid) AND t.status = 'frozen'
+                    AND ($4 OR NOT EXISTS (SELECT 1 FROM Collusion_Flags f
+                        WHERE f.chat_id = t.chat_id AND f.uid = t.from_uid AND f.status <> 'dismissed'
+                            AND coalesce(f.counterpart_uid, t.to_uid) = t.to_uid))
+                ORDER BY t.id
+                FOR UPDATE"#,
+            chat_id, uid, counterpart_uid, verdict == ReviewVerdict::Confirm)
+            .fetch_all(&mut *tx)
+            .await
+            .context(format!("couldn't get the transfers frozen because of the collusion flag {flag_id}"))?
+            .into_iter()
+            .map(|row| (row.id, row.from_uid, row.to_uid, row.amount))
+            .collect();
+
+        let mut amount = 0;
+        for &(transfer_id, from_uid, to_uid, held_amount) in &held {
+            let (owner, transfer_status) = match verdict {
+                ReviewVerdict::Confirm => (from_uid, "reverted"),
+                ReviewVerdict::Dismiss => (to_uid, "done"),
+            };
+            Self::give_back(&mut tx, chat_id, owner, held_amount).await?;
+            sqlx::query!("UPDATE transfers SET status = $2 WHERE id = $1",
+                transfer_id, transfer_status)
+                .execute(&mut *tx)
+                .await
+                .context(format!("couldn't set the status of the transfer {transfer_id} to {transfer_status}"))?;
+            amount += i64::from(held_amount);
+        }
+        tx.commit().await?;
+
+        Ok(Some(ReviewOutcome {
+            transfers: held.len(),
+            amount,
+        }))
+    },
+    /// The dick may have been deleted since the transfer; the centimeters are lost in such a case.
+    async fn give_back(tx: &mut Transaction<'_, Postgres>, chat_id: i64, uid: i64, amount: i32) -> anyhow::Result<()> {
+        sqlx::query!("UPDATE Dicks SET length = (length + $3), bonus_attempts = (bonus_attempts + 1) WHERE chat_id = $1 AND uid = $2",
+            chat_id, uid, amount)
+            .execute(&mut **tx)
+            .await
+            .context(format!("couldn't give {amount} cm held by a collusion flag to {uid} in {chat_id}"))?;
+        Ok(())
+    }
+);
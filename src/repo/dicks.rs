@@ -14,6 +14,12 @@ pub struct Dick {
     pub position: Option<i64>,
 }
 
+#[derive(sqlx::FromRow, Debug, Default, Copy, Clone)]
+pub struct GrowStreak {
+    pub current: i32,
+    pub max: i32,
+}
+
 pub struct GrowthResult {
     pub new_length: i32,
     pub pos_in_top: Option<u64>,
@@ -43,14 +49,32 @@ impl Dicks {
     ) -> anyhow::Result<GrowthResult> {
         let uid = uid.0 as i64;
         let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
+        // the streak goes on if the dick was grown yesterday and starts over if a day was missed
         let new_length = sqlx::query_scalar!(
-            "INSERT INTO dicks(uid, chat_id, length, updated_at) VALUES ($1, $2, $3, current_timestamp)
-                ON CONFLICT (uid, chat_id) DO UPDATE SET length = (dicks.length + $3), updated_at = current_timestamp
+            "INSERT INTO dicks(uid, chat_id, length, updated_at, grown_on, grow_streak_current, grow_streak_max)
+                VALUES ($1, $2, $3, current_timestamp, chat_date($2, current_timestamp), 1, 1)
+                ON CONFLICT (uid, chat_id) DO UPDATE SET length = (dicks.length + $3), updated_at = current_timestamp,
+                    grown_on = excluded.grown_on,
+                    grow_streak_current = CASE
+                        WHEN dicks.grown_on = excluded.grown_on THEN dicks.grow_streak_current
+                        WHEN dicks.grown_on = excluded.grown_on - 1 THEN dicks.grow_streak_current + 1
+                        ELSE 1
+                    END,
+                    grow_streak_max = GREATEST(dicks.grow_streak_max, CASE
+                        WHEN dicks.grown_on = excluded.grown_on - 1 THEN dicks.grow_streak_current + 1
+                        ELSE 1
+                    END)
                 RETURNING length",
-                uid, internal_chat_id, increment)
+            uid, internal_chat_id, increment)
             .fetch_one(&self.pool)
             .await
             .context(format!("couldn't upsert the dick of {uid} in {chat_id} with increment of {increment}"))?;
+        // growing in the chat proves the user is there, even if the bot has missed the update about their return
+        sqlx::query!("UPDATE Chat_Members SET left_at = NULL, updated_at = current_timestamp WHERE chat_id = $1 AND uid = $2 AND left_at IS NOT NULL",
+            internal_chat_id, uid)
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't mark {uid} as a member of {chat_id} again"))?;
         let pos_in_top = self.get_position_in_top(internal_chat_id, uid).await?;
         Ok(GrowthResult {
             new_length,
@@ -73,6 +97,22 @@ impl Dicks {
         .context(format!("couldn't fetch length for {chat_id} and {uid}"))
     }
 
+    /// The current streak is 0 if the dick wasn't grown yesterday or today.
+    pub async fn fetch_grow_streak(&self, uid: UserId, chat_id: &ChatIdKind) -> anyhow::Result<GrowStreak> {
+        sqlx::query_as!(
+            GrowStreak,
+            r#"SELECT CASE WHEN d.grown_on >= chat_date(c.id, current_timestamp) - 1 THEN d.grow_streak_current ELSE 0 END AS "current!",
+                    d.grow_streak_max AS max
+                FROM Dicks d
+                JOIN Chats c ON d.chat_id = c.id
+                WHERE d.uid = $1 AND (c.chat_id = $2::bigint OR c.chat_instance = $2::text)"#,
+            uid.0 as i64, chat_id.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .map(Option::unwrap_or_default)
+            .context(format!("couldn't fetch the grow streak for {chat_id} and {uid}"))
+    }
+
     pub async fn is_user_has_dick(
         &self,
         uid: UserId,
@@ -125,7 +165,9 @@ impl Dicks {
                 FROM dicks d
                 JOIN users using (uid)
                 JOIN chats c ON c.id = d.chat_id
-                WHERE c.chat_id = $1::bigint OR c.chat_instance = $1::text
+                WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text)
+                    AND NOT EXISTS (SELECT 1 FROM Chat_Bans b WHERE b.chat_id = d.chat_id AND b.uid = d.uid)
+                    AND NOT EXISTS (SELECT 1 FROM Chat_Members m WHERE m.chat_id = d.chat_id AND m.uid = d.uid AND m.left_at IS NOT NULL)
                 OFFSET $2 LIMIT $3"#,
             chat_id.value() as String,
             offset as i64,
@@ -192,6 +234,74 @@ impl Dicks {
         ))
     }
 
+    /// Sets the length of the dick regardless of the daily attempts. Returns None if the user has no dick in the chat.
+    pub async fn set_length(
+        &self,
+        chat_id: &ChatIdKind,
+        user_id: UserId,
+        length: i32,
+    ) -> anyhow::Result<Option<GrowthResult>> {
+        let chat_internal_id = match self.chats.get_chat(chat_id.clone()).await? {
+            Some(chat) => chat.internal_id,
+            None => return Ok(None),
+        };
+        let uid = user_id.0 as i64;
+        let maybe_length = sqlx::query_scalar!(
+            "UPDATE Dicks SET length = $3, bonus_attempts = (bonus_attempts + 1)
+                WHERE chat_id = $1 AND uid = $2
+                RETURNING length",
+            chat_internal_id, uid, length)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't set the length of the dick of ({chat_id}, {uid}) to {length}"))?;
+        match maybe_length {
+            Some(new_length) => {
+                let pos_in_top = self.get_position_in_top(chat_internal_id, uid).await?;
+                Ok(Some(GrowthResult { new_length, pos_in_top }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Adds the one-time bonus for the first growth in the chat. Returns the new length or None if the bonus
+    /// has been already received, even if the dick was deleted since then.
+    pub async fn grant_starter_bonus(
+        &self,
+        uid: UserId,
+        chat_id: &ChatIdKind,
+        bonus: u16,
+    ) -> anyhow::Result<Option<i32>> {
+        let chat_internal_id = match self.chats.get_chat(chat_id.clone()).await? {
+            Some(chat) => chat.internal_id,
+            None => return Ok(None),
+        };
+        let uid = uid.0 as i64;
+        let mut tx = self.pool.begin().await?;
+        let granted = sqlx::query!(
+            "INSERT INTO Starter_Bonuses (chat_id, uid, bonus) VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING",
+            chat_internal_id, uid, i32::from(bonus))
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't record the starter bonus of ({chat_id}, {uid})"))?
+            .rows_affected() > 0;
+        if !granted {
+            return Ok(None);
+        }
+        let new_length = sqlx::query_scalar!(
+            "UPDATE Dicks SET length = length + $3, bonus_attempts = (bonus_attempts + 1)
+                WHERE chat_id = $1 AND uid = $2
+                RETURNING length",
+            chat_internal_id, uid, i32::from(bonus))
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("couldn't add the starter bonus of {bonus} cm to ({chat_id}, {uid})"))?;
+        if new_length.is_some() {
+            tx.commit().await?;
+        }
+        Ok(new_length)
+    }
+
     pub async fn move_length(
         &self,
         chat_id: &ChatIdPartiality,
@@ -329,7 +439,9 @@ impl Dicks {
                 FROM dicks d
                 JOIN chats c ON c.id = d.chat_id
                 WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text)
-                AND ($2::bigint IS NULL OR d.uid != $2)",
+                AND ($2::bigint IS NULL OR d.uid != $2)
+                AND NOT EXISTS (SELECT 1 FROM Chat_Bans b WHERE b.chat_id = d.chat_id AND b.uid = d.uid)
+                AND NOT EXISTS (SELECT 1 FROM Chat_Members m WHERE m.chat_id = d.chat_id AND m.uid = d.uid AND m.left_at IS NOT NULL)",
             chat_id.value() as String,
             sender_uid
         )
@@ -356,6 +468,8 @@ impl Dicks {
                 JOIN chats c ON c.id = d.chat_id
                 WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text)
                 AND ($2::bigint IS NULL OR d.uid != $2)
+                AND NOT EXISTS (SELECT 1 FROM Chat_Bans b WHERE b.chat_id = d.chat_id AND b.uid = d.uid)
+                AND NOT EXISTS (SELECT 1 FROM Chat_Members m WHERE m.chat_id = d.chat_id AND m.uid = d.uid AND m.left_at IS NOT NULL)
                 ORDER BY length DESC, updated_at DESC, name
                 OFFSET $3 LIMIT 1",
             chat_id.value() as String,
@@ -0,0 +1,244 @@
+use super::transfers::calc_tax;
+use super::{ChatIdPartiality, TransferKind, TransferLeg, TransferRejection, Transfers};
+use crate::config;
+use crate::domain::Username;
+use crate::repository;
+use anyhow::Context;
+use teloxide::types::{MessageId, UserId};
+
+/// Centimeters thrown into a chat by `/fire rain` for the first users who click the button.
+#[derive(Debug)]
+pub struct FireRain {
+    pub id: i64,
+    pub chat_id: Option<i64>,
+    pub message_id: Option<i32>,
+    pub sender_name: Username,
+    pub amount: i32,
+    pub shares: i32,
+    pub remaining: i32,
+    /// How much has been returned to the sender after the timeout
+    pub refunded: i32,
+    pub finished: bool,
+}
+
+#[derive(Debug)]
+pub struct FireRainClaim {
+    pub name: Username,
+    pub amount: i32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ClaimReceipt {
+    pub amount: i32,
+    pub tax: i32,
+    /// The share is held until an operator reviews the collusion flags of the sender
+    pub held: bool,
+}
+
+/// Why a share was not given. Nothing is changed in such a case.
+#[derive(Debug, PartialEq)]
+pub enum ClaimRejection {
+    Over,
+    AlreadyClaimed,
+    OwnRain,
+    NoDick,
+    TooNew,
+}
+
+repository!(
+    FireRains,
+    with_(chats)_(Chats),
+    /// Takes the centimeters from the sender and keeps them in the rain until they are claimed or refunded.
+    /// The same limits as for transfers apply to the whole amount.
+    pub async fn start(
+        &self,
+        chat_id: &ChatIdPartiality,
+        from: UserId,
+        amount: u16,
+        shares: u16,
+        limits: &config::GiftsConfig,
+        timeout_minutes: u16,
+    ) -> anyhow::Result<Result<i64, TransferRejection>> {
+        let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
+        let sender = from.0 as i64;
+        let amount = i32::from(amount);
+
+        let mut tx = self.pool.begin().await?;
+        let current = sqlx::query_scalar!("SELECT length FROM Dicks WHERE chat_id = $1 AND uid = $2 FOR UPDATE",
+            internal_chat_id, sender)
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("couldn't lock the dick of {from} in {chat_id} to start a fire rain"))?
+            .unwrap_or_default();
+        if current < amount {
+            return Ok(Err(TransferRejection::NotEnough { current }))
+        }
+        if let Some(rejection) = Transfers::check_limits(&mut tx, internal_chat_id, sender, amount, limits).await? {
+            return Ok(Err(rejection))
+        }
+
+        Transfers::change_length(&mut tx, internal_chat_id, sender, -amount).await?;
+        let rain_id = sqlx::query_scalar!(
+            "INSERT INTO Fire_Rains (chat_id, uid, amount, shares, remaining, expires_at)
+                VALUES ($1, $2, $3, $4, $3, current_timestamp + make_interval(mins => $5))
+                RETURNING id",
+            internal_chat_id, sender, amount, i32::from(shares), i32::from(timeout_minutes))
+            .fetch_one(&mut *tx)
+            .await
+            .context(format!("couldn't start a fire rain of {amount} cm from {from} in {chat_id}"))?;
+        tx.commit().await?;
+        Ok(Ok(rain_id))
+    },
+    pub async fn set_message(&self, rain_id: i64, message_id: MessageId) -> anyhow::Result<()> {
+        sqlx::query!("UPDATE Fire_Rains SET message_id = $2 WHERE id = $1",
+            rain_id, message_id.0)
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't set the message of the fire rain {rain_id}"))?;
+        Ok(())
+    },
+    /// Gives the next share of the rain to the user. All the shares are equal except the last one
+    /// which takes the rest of the amount.
+    pub async fn claim(
+        &self,
+        rain_id: i64,
+        uid: UserId,
+        limits: &config::GiftsConfig,
+    ) -> anyhow::Result<Result<ClaimReceipt, ClaimRejection>> {
+        let recipient = uid.0 as i64;
+        let mut tx = self.pool.begin().await?;
+        let rain = sqlx::query!(
+            r#"SELECT chat_id, uid, amount, shares, remaining, finished_at IS NOT NULL OR expires_at < current_timestamp AS "over!"
+                FROM Fire_Rains WHERE id = $1 FOR UPDATE"#,
+            rain_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("couldn't lock the fire rain {rain_id}"))?;
+        let Some(rain) = rain else {
+            return Ok(Err(ClaimRejection::Over))
+        };
+        let (chat_id, sender, amount, shares, remaining) = (rain.chat_id, rain.uid, rain.amount, rain.shares, rain.remaining);
+        let over = rain.over;
+        if over || remaining == 0 {
+            return Ok(Err(ClaimRejection::Over))
+        }
+        if sender == recipient {
+            return Ok(Err(ClaimRejection::OwnRain))
+        }
+
+        let claims = sqlx::query!(
+            r#"SELECT count(*) AS "count!", coalesce(bool_or(uid = $2), false) AS "already_claimed!"
+                FROM Fire_Rain_Claims WHERE rain_id = $1"#,
+            rain_id, recipient)
+            .fetch_one(&mut *tx)
+            .await
+            .context(format!("couldn't count the claims of the fire rain {rain_id}"))?;
+        if claims.already_claimed {
+            return Ok(Err(ClaimRejection::AlreadyClaimed))
+        }
+        let has_dick = sqlx::query_scalar!("SELECT 1 FROM Dicks WHERE chat_id = $1 AND uid = $2 FOR UPDATE",
+            chat_id, recipient)
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("couldn't lock the dick of {uid} to claim the fire rain {rain_id}"))?
+            .is_some();
+        if !has_dick {
+            return Ok(Err(ClaimRejection::NoDick))
+        }
+        if Transfers::find_too_new_recipient(&mut tx, chat_id, &[recipient], limits).await?.is_some() {
+            return Ok(Err(ClaimRejection::TooNew))
+        }
+
+        let share = if claims.count + 1 >= i64::from(shares) { remaining } else { amount / shares };
+        let tax = calc_tax(share, limits);
+        let held = Transfers::find_frozen_recipients(&mut tx, chat_id, sender, &[recipient], limits).await?
+            .contains(&recipient);
+        let status = if held {
+            "frozen"
+        } else {
+            Transfers::change_length(&mut tx, chat_id, recipient, share - tax).await?;
+            "done"
+        };
+        let leg = TransferLeg { to: uid, amount: share as u16 };
+        Transfers::log_leg(&mut tx, chat_id, sender, &leg, tax, TransferKind::Fire, status).await?;
+
+        sqlx::query!("INSERT INTO Fire_Rain_Claims (rain_id, uid, amount) VALUES ($1, $2, $3)",
+            rain_id, recipient, share)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't insert the claim of {uid} to the fire rain {rain_id}"))?;
+        sqlx::query!(
+            "UPDATE Fire_Rains SET remaining = remaining - $2,
+                    finished_at = CASE WHEN remaining = $2 THEN current_timestamp END
+                WHERE id = $1",
+            rain_id, share)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't take {share} cm from the fire rain {rain_id}"))?;
+        tx.commit().await?;
+        Ok(Ok(ClaimReceipt { amount: share, tax, held }))
+    },
+    pub async fn get(&self, rain_id: i64) -> anyhow::Result<Option<FireRain>> {
+        sqlx::query_as!(
+            FireRain,
+            r#"SELECT r.id, c.chat_id, r.message_id, u.name AS sender_name, r.amount, r.shares, r.remaining, r.refunded,
+                    r.finished_at IS NOT NULL AS "finished!"
+                FROM Fire_Rains r
+                JOIN Chats c ON c.id = r.chat_id
+                JOIN Users u ON u.uid = r.uid
+                WHERE r.id = $1"#,
+            rain_id)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't get the fire rain {rain_id}"))
+    },
+    pub async fn get_claims(&self, rain_id: i64) -> anyhow::Result<Vec<FireRainClaim>> {
+        sqlx::query_as!(
+            FireRainClaim,
+            "SELECT u.name, fc.amount FROM Fire_Rain_Claims fc
+                JOIN Users u ON u.uid = fc.uid
+                WHERE fc.rain_id = $1
+                ORDER BY fc.created_at",
+            rain_id)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't get the claims of the fire rain {rain_id}"))
+    },
+    /// Finishes the expired rains and returns what nobody has claimed to the senders.
+    /// The dick of the sender may have been deleted since the start; the centimeters are lost in such a case.
+    pub async fn refund_expired(&self) -> anyhow::Result<Vec<FireRain>> {
+        let mut tx = self.pool.begin().await?;
+        let expired: Vec<(i64, i64, i64, i32)> = sqlx::query!(
+            "UPDATE Fire_Rains SET finished_at = current_timestamp, refunded = remaining, remaining = 0
+                WHERE finished_at IS NULL AND expires_at < current_timestamp
+                RETURNING id, chat_id, uid, refunded")
+            .fetch_all(&mut *tx)
+            .await
+            .context("couldn't finish the expired fire rains")?
+            .into_iter()
+            .map(|row| (row.id, row.chat_id, row.uid, row.refunded))
+            .collect();
+        for &(rain_id, chat_id, uid, refunded) in expired.iter().filter(|(.., refunded)| *refunded > 0) {
+            sqlx::query!("UPDATE Dicks SET length = (length + $3), bonus_attempts = (bonus_attempts + 1) WHERE chat_id = $1 AND uid = $2",
+                chat_id, uid, refunded)
+                .execute(&mut *tx)
+                .await
+                .context(format!("couldn't refund {refunded} cm of the fire rain {rain_id} to {uid}"))?;
+        }
+        tx.commit().await?;
+
+        let rain_ids: Vec<i64> = expired.into_iter().map(|(rain_id, ..)| rain_id).collect();
+        sqlx::query_as!(
+            FireRain,
+            r#"SELECT r.id, c.chat_id, r.message_id, u.name AS sender_name, r.amount, r.shares, r.remaining, r.refunded,
+                    r.finished_at IS NOT NULL AS "finished!"
+                FROM Fire_Rains r
+                JOIN Chats c ON c.id = r.chat_id
+                JOIN Users u ON u.uid = r.uid
+                WHERE r.id = ANY($1) ORDER BY r.id"#,
+            &rain_ids)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't get the refunded fire rains {rain_ids:?}"))
+    }
+);
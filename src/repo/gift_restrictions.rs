@@ -0,0 +1,36 @@
+use crate::repository;
+use anyhow::Context;
+use teloxide::types::UserId;
+
+repository!(
+    GiftRestrictions,
+    /// Returns the name shown instead of the user's one if the user cannot receive gifts.
+    pub async fn get(&self, user_id: UserId) -> anyhow::Result<Option<String>> {
+        sqlx::query_scalar!("SELECT custom_name FROM Gift_Restrictions WHERE uid = $1",
+            user_id.0 as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't get the gift restriction of {user_id}"))
+    },
+    pub async fn set(&self, user_id: UserId, custom_name: &str, operator: UserId) -> anyhow::Result<()> {
+        sqlx::query!(
+            "INSERT INTO Gift_Restrictions (uid, custom_name, created_by) VALUES ($1, $2, $3)
+                ON CONFLICT (uid) DO UPDATE SET custom_name = EXCLUDED.custom_name,
+                                                created_by = EXCLUDED.created_by,
+                                                created_at = current_timestamp",
+            user_id.0 as i64, custom_name, operator.0 as i64)
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't restrict gifts to {user_id}"))?;
+        Ok(())
+    },
+    /// Returns false if the user was not restricted.
+    pub async fn remove(&self, user_id: UserId) -> anyhow::Result<bool> {
+        sqlx::query!("DELETE FROM Gift_Restrictions WHERE uid = $1",
+            user_id.0 as i64)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't remove the gift restriction of {user_id}"))
+    }
+);
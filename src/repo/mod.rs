@@ -1,8 +1,13 @@
 mod announcements;
 mod chats;
+mod collusion;
 mod dicks;
+mod fire_rains;
+mod gift_restrictions;
 mod import;
 mod loans;
+mod moderation;
+mod personal_data;
 mod promo;
 mod pvpstats;
 mod stats;
@@ -17,9 +22,14 @@ use crate::config::DatabaseConfig;
 pub use announcements::*;
 use anyhow::anyhow;
 pub use chats::*;
+pub use collusion::*;
 pub use dicks::*;
+pub use fire_rains::*;
+pub use gift_restrictions::*;
 pub use import::*;
 pub use loans::*;
+pub use moderation::*;
+pub use personal_data::*;
 pub use promo::*;
 pub use pvpstats::*;
 use sqlx::postgres::PgQueryResult;
@@ -41,6 +51,12 @@ pub struct Repositories {
     pub pvp_stats: BattleStatsRepo,
     pub personal_stats: PersonalStatsRepo,
     pub transfers: Transfers,
+    pub fire_rains: FireRains,
+    pub collusion: Collusion,
+    pub moderation: Moderation,
+    pub personal_data: PersonalDataRepo,
+    pub gift_restrictions: GiftRestrictions,
+    pub global_stats: GlobalStatsRepo,
 }
 
 impl Repositories {
@@ -56,6 +72,12 @@ impl Repositories {
             pvp_stats: BattleStatsRepo::new(db_conn.clone(), config.features),
             personal_stats: PersonalStatsRepo::new(db_conn.clone()),
             transfers: Transfers::new(db_conn.clone(), config.features),
+            fire_rains: FireRains::new(db_conn.clone(), config.features),
+            collusion: Collusion::new(db_conn.clone()),
+            moderation: Moderation::new(db_conn.clone(), config.features),
+            personal_data: PersonalDataRepo::new(db_conn.clone()),
+            gift_restrictions: GiftRestrictions::new(db_conn.clone()),
+            global_stats: GlobalStatsRepo::new(db_conn.clone()),
         }
     }
 }
@@ -168,7 +190,8 @@ impl From<&ChatIdKind> for ChatIdType {
 pub struct ChatIdInternal(i64);
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, derive_more::From, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, derive_more::From, PartialEq, Eq, Hash, Copy, Clone, sqlx::Type)]
+#[sqlx(transparent)]
 pub struct UID(i64);
 
 impl From<UserId> for UID {
@@ -0,0 +1,209 @@
+use super::{ChatIdKind, ChatIdPartiality};
+use crate::repository;
+use anyhow::Context;
+use teloxide::types::UserId;
+
+#[derive(Debug, Copy, Clone, strum_macros::Display, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum AdminAction {
+    DeleteUser,
+    Language,
+    Timezone,
+    Ban,
+    Unban,
+    SetLength,
+    ResetChat,
+    Freeze,
+    Unfreeze,
+    DisableCommand,
+    EnableCommand,
+    GameTopic,
+}
+
+repository!(
+    Moderation,
+    with_(chats)_(Chats),
+    /// Returns false if the user has already been banned in the chat.
+    pub async fn ban(&self, chat_id: &ChatIdPartiality, uid: UserId, banned_by: UserId) -> anyhow::Result<bool> {
+        let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
+        sqlx::query!("INSERT INTO Chat_Bans (chat_id, uid, banned_by) VALUES ($1, $2, $3)
+                ON CONFLICT (chat_id, uid) DO NOTHING",
+            internal_chat_id, uid.0 as i64, banned_by.0 as i64)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't ban the user {uid} in the chat {chat_id}"))
+    },
+    /// Returns false if the user wasn't banned in the chat.
+    pub async fn unban(&self, chat_id: &ChatIdKind, uid: UserId) -> anyhow::Result<bool> {
+        let internal_chat_id = match self.chats.get_chat(chat_id.clone()).await? {
+            Some(chat) => chat.internal_id,
+            None => return Ok(false),
+        };
+        sqlx::query!("DELETE FROM Chat_Bans WHERE chat_id = $1 AND uid = $2",
+            internal_chat_id, uid.0 as i64)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't unban the user {uid} in the chat {chat_id}"))
+    },
+    pub async fn is_banned(&self, chat_id: &ChatIdKind, uid: UserId) -> anyhow::Result<bool> {
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM Chat_Bans b
+                JOIN Chats c ON b.chat_id = c.id
+                WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text) AND b.uid = $2) AS "banned!""#,
+            chat_id.value() as String, uid.0 as i64)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("couldn't check whether the user {uid} is banned in the chat {chat_id}"))
+    },
+    pub async fn set_frozen(&self, chat_id: &ChatIdPartiality, frozen: bool) -> anyhow::Result<()> {
+        let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
+        sqlx::query!("UPDATE Chats SET frozen = $2 WHERE id = $1",
+            internal_chat_id, frozen)
+            .execute(&self.pool)
+            .await
+            .map_err(Into::into)
+            .and_then(super::ensure_only_one_row_updated)
+            .context(format!("couldn't set the frozen flag of the chat {chat_id} to {frozen}"))
+    },
+    pub async fn is_frozen(&self, chat_id: &ChatIdKind) -> anyhow::Result<bool> {
+        sqlx::query_scalar!(
+            "SELECT frozen FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text",
+            chat_id.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .map(Option::unwrap_or_default)
+            .context(format!("couldn't check whether the chat {chat_id} is frozen"))
+    },
+    /// Restricts the game commands to one forum topic, or allows them everywhere if the topic is None.
+    pub async fn set_game_topic(&self, chat_id: &ChatIdPartiality, thread_id: Option<i32>) -> anyhow::Result<()> {
+        let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
+        sqlx::query!("UPDATE Chats SET game_topic_id = $2 WHERE id = $1",
+            internal_chat_id, thread_id)
+            .execute(&self.pool)
+            .await
+            .map_err(Into::into)
+            .and_then(super::ensure_only_one_row_updated)
+            .context(format!("couldn't set the game topic of the chat {chat_id} to {thread_id:?}"))
+    },
+    pub async fn get_game_topic(&self, chat_id: &ChatIdKind) -> anyhow::Result<Option<i32>> {
+        sqlx::query_scalar!(
+            "SELECT game_topic_id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text",
+            chat_id.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .map(Option::flatten)
+            .context(format!("couldn't get the game topic of the chat {chat_id}"))
+    },
+    /// Returns false if the command has already been disabled in the chat.
+    pub async fn disable_command(&self, chat_id: &ChatIdPartiality, command: &str, disabled_by: UserId) -> anyhow::Result<bool> {
+        let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
+        sqlx::query!("INSERT INTO Chat_Disabled_Commands (chat_id, command, disabled_by) VALUES ($1, $2, $3)
+                ON CONFLICT (chat_id, command) DO NOTHING",
+            internal_chat_id, command, disabled_by.0 as i64)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't disable the command {command} in the chat {chat_id}"))
+    },
+    /// Returns false if the command wasn't disabled in the chat.
+    pub async fn enable_command(&self, chat_id: &ChatIdKind, command: &str) -> anyhow::Result<bool> {
+        let internal_chat_id = match self.chats.get_chat(chat_id.clone()).await? {
+            Some(chat) => chat.internal_id,
+            None => return Ok(false),
+        };
+        sqlx::query!("DELETE FROM Chat_Disabled_Commands WHERE chat_id = $1 AND command = $2",
+            internal_chat_id, command)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't enable the command {command} in the chat {chat_id}"))
+    },
+    pub async fn is_command_disabled(&self, chat_id: &ChatIdKind, command: &str) -> anyhow::Result<bool> {
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM Chat_Disabled_Commands d
+                JOIN Chats c ON d.chat_id = c.id
+                WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text) AND d.command = $2) AS "disabled!""#,
+            chat_id.value() as String, command)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("couldn't check whether the command {command} is disabled in the chat {chat_id}"))
+    },
+    pub async fn get_disabled_commands(&self, chat_id: &ChatIdKind) -> anyhow::Result<Vec<String>> {
+        sqlx::query_scalar!(
+            "SELECT d.command FROM Chat_Disabled_Commands d
+                JOIN Chats c ON d.chat_id = c.id
+                WHERE c.chat_id = $1::bigint OR c.chat_instance = $1::text
+                ORDER BY d.command",
+            chat_id.value() as String)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't get the disabled commands of the chat {chat_id}"))
+    },
+    /// Deletes all dicks, loans, battle statistics and Dicks of the Day of the chat. Returns the count of deleted dicks.
+    pub async fn reset_chat(&self, chat_id: &ChatIdKind) -> anyhow::Result<u64> {
+        let internal_chat_id = match self.chats.get_chat(chat_id.clone()).await? {
+            Some(chat) => chat.internal_id,
+            None => return Ok(0),
+        };
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!("DELETE FROM Loans WHERE chat_id = $1",
+            internal_chat_id)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete loans of the chat {chat_id}"))?;
+
+        sqlx::query!("DELETE FROM Battle_Stats WHERE chat_id = $1",
+            internal_chat_id)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete battle stats of the chat {chat_id}"))?;
+
+        sqlx::query!("DELETE FROM Dick_of_Day WHERE chat_id = $1",
+            internal_chat_id)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete the dicks of the day of the chat {chat_id}"))?;
+
+        let res = sqlx::query!("DELETE FROM Dicks WHERE chat_id = $1",
+            internal_chat_id)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete dicks of the chat {chat_id}"))?;
+
+        tx.commit().await?;
+        Ok(res.rows_affected())
+    },
+    pub async fn log_action(
+        &self,
+        chat_id: &ChatIdPartiality,
+        admin: UserId,
+        action: AdminAction,
+        target: Option<UserId>,
+        details: Option<String>,
+    ) -> anyhow::Result<()> {
+        let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
+        let action_str: &'static str = action.into();
+        sqlx::query!("INSERT INTO Admin_Audit (chat_id, admin_uid, action, target_uid, details)
+                VALUES ($1, $2, $3, $4, $5)",
+            internal_chat_id, admin.0 as i64, action_str, target.map(|uid| uid.0 as i64), details)
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't log the admin action {action} of {admin} in the chat {chat_id}"))?;
+        Ok(())
+    },
+    #[cfg(test)]
+    pub async fn get_logged_actions(&self, chat_id: &ChatIdKind) -> anyhow::Result<Vec<String>> {
+        sqlx::query_scalar!(
+            "SELECT action FROM Admin_Audit a
+                JOIN Chats c ON a.chat_id = c.id
+                WHERE c.chat_id = $1::bigint OR c.chat_instance = $1::text
+                ORDER BY a.id",
+            chat_id.value() as String)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Into::into)
+    }
+);
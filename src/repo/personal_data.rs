@@ -0,0 +1,263 @@
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use teloxide::types::UserId;
+
+use crate::repository;
+
+/// Everything stored about a user, ready to be serialized and sent to them.
+#[derive(Serialize)]
+pub struct PersonalData {
+    pub user: UserEntry,
+    pub dicks: Vec<DickEntry>,
+    pub battle_stats: Vec<BattleStatsEntry>,
+    pub battles: Vec<BattleEntry>,
+    pub loans: Vec<LoanEntry>,
+    pub transfers: Vec<TransferEntry>,
+    pub fire_rains: Vec<FireRainEntry>,
+    pub promo_activations: Vec<PromoActivationEntry>,
+    pub dod_wins: Vec<DodWinEntry>,
+    pub starter_bonuses: Vec<StarterBonusEntry>,
+    pub left_chats: Vec<LeftChatEntry>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct UserEntry {
+    pub uid: i64,
+    pub name: String,
+    pub username: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct DickEntry {
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    pub length: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct BattleStatsEntry {
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    pub battles_total: i32,
+    pub battles_won: i32,
+    pub win_streak_current: i16,
+    pub win_streak_max: i16,
+    pub lose_streak_current: i16,
+    pub lose_streak_max: i16,
+    pub acquired_length: i32,
+    pub lost_length: i32,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct LoanEntry {
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    pub debt: i32,
+    pub payout_ratio: f32,
+    pub created_at: NaiveDate,
+    pub repaid_at: Option<NaiveDate>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct BattleEntry {
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    pub winner_uid: i64,
+    pub loser_uid: i64,
+    pub bet: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct TransferEntry {
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    pub from_uid: i64,
+    pub to_uid: i64,
+    pub amount: i32,
+    pub tax: i32,
+    pub kind: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct FireRainEntry {
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    pub amount: i32,
+    pub shares: i32,
+    pub refunded: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct PromoActivationEntry {
+    pub code: String,
+    pub affected_chats: i32,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct DodWinEntry {
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    pub date: NaiveDate,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct StarterBonusEntry {
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    pub bonus: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct LeftChatEntry {
+    pub chat_id: Option<i64>,
+    pub chat_instance: Option<String>,
+    pub left_at: DateTime<Utc>,
+}
+
+repository!(
+    PersonalDataRepo,
+    /// Returns None if the user is unknown to the bot.
+    pub async fn export(&self, user_id: UserId) -> anyhow::Result<Option<PersonalData>> {
+        let uid = user_id.0 as i64;
+        let user = sqlx::query_as!(UserEntry, "SELECT uid, name, username, created_at FROM Users WHERE uid = $1", uid)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't export the user {user_id}"))?;
+        let user = match user {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+
+        let dicks = sqlx::query_as!(
+            DickEntry,
+            "SELECT c.chat_id, c.chat_instance, d.length, d.updated_at FROM Dicks d
+                JOIN Chats c ON d.chat_id = c.id
+                WHERE d.uid = $1
+                ORDER BY c.id",
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export dicks of the user {user_id}"))?;
+
+        let battle_stats = sqlx::query_as!(
+            BattleStatsEntry,
+            "SELECT c.chat_id, c.chat_instance, battles_total, battles_won, win_streak_current, win_streak_max,
+                    lose_streak_current, lose_streak_max, acquired_length, lost_length
+                FROM Battle_Stats bs
+                JOIN Chats c ON bs.chat_id = c.id
+                WHERE bs.uid = $1
+                ORDER BY c.id",
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export battle stats of the user {user_id}"))?;
+
+        let battles = sqlx::query_as!(
+            BattleEntry,
+            "SELECT c.chat_id, c.chat_instance, b.winner_uid, b.loser_uid, b.bet, b.created_at FROM Battle_Log b
+                JOIN Chats c ON b.chat_id = c.id
+                WHERE b.winner_uid = $1 OR b.loser_uid = $1
+                ORDER BY b.id",
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export battles of the user {user_id}"))?;
+
+        let loans = sqlx::query_as!(
+            LoanEntry,
+            "SELECT c.chat_id, c.chat_instance, l.debt, l.payout_ratio, l.created_at, l.repaid_at FROM Loans l
+                JOIN Chats c ON l.chat_id = c.id
+                WHERE l.uid = $1
+                ORDER BY l.id",
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export loans of the user {user_id}"))?;
+
+        let transfers = sqlx::query_as!(
+            TransferEntry,
+            "SELECT c.chat_id, c.chat_instance, t.from_uid, t.to_uid, t.amount, t.tax, t.kind, t.status, t.created_at FROM transfers t
+                JOIN Chats c ON t.chat_id = c.id
+                WHERE t.from_uid = $1 OR t.to_uid = $1
+                ORDER BY t.id",
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export transfers of the user {user_id}"))?;
+
+        let fire_rains = sqlx::query_as!(
+            FireRainEntry,
+            "SELECT c.chat_id, c.chat_instance, r.amount, r.shares, r.refunded, r.created_at FROM Fire_Rains r
+                JOIN Chats c ON r.chat_id = c.id
+                WHERE r.uid = $1
+                ORDER BY r.id",
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export fire rains of the user {user_id}"))?;
+
+        let promo_activations = sqlx::query_as!(
+            PromoActivationEntry,
+            "SELECT code, affected_chats FROM Promo_Code_Activations WHERE uid = $1 ORDER BY code",
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export promo code activations of the user {user_id}"))?;
+
+        let dod_wins = sqlx::query_as!(
+            DodWinEntry,
+            "SELECT c.chat_id, c.chat_instance, dod.created_at AS date FROM Dick_of_Day dod
+                JOIN Chats c ON dod.chat_id = c.id
+                WHERE dod.winner_uid = $1
+                ORDER BY dod.created_at",
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export Dick of the Day wins of the user {user_id}"))?;
+
+        let starter_bonuses = sqlx::query_as!(
+            StarterBonusEntry,
+            "SELECT c.chat_id, c.chat_instance, sb.bonus, sb.created_at FROM Starter_Bonuses sb
+                JOIN Chats c ON sb.chat_id = c.id
+                WHERE sb.uid = $1
+                ORDER BY sb.created_at",
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export starter bonuses of the user {user_id}"))?;
+
+        let left_chats = sqlx::query_as!(
+            LeftChatEntry,
+            r#"SELECT c.chat_id, c.chat_instance, m.left_at AS "left_at!" FROM Chat_Members m
+                JOIN Chats c ON m.chat_id = c.id
+                WHERE m.uid = $1 AND m.left_at IS NOT NULL
+                ORDER BY m.left_at"#,
+            uid)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't export the chats left by the user {user_id}"))?;
+
+        Ok(Some(PersonalData {
+            user,
+            dicks,
+            battle_stats,
+            battles,
+            loans,
+            transfers,
+            fire_rains,
+            promo_activations,
+            dod_wins,
+            starter_bonuses,
+            left_chats,
+        }))
+    }
+);
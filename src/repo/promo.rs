@@ -26,7 +26,6 @@ impl<T: Into<anyhow::Error>> From<T> for ActivationError {
     }
 }
 
-#[cfg(test)]
 pub struct PromoCodeParams {
     pub code: String,
     pub bonus_length: u32,
@@ -41,17 +40,28 @@ struct PromoCodeInfo {
 
 repository!(
     Promo,
-    #[cfg(test)]
-    pub async fn create(&self, p: PromoCodeParams) -> anyhow::Result<()> {
+    /// Returns false if a code with the same name in any case already exists.
+    pub async fn create(&self, p: PromoCodeParams) -> anyhow::Result<bool> {
         sqlx::query!(
-            "INSERT INTO Promo_Codes (code, bonus_length, capacity) VALUES ($1, $2, $3)",
-            p.code,
-            p.bonus_length as i32,
-            p.capacity as i32
-        )
-        .execute(&self.pool)
-        .await?;
-        Ok(())
+            "INSERT INTO Promo_Codes (code, bonus_length, capacity) SELECT $1::text, $2, $3
+                WHERE NOT EXISTS (SELECT 1 FROM Promo_Codes WHERE lower(code) = lower($1::text))",
+            &p.code, p.bonus_length as i32, p.capacity as i32)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't create the promo code {}", p.code))
+    },
+    /// Makes the code expire yesterday, so it cannot be activated anymore but the activations are kept.
+    /// Returns false if there is no active code with such name.
+    pub async fn disable(&self, code: &str) -> anyhow::Result<bool> {
+        sqlx::query!(
+            "UPDATE Promo_Codes SET until = current_date - 1
+                WHERE lower(code) = lower($1) AND (until IS NULL OR until >= current_date)",
+            code)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't disable the promo code {code}"))
     },
     pub async fn activate(
         &self,
@@ -11,6 +11,7 @@ struct PersonalStatsEntity {
     total_length: Option<i64>,
     given_cm: Option<i64>,
     received_cm: Option<i64>,
+    max_grow_streak: Option<i32>,
 }
 
 pub struct PersonalStats {
@@ -19,6 +20,7 @@ pub struct PersonalStats {
     pub total_length: i64,
     pub given_cm: i64,
     pub received_cm: i64,
+    pub max_grow_streak: i32,
 }
 
 impl From<PersonalStatsEntity> for PersonalStats {
@@ -35,6 +37,7 @@ impl From<PersonalStatsEntity> for PersonalStats {
             total_length: value.total_length.unwrap_or_default(),
             given_cm: value.given_cm.unwrap_or_default(),
             received_cm: value.received_cm.unwrap_or_default(),
+            max_grow_streak: value.max_grow_streak.unwrap_or_default(),
         }
     }
 }
@@ -46,8 +49,9 @@ repository!(
             r#"SELECT count(chat_id) AS chats,
                           max(length) AS max_length,
                           sum(length) AS total_length,
-                          (SELECT COALESCE(sum(amount), 0) FROM transfers WHERE from_uid = $1) AS given_cm,
-                          (SELECT COALESCE(sum(amount), 0) FROM transfers WHERE to_uid = $1) AS received_cm
+                          max(grow_streak_max) AS max_grow_streak,
+                          (SELECT COALESCE(sum(amount), 0) FROM transfers WHERE from_uid = $1 AND status <> 'reverted') AS given_cm,
+                          (SELECT COALESCE(sum(amount - tax), 0) FROM transfers WHERE to_uid = $1 AND status = 'done') AS received_cm
                    FROM Dicks WHERE uid = $1"#,
         )
         .bind(user_id.0 as i64)
@@ -57,3 +61,37 @@ repository!(
         .context(format!("couldn't get the personal stats of {user_id}"))
     }
 );
+
+pub struct GlobalStats {
+    pub users: i64,
+    pub chats: i64,
+    pub dicks: i64,
+    pub active_today: i64,
+    pub battles: i64,
+    pub active_loans: i64,
+    pub promo_activations: i64,
+}
+
+repository!(
+    GlobalStatsRepo,
+    pub async fn get(&self) -> anyhow::Result<GlobalStats> {
+        // every battle has exactly one winner, so the sum of victories is the amount of battles;
+        // the chats the bot has been removed from are not taken into account
+        sqlx::query_as!(
+            GlobalStats,
+            r#"SELECT (SELECT count(*) FROM Users) AS "users!",
+                      (SELECT count(*) FROM Chats WHERE active) AS "chats!",
+                      (SELECT count(*) FROM Dicks d JOIN Chats c ON d.chat_id = c.id WHERE c.active) AS "dicks!",
+                      (SELECT count(*) FROM Dicks d JOIN Chats c ON d.chat_id = c.id
+                          WHERE c.active AND d.updated_at >= current_date) AS "active_today!",
+                      (SELECT COALESCE(sum(battles_won), 0)::bigint FROM Battle_Stats bs
+                          JOIN Chats c ON bs.chat_id = c.id WHERE c.active) AS "battles!",
+                      (SELECT count(*) FROM Loans l JOIN Chats c ON l.chat_id = c.id
+                          WHERE c.active AND l.repaid_at IS NULL) AS "active_loans!",
+                      (SELECT count(*) FROM Promo_Code_Activations) AS "promo_activations!""#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("couldn't get the global stats")
+    }
+);
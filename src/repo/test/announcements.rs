@@ -1,7 +1,7 @@
 use crate::config::Announcement;
 use crate::domain::SupportedLanguage::{EN, RU};
 use crate::domain::{LanguageCode, SupportedLanguage};
-use crate::repo::test::{dicks, start_postgres, CHAT_ID_KIND};
+use crate::repo::test::{dicks, start_postgres, CHAT_ID_KIND, USER_ID};
 use crate::{config, repo};
 use sqlx::{Pool, Postgres};
 use std::collections::HashMap;
@@ -92,6 +92,56 @@ async fn test_no_announcements() {
     assert!(announcement.is_none());
 }
 
+#[tokio::test]
+async fn test_overridden() {
+    let (_container, db) = start_postgres().await;
+    let [en, ru] = get_languages();
+    create_chat(&db).await;
+
+    let announcements_config = config::AnnouncementsConfig {
+        max_shows: 1,
+        announcements: get_announcements_as_map(1),
+    };
+    let ann_repo = repo::Announcements::new(db.clone(), announcements_config);
+
+    // Ensure the text set by an operator takes precedence over the properties:
+
+    ann_repo
+        .set_text(EN, "override", USER_ID)
+        .await
+        .expect("couldn't set an announcement");
+    let announcement = ann_repo
+        .get_new(&CHAT_ID_KIND, &en)
+        .await
+        .expect("couldn't get an announcement");
+    assert_eq!(announcement, Some("override".to_owned()));
+
+    // Ensure an empty text disables the announcement:
+
+    ann_repo
+        .set_text(RU, "", USER_ID)
+        .await
+        .expect("couldn't disable an announcement");
+    let announcement = ann_repo
+        .get_new(&CHAT_ID_KIND, &ru)
+        .await
+        .expect("couldn't get an announcement in Russian");
+    assert!(announcement.is_none());
+
+    // Ensure the announcement from the properties returns after a reset:
+
+    let reset = ann_repo
+        .reset_text(RU)
+        .await
+        .expect("couldn't reset an announcement");
+    assert!(reset);
+    let announcement = ann_repo
+        .get_new(&CHAT_ID_KIND, &ru)
+        .await
+        .expect("couldn't get an announcement in Russian after the reset");
+    assert_eq!(announcement, Some("тест 1".to_owned()));
+}
+
 async fn create_chat(db: &Pool<Postgres>) {
     let chat_id_part = CHAT_ID_KIND.clone().into();
     dicks::create_user_and_dick_2(db, &chat_id_part, "Ann").await;
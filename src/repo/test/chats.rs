@@ -1,6 +1,7 @@
+use crate::domain::SupportedLanguage;
 use crate::repo;
-use crate::repo::test::dicks::create_user;
-use crate::repo::test::{start_postgres, CHAT_ID, UID};
+use crate::repo::test::dicks::{create_another_user_and_dick, create_user};
+use crate::repo::test::{start_postgres, CHAT_ID, CHAT_ID_KIND, UID};
 use crate::repo::{ChatIdFull, ChatIdPartiality};
 use sqlx::{Pool, Postgres};
 use teloxide::types::{ChatId, UserId};
@@ -30,6 +31,203 @@ async fn upsert_chat() {
     two_separate_chats(&db, &chats, chat_id_full).await;
 }
 
+#[tokio::test]
+async fn chat_language() {
+    let (_container, db) = start_postgres().await;
+    let chats = repo::Chats::new(db.clone(), Default::default());
+
+    let lang = chats.get_language(&CHAT_ID_KIND).await
+        .expect("couldn't get the language of a non-existent chat");
+    assert_eq!(lang, None);
+
+    let chat_id = ChatIdPartiality::Specific(CHAT_ID_KIND);
+    chats.set_language(&chat_id, Some(SupportedLanguage::RU)).await
+        .expect("couldn't set the language");
+    let lang = chats.get_language(&CHAT_ID_KIND).await
+        .expect("couldn't get the language");
+    assert_eq!(lang, Some(SupportedLanguage::RU));
+
+    chats.set_language(&chat_id, None).await
+        .expect("couldn't reset the language");
+    let lang = chats.get_language(&CHAT_ID_KIND).await
+        .expect("couldn't get the language");
+    assert_eq!(lang, None);
+}
+
+#[tokio::test]
+async fn chat_timezone() {
+    let (_container, db) = start_postgres().await;
+    let chats = repo::Chats::new(db.clone(), Default::default());
+
+    let timezone = chats.get_timezone(&CHAT_ID_KIND).await
+        .expect("couldn't get the timezone of a non-existent chat");
+    assert_eq!(timezone, None);
+
+    let unknown = chats.find_timezone("Mars/Olympus_Mons").await
+        .expect("couldn't look for an unknown timezone");
+    assert_eq!(unknown, None);
+    let timezone = chats.find_timezone("asia/tokyo").await
+        .expect("couldn't look for a timezone")
+        .expect("Asia/Tokyo must be known to PostgreSQL");
+    assert_eq!(timezone, "Asia/Tokyo");
+
+    let chat_id = ChatIdPartiality::Specific(CHAT_ID_KIND);
+    chats.set_timezone(&chat_id, Some(&timezone)).await
+        .expect("couldn't set the timezone");
+    let actual = chats.get_timezone(&CHAT_ID_KIND).await
+        .expect("couldn't get the timezone");
+    assert_eq!(actual, Some(timezone));
+    // Japan doesn't observe daylight saving time
+    let offset = chats.get_utc_offset(&CHAT_ID_KIND).await
+        .expect("couldn't get the UTC offset");
+    assert_eq!(offset.local_minus_utc(), 9 * 3600);
+
+    chats.set_timezone(&chat_id, None).await
+        .expect("couldn't reset the timezone");
+    let actual = chats.get_timezone(&CHAT_ID_KIND).await
+        .expect("couldn't get the timezone");
+    assert_eq!(actual, None);
+}
+
+#[tokio::test]
+async fn migrate_chat() {
+    let (_container, db) = start_postgres().await;
+    create_user(&db).await;
+    let chats = repo::Chats::new(db.clone(), Default::default());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let user_id = UserId(UID as u64);
+    let (group, supergroup) = (ChatId(CHAT_ID), ChatId(-1000000000000 - CHAT_ID));
+
+    let migrated = chats.migrate_chat(group, supergroup).await
+        .expect("couldn't migrate an unknown chat");
+    assert!(!migrated);
+
+    // the supergroup hasn't been used yet
+    dicks.create_or_grow(user_id, &group.into(), 5).await
+        .expect("couldn't create a dick in the group");
+    chats.set_language(&group.into(), Some(SupportedLanguage::RU)).await
+        .expect("couldn't set the language");
+    let migrated = chats.migrate_chat(group, supergroup).await
+        .expect("couldn't migrate the chat");
+    assert!(migrated);
+    let migrated = chats.migrate_chat(group, supergroup).await
+        .expect("couldn't migrate the chat twice");
+    assert!(!migrated);
+    let group_chat = chats.get_chat(group.into()).await
+        .expect("couldn't fetch the group");
+    assert!(group_chat.is_none());
+    let language = chats.get_language(&supergroup.into()).await
+        .expect("couldn't get the language of the supergroup");
+    assert_eq!(language, Some(SupportedLanguage::RU));
+    let length = dicks.fetch_length(user_id, &supergroup.into()).await
+        .expect("couldn't fetch the length in the supergroup");
+    assert_eq!(length, 5);
+
+    // the supergroup has been used before the migration was processed
+    let (group, supergroup) = (ChatId(CHAT_ID + 1), ChatId(-1000000000000 - CHAT_ID - 1));
+    dicks.create_or_grow(user_id, &group.into(), 5).await
+        .expect("couldn't create a dick in the second group");
+    dicks.create_or_grow(user_id, &supergroup.into(), 2).await
+        .expect("couldn't create a dick in the second supergroup");
+    create_another_user_and_dick(&db, &supergroup.into(), 2, "newcomer", 3).await;
+    // only the flag with the same key is dropped, the one with another counterpart is moved
+    sqlx::query!(
+        "INSERT INTO Collusion_Flags (chat_id, kind, uid, counterpart_uid, amount, occurrences)
+            SELECT id, kind, $3::bigint, counterpart_uid, 10, 1 FROM Chats
+            CROSS JOIN (VALUES ('gift_only', NULL), ('funnelling', $3::bigint + 1)) AS f(kind, counterpart_uid)
+            WHERE chat_id = $2 OR (chat_id = $1 AND kind = 'gift_only')",
+        group.0, supergroup.0, UID)
+        .execute(&db)
+        .await
+        .expect("couldn't create collusion flags");
+    // the message of the group's rain can't be edited after the migration, unlike the one of the supergroup
+    sqlx::query!(
+        "INSERT INTO Fire_Rains (chat_id, uid, amount, shares, remaining, message_id, expires_at)
+            SELECT id, $3::bigint, 10, 2, 10, CASE WHEN chat_id = $1 THEN 1 ELSE 2 END, current_timestamp + interval '10 minutes' FROM Chats
+            WHERE chat_id IN ($1, $2)",
+        group.0, supergroup.0, UID)
+        .execute(&db)
+        .await
+        .expect("couldn't create fire rains");
+    let migrated = chats.migrate_chat(group, supergroup).await
+        .expect("couldn't merge the chats");
+    assert!(migrated);
+    let message_ids = sqlx::query_scalar!(
+        "SELECT r.message_id FROM Fire_Rains r JOIN Chats c ON c.id = r.chat_id WHERE c.chat_id = $1 ORDER BY r.message_id",
+        supergroup.0)
+        .fetch_all(&db)
+        .await
+        .expect("couldn't fetch the fire rains");
+    assert_eq!(message_ids, vec![Some(2), None]);
+    let flags = sqlx::query_scalar!(
+        r#"SELECT count(*) AS "count!" FROM Collusion_Flags f JOIN Chats c ON c.id = f.chat_id WHERE c.chat_id = $1"#,
+        supergroup.0)
+        .fetch_one(&db)
+        .await
+        .expect("couldn't count the collusion flags");
+    assert_eq!(flags, 2);
+    let top = dicks.get_top(&supergroup.into(), 0, 10).await
+        .expect("couldn't fetch the top of the merged supergroup");
+    let lengths = top.iter().map(|d| d.length).collect::<Vec<i32>>();
+    assert_eq!(lengths, vec![7, 3]);
+    let count = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM Chats WHERE chat_id IN ($1, $2)"#,
+        group.0, supergroup.0)
+        .fetch_one(&db)
+        .await
+        .expect("couldn't count the chats");
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn chat_lifecycle() {
+    let (_container, db) = start_postgres().await;
+    create_user(&db).await;
+    let chats = repo::Chats::new(db.clone(), Default::default());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let stats = repo::GlobalStatsRepo::new(db.clone());
+    let chat_id = ChatId(CHAT_ID);
+
+    let updated = chats.set_active(chat_id, false).await
+        .expect("couldn't deactivate an unknown chat");
+    assert!(!updated);
+
+    dicks.create_or_grow(UserId(UID as u64), &chat_id.into(), 5).await
+        .expect("couldn't create a dick");
+    let updated = chats.set_active(chat_id, false).await
+        .expect("couldn't deactivate the chat");
+    assert!(updated);
+    let global_stats = stats.get().await
+        .expect("couldn't get the global stats");
+    assert_eq!(global_stats.chats, 0);
+    assert_eq!(global_stats.dicks, 0);
+
+    // the retention period hasn't passed yet
+    let purged = chats.purge_inactive(1).await
+        .expect("couldn't purge inactive chats");
+    assert_eq!(purged, 0);
+
+    chats.set_active(chat_id, true).await
+        .expect("couldn't activate the chat");
+    let global_stats = stats.get().await
+        .expect("couldn't get the global stats");
+    assert_eq!(global_stats.chats, 1);
+    assert_eq!(global_stats.dicks, 1);
+
+    chats.set_active(chat_id, false).await
+        .expect("couldn't deactivate the chat again");
+    sqlx::query!("UPDATE Chats SET left_at = left_at - interval '2 days' WHERE chat_id = $1",
+        CHAT_ID)
+        .execute(&db)
+        .await
+        .expect("couldn't move the time of leaving back");
+    let purged = chats.purge_inactive(1).await
+        .expect("couldn't purge inactive chats");
+    assert_eq!(purged, 1);
+    let chat = chats.get_chat(chat_id.into()).await
+        .expect("couldn't fetch the purged chat");
+    assert!(chat.is_none());
+}
+
 async fn clear_dicks_and_chats(db: &Pool<Postgres>) {
     sqlx::query!("DELETE FROM Dicks")
         .execute(db)
@@ -0,0 +1,80 @@
+use crate::config::{CollusionConfig, GiftsConfig};
+use crate::repo;
+use crate::repo::test::dicks::create_another_user_and_dick;
+use crate::repo::test::{start_postgres, CHAT_ID, CHAT_ID_KIND, NAME, UID, USER_ID};
+use crate::repo::{ChatIdPartiality, ReviewOutcome, ReviewVerdict, TransferKind, TransferLeg};
+use teloxide::types::{ChatId, UserId};
+
+#[tokio::test]
+async fn detect_and_review() {
+    let (_container, db) = start_postgres().await;
+    let users = repo::Users::new(db.clone());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let transfers = repo::Transfers::new(db.clone(), Default::default());
+    let collusion = repo::Collusion::new(db.clone());
+    let chat_id = ChatIdPartiality::from(ChatId(CHAT_ID));
+    let alt = UserId(UID as u64 + 1);
+
+    users.create_or_update(USER_ID, NAME, None).await
+        .expect("couldn't create a user");
+    dicks.create_or_grow(USER_ID, &chat_id, 10).await
+        .expect("couldn't create a dick");
+    create_another_user_and_dick(&db, &chat_id, 2, "alt", 100).await;
+
+    let to_main = |amount| [TransferLeg { to: USER_ID, amount }];
+    let mut limits = GiftsConfig::default();
+    transfers.transfer(&chat_id, alt, &to_main(60), TransferKind::Gift, &limits, true).await
+        .expect("couldn't make a transfer")
+        .expect("the transfer was rejected");
+
+    let cfg = CollusionConfig {
+        window_days: 7,
+        fresh_account_days: 3,
+        min_funnelled: 50,
+        min_pvp_battles: 3,
+        pvp_loss_ratio: 0.8,
+        min_gifts_only: 5,
+    };
+    assert_eq!(collusion.detect(&cfg).await.expect("couldn't detect collusion"), 1);
+
+    limits.freeze_flagged = true;
+    let receipt = transfers.transfer(&chat_id, alt, &to_main(15), TransferKind::Gift, &limits, true).await
+        .expect("couldn't make a flagged transfer")
+        .expect("the flagged transfer was rejected");
+    assert_eq!(receipt.held, 15);
+    assert_eq!(receipt.sender_length, 25);
+    assert_eq!(receipt.recipient_lengths, vec![70]);
+
+    for _ in 0..3 {
+        collusion.log_battle(&CHAT_ID_KIND, USER_ID, alt, 5).await
+            .expect("couldn't log a battle");
+    }
+    assert_eq!(collusion.detect(&cfg).await.expect("couldn't detect collusion again"), 2);
+
+    let flags = collusion.get_pending_flags(10).await
+        .expect("couldn't get the flags");
+    let kinds: Vec<&str> = flags.iter().map(|flag| flag.kind.as_str()).collect();
+    assert_eq!(kinds, vec!["funnelling", "pvp_pair"]);
+    assert_eq!(flags[0].amount, 75);
+    assert_eq!(flags[0].held, 15);
+    assert_eq!(flags[1].amount, 15);
+    assert_eq!(flags[1].occurrences, 3);
+
+    // the pvp flag still holds the transfer
+    let outcome = collusion.review(flags[0].id, ReviewVerdict::Dismiss, USER_ID).await
+        .expect("couldn't dismiss the flag");
+    assert_eq!(outcome, Some(ReviewOutcome { transfers: 0, amount: 0 }));
+    let outcome = collusion.review(flags[1].id, ReviewVerdict::Confirm, USER_ID).await
+        .expect("couldn't confirm the flag");
+    assert_eq!(outcome, Some(ReviewOutcome { transfers: 1, amount: 15 }));
+    let outcome = collusion.review(flags[0].id, ReviewVerdict::Confirm, USER_ID).await
+        .expect("couldn't review the dismissed flag");
+    assert_eq!(outcome, None);
+
+    let alt_length = dicks.fetch_length(alt, &chat_id.kind()).await
+        .expect("couldn't fetch the length of the alt");
+    assert_eq!(alt_length, 40);
+    assert!(collusion.get_pending_flags(10).await
+        .expect("couldn't get the flags after the review")
+        .is_empty());
+}
@@ -82,6 +82,60 @@ async fn test_all_with_top_pagination_disabled() {
     check_top(&dicks, &chat_id, new_length).await;
 }
 
+#[tokio::test]
+async fn test_starter_bonus() {
+    let (_container, db) = start_postgres().await;
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let users = repo::Users::new(db.clone());
+    let user_id = UserId(UID as u64);
+    let chat_id = ChatIdKind::ID(ChatId(CHAT_ID));
+
+    let granted = dicks
+        .grant_starter_bonus(user_id, &chat_id, 5)
+        .await
+        .expect("couldn't grant the starter bonus without a chat");
+    assert_eq!(granted, None);
+
+    create_user(&db).await;
+    create_dick(&db).await;
+    let granted = dicks
+        .grant_starter_bonus(user_id, &chat_id, 5)
+        .await
+        .expect("couldn't grant the starter bonus");
+    assert_eq!(granted, Some(5));
+    let granted = dicks
+        .grant_starter_bonus(user_id, &chat_id, 5)
+        .await
+        .expect("couldn't grant the starter bonus twice");
+    assert_eq!(granted, None);
+    check_dick(&db, 5).await;
+
+    // leaving and rejoining the chat must not give the bonus again
+    users
+        .delete_from_chat(user_id, &chat_id)
+        .await
+        .expect("couldn't delete the dick");
+    create_dick(&db).await;
+    let granted = dicks
+        .grant_starter_bonus(user_id, &chat_id, 5)
+        .await
+        .expect("couldn't grant the starter bonus after rejoining");
+    assert_eq!(granted, None);
+    check_dick(&db, 0).await;
+
+    let uid = repo::UID::from(user_id);
+    let newcomers = users
+        .filter_newcomers(&[uid], 1)
+        .await
+        .expect("couldn't filter the newcomers");
+    assert!(newcomers.contains(&uid));
+    let newcomers = users
+        .filter_newcomers(&[uid], 0)
+        .await
+        .expect("couldn't filter the newcomers without a grace period");
+    assert!(newcomers.is_empty());
+}
+
 #[tokio::test]
 async fn test_top_page() {
     let (_container, db) = start_postgres().await;
@@ -160,12 +214,29 @@ async fn test_pvp() {
         assert_eq!(gr2.pos_in_top, Some(1));
         assert_eq!(gr1.pos_in_top, Some(2));
     }
+    {
+        // centimeters received before the growth of the day don't count as the growth
+        let uid2 = UserId((UID + 1) as u64);
+        sqlx::query!("UPDATE Dicks SET updated_at = updated_at - interval '1 day', bonus_attempts = (bonus_attempts + 1) WHERE uid = $1",
+            uid2.0 as i64)
+            .execute(&db)
+            .await
+            .expect("couldn't move the growth to yesterday");
+        dicks
+            .move_length(chat_id_part, uid, uid2, 0)
+            .await
+            .expect("couldn't move the length again");
+        dicks
+            .create_or_grow(uid2, chat_id_part, 1)
+            .await
+            .expect("couldn't grow after receiving the length");
+    }
 }
 
 pub async fn create_user(db: &Pool<Postgres>) {
     let users = repo::Users::new(db.clone());
     users
-        .create_or_update(UserId(UID as u64), NAME)
+        .create_or_update(UserId(UID as u64), NAME, None)
         .await
         .expect("couldn't create a user");
 }
@@ -188,7 +259,7 @@ pub async fn create_another_user_and_dick(
     let dicks = repo::Dicks::new(db.clone(), Default::default());
     let uid2 = UserId((UID + n - 1) as u64);
     users
-        .create_or_update(uid2, name)
+        .create_or_update(uid2, name, None)
         .await
         .unwrap_or_else(|_| panic!("couldn't create a user #{n}"));
     dicks
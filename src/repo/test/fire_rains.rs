@@ -0,0 +1,81 @@
+use crate::config::GiftsConfig;
+use crate::repo;
+use crate::repo::test::dicks::create_another_user_and_dick;
+use crate::repo::test::{start_postgres, CHAT_ID, CHAT_ID_KIND, NAME, UID, USER_ID};
+use crate::repo::{ChatIdPartiality, ClaimReceipt, ClaimRejection, TransferRejection};
+use teloxide::types::{ChatId, UserId};
+
+#[tokio::test]
+async fn claim_and_refund() {
+    let (_container, db) = start_postgres().await;
+    let users = repo::Users::new(db.clone());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let fire_rains = repo::FireRains::new(db.clone(), Default::default());
+    let chat_id = ChatIdPartiality::from(ChatId(CHAT_ID));
+    let limits = GiftsConfig::default();
+
+    users.create_or_update(USER_ID, NAME, None).await
+        .expect("couldn't create a user");
+    dicks.create_or_grow(USER_ID, &chat_id, 20).await
+        .expect("couldn't create a dick");
+    for n in 2..=4 {
+        create_another_user_and_dick(&db, &chat_id, n, &format!("user{n}"), 1).await;
+    }
+    let other = |n: u64| UserId(UID as u64 + n - 1);
+
+    let rejection = fire_rains.start(&chat_id, USER_ID, 30, 3, &limits, 10).await
+        .expect("couldn't start a too big rain");
+    assert_eq!(rejection, Err(TransferRejection::NotEnough { current: 20 }));
+
+    let rain_id = fire_rains.start(&chat_id, USER_ID, 10, 3, &limits, 10).await
+        .expect("couldn't start a rain")
+        .expect("the rain was rejected");
+    let length = dicks.fetch_length(USER_ID, &CHAT_ID_KIND).await
+        .expect("couldn't fetch the length of the sender");
+    assert_eq!(length, 10);
+
+    let (rains, limits_ref) = (&fire_rains, &limits);
+    let claim = move |uid| rains.claim(rain_id, uid, limits_ref);
+    assert_eq!(claim(USER_ID).await.expect("couldn't claim the own rain"), Err(ClaimRejection::OwnRain));
+    let receipt = claim(other(2)).await
+        .expect("couldn't claim a share")
+        .expect("the claim was rejected");
+    assert_eq!(receipt, ClaimReceipt { amount: 3, tax: 0, held: false });
+    assert_eq!(claim(other(2)).await.expect("couldn't claim twice"), Err(ClaimRejection::AlreadyClaimed));
+    claim(other(3)).await
+        .expect("couldn't claim the second share")
+        .expect("the second claim was rejected");
+    // the last share takes the rest
+    let receipt = claim(other(4)).await
+        .expect("couldn't claim the last share")
+        .expect("the last claim was rejected");
+    assert_eq!(receipt.amount, 4);
+    assert_eq!(claim(UserId(UID as u64 + 10)).await.expect("couldn't claim a finished rain"), Err(ClaimRejection::Over));
+
+    let rain = fire_rains.get(rain_id).await
+        .expect("couldn't get the rain")
+        .expect("the rain is missing");
+    assert!(rain.finished);
+    assert_eq!(rain.remaining, 0);
+    let claims = fire_rains.get_claims(rain_id).await
+        .expect("couldn't get the claims");
+    let amounts: Vec<i32> = claims.iter().map(|claim| claim.amount).collect();
+    assert_eq!(amounts, vec![3, 3, 4]);
+    let length = dicks.fetch_length(other(4), &CHAT_ID_KIND).await
+        .expect("couldn't fetch the length of the last catcher");
+    assert_eq!(length, 5);
+
+    // nobody catches the next one in time
+    let rain_id = fire_rains.start(&chat_id, USER_ID, 6, 2, &limits, 0).await
+        .expect("couldn't start an expiring rain")
+        .expect("the expiring rain was rejected");
+    let refunded = fire_rains.refund_expired().await
+        .expect("couldn't refund the expired rains");
+    assert_eq!(refunded.len(), 1);
+    assert_eq!(refunded[0].id, rain_id);
+    assert_eq!(refunded[0].refunded, 6);
+    assert_eq!(fire_rains.claim(rain_id, other(2), &limits).await.expect("couldn't claim an expired rain"), Err(ClaimRejection::Over));
+    let length = dicks.fetch_length(USER_ID, &CHAT_ID_KIND).await
+        .expect("couldn't fetch the length of the sender after the refund");
+    assert_eq!(length, 10);
+}
@@ -0,0 +1,40 @@
+use crate::repo;
+use crate::repo::test::{start_postgres, USER_ID};
+use teloxide::types::UserId;
+
+const OPERATOR_ID: UserId = UserId(1);
+
+#[tokio::test]
+async fn set_and_remove() {
+    let (_container, db) = start_postgres().await;
+    let restrictions = repo::GiftRestrictions::new(db.clone());
+
+    let name = restrictions
+        .get(USER_ID)
+        .await
+        .expect("couldn't get a restriction");
+    assert!(name.is_none());
+
+    for custom_name in ["Mr. Nobody", "Ms. Nobody"] {
+        restrictions
+            .set(USER_ID, custom_name, OPERATOR_ID)
+            .await
+            .expect("couldn't set a restriction");
+        let name = restrictions
+            .get(USER_ID)
+            .await
+            .expect("couldn't get the restriction");
+        assert_eq!(name, Some(custom_name.to_owned()));
+    }
+
+    let removed = restrictions
+        .remove(USER_ID)
+        .await
+        .expect("couldn't remove the restriction");
+    assert!(removed);
+    let removed = restrictions
+        .remove(USER_ID)
+        .await
+        .expect("couldn't remove the restriction twice");
+    assert!(!removed);
+}
@@ -1,11 +1,17 @@
 mod announcements;
 mod chats;
+mod collusion;
 mod dicks;
+mod fire_rains;
+mod gift_restrictions;
 mod import;
 mod loans;
+mod moderation;
+mod personal_data;
 mod promo;
 mod pvpstats;
 mod stats;
+mod transfers;
 mod users;
 
 use crate::config::DatabaseConfig;
@@ -0,0 +1,113 @@
+use crate::repo;
+use crate::repo::test::dicks::{create_dick, create_user};
+use crate::repo::test::{start_postgres, CHAT_ID_KIND, USER_ID};
+use crate::repo::{AdminAction, ChatIdPartiality};
+use teloxide::types::UserId;
+
+const ADMIN_ID: UserId = UserId(1);
+
+#[tokio::test]
+async fn ban_and_unban() {
+    let (_container, db) = start_postgres().await;
+    let moderation = repo::Moderation::new(db.clone(), Default::default());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let users = repo::Users::new(db.clone());
+    create_user(&db).await;
+    create_dick(&db).await;
+    let chat_id: ChatIdPartiality = CHAT_ID_KIND.into();
+
+    assert!(!moderation.is_banned(&CHAT_ID_KIND, USER_ID).await.expect("couldn't check the ban"));
+    assert!(moderation.ban(&chat_id, USER_ID, ADMIN_ID).await.expect("couldn't ban the user"));
+    assert!(!moderation.ban(&chat_id, USER_ID, ADMIN_ID).await.expect("couldn't ban the user twice"));
+    assert!(moderation.is_banned(&CHAT_ID_KIND, USER_ID).await.expect("couldn't check the ban"));
+
+    let top = dicks.get_top(&CHAT_ID_KIND, 0, 10).await.expect("couldn't fetch the top");
+    assert!(top.is_empty());
+    let member = users.get_random_active_member(&CHAT_ID_KIND).await
+        .expect("couldn't fetch a random member");
+    assert!(member.is_none());
+
+    assert!(moderation.unban(&CHAT_ID_KIND, USER_ID).await.expect("couldn't unban the user"));
+    assert!(!moderation.unban(&CHAT_ID_KIND, USER_ID).await.expect("couldn't unban the user twice"));
+    let top = dicks.get_top(&CHAT_ID_KIND, 0, 10).await.expect("couldn't fetch the top");
+    assert_eq!(top.len(), 1);
+}
+
+#[tokio::test]
+async fn freeze_and_reset() {
+    let (_container, db) = start_postgres().await;
+    let moderation = repo::Moderation::new(db.clone(), Default::default());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    create_user(&db).await;
+    create_dick(&db).await;
+    let chat_id: ChatIdPartiality = CHAT_ID_KIND.into();
+
+    assert!(!moderation.is_frozen(&CHAT_ID_KIND).await.expect("couldn't check the frozen flag"));
+    moderation.set_frozen(&chat_id, true).await.expect("couldn't freeze the chat");
+    assert!(moderation.is_frozen(&CHAT_ID_KIND).await.expect("couldn't check the frozen flag"));
+
+    let growth = dicks.set_length(&CHAT_ID_KIND, USER_ID, 42).await
+        .expect("couldn't set the length")
+        .expect("the user has no dick");
+    assert_eq!(growth.new_length, 42);
+    dicks.set_dod_winner(&chat_id, USER_ID, 1).await
+        .expect("couldn't elect the winner")
+        .expect("the winner has no dick");
+
+    let deleted = moderation.reset_chat(&CHAT_ID_KIND).await.expect("couldn't reset the chat");
+    assert_eq!(deleted, 1);
+    let missing = dicks.set_length(&CHAT_ID_KIND, USER_ID, 1).await
+        .expect("couldn't set the length");
+    assert!(missing.is_none());
+    // the winner of the day has been reset too
+    create_dick(&db).await;
+    dicks.set_dod_winner(&chat_id, USER_ID, 1).await
+        .expect("couldn't elect the winner after the reset")
+        .expect("the winner has no dick after the reset");
+
+    moderation.log_action(&chat_id, ADMIN_ID, AdminAction::Freeze, None, None).await
+        .expect("couldn't log the action");
+    moderation.log_action(&chat_id, ADMIN_ID, AdminAction::ResetChat, None, Some(deleted.to_string())).await
+        .expect("couldn't log the action");
+    let actions = moderation.get_logged_actions(&CHAT_ID_KIND).await
+        .expect("couldn't fetch the audit log");
+    assert_eq!(actions, vec!["freeze", "reset_chat"]);
+}
+
+#[tokio::test]
+async fn disable_and_enable_commands() {
+    let (_container, db) = start_postgres().await;
+    let moderation = repo::Moderation::new(db.clone(), Default::default());
+    let chat_id: ChatIdPartiality = CHAT_ID_KIND.into();
+
+    assert!(!moderation.is_command_disabled(&CHAT_ID_KIND, "pvp").await.expect("couldn't check the command"));
+    assert!(!moderation.enable_command(&CHAT_ID_KIND, "pvp").await.expect("couldn't enable the command"));
+
+    assert!(moderation.disable_command(&chat_id, "pvp", ADMIN_ID).await.expect("couldn't disable the command"));
+    assert!(!moderation.disable_command(&chat_id, "pvp", ADMIN_ID).await.expect("couldn't disable the command twice"));
+    assert!(moderation.disable_command(&chat_id, "fire", ADMIN_ID).await.expect("couldn't disable the command"));
+    assert!(moderation.is_command_disabled(&CHAT_ID_KIND, "pvp").await.expect("couldn't check the command"));
+    assert!(!moderation.is_command_disabled(&CHAT_ID_KIND, "grow").await.expect("couldn't check the command"));
+    let disabled = moderation.get_disabled_commands(&CHAT_ID_KIND).await
+        .expect("couldn't fetch the disabled commands");
+    assert_eq!(disabled, vec!["fire", "pvp"]);
+
+    assert!(moderation.enable_command(&CHAT_ID_KIND, "pvp").await.expect("couldn't enable the command"));
+    assert!(!moderation.is_command_disabled(&CHAT_ID_KIND, "pvp").await.expect("couldn't check the command"));
+}
+
+#[tokio::test]
+async fn game_topic() {
+    let (_container, db) = start_postgres().await;
+    let moderation = repo::Moderation::new(db.clone(), Default::default());
+    let chat_id: ChatIdPartiality = CHAT_ID_KIND.into();
+
+    let topic = moderation.get_game_topic(&CHAT_ID_KIND).await.expect("couldn't get the game topic");
+    assert_eq!(topic, None);
+    moderation.set_game_topic(&chat_id, Some(42)).await.expect("couldn't set the game topic");
+    let topic = moderation.get_game_topic(&CHAT_ID_KIND).await.expect("couldn't get the game topic");
+    assert_eq!(topic, Some(42));
+    moderation.set_game_topic(&chat_id, None).await.expect("couldn't reset the game topic");
+    let topic = moderation.get_game_topic(&CHAT_ID_KIND).await.expect("couldn't get the game topic");
+    assert_eq!(topic, None);
+}
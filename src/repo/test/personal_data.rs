@@ -0,0 +1,36 @@
+use crate::repo;
+use crate::repo::test::dicks::{create_dick, create_user};
+use crate::repo::test::{start_postgres, CHAT_ID, NAME, USER_ID};
+
+#[tokio::test]
+async fn export() {
+    let (_container, db) = start_postgres().await;
+    let personal_data = repo::PersonalDataRepo::new(db.clone());
+
+    let data = personal_data.export(USER_ID).await
+        .expect("couldn't export the data of an unknown user");
+    assert!(data.is_none());
+
+    create_user(&db).await;
+    create_dick(&db).await;
+
+    let data = personal_data.export(USER_ID).await
+        .expect("couldn't export the data")
+        .expect("no data was exported");
+    assert_eq!(data.user.name, NAME);
+    assert_eq!(data.user.username, None);
+    assert_eq!(data.dicks.len(), 1);
+    assert_eq!(data.dicks[0].chat_id, Some(CHAT_ID));
+    assert!(data.battle_stats.is_empty());
+    assert!(data.battles.is_empty());
+    assert!(data.loans.is_empty());
+    assert!(data.transfers.is_empty());
+    assert!(data.fire_rains.is_empty());
+    assert!(data.promo_activations.is_empty());
+    assert!(data.dod_wins.is_empty());
+    assert!(data.starter_bonuses.is_empty());
+    assert!(data.left_chats.is_empty());
+
+    let json = serde_json::to_value(&data).expect("couldn't serialize the data");
+    assert_eq!(json["user"]["uid"], USER_ID.0);
+}
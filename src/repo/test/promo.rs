@@ -13,7 +13,7 @@ async fn activate() {
     let (_container, db) = start_postgres().await;
 
     let promo = repo::Promo::new(db.clone());
-    promo
+    let created = promo
         .create(PromoCodeParams {
             code: PROMO_CODE.to_owned(),
             bonus_length: PROMO_BONUS,
@@ -21,6 +21,7 @@ async fn activate() {
         })
         .await
         .expect("couldn't create a promo code");
+    assert!(created);
 
     create_user(&db).await;
     create_dick(&db).await;
@@ -36,3 +37,41 @@ async fn activate() {
     let res = promo.activate(UserId(UID as u64), PROMO_CODE).await;
     assert!(res.is_err());
 }
+
+#[tokio::test]
+async fn create_and_disable() {
+    let (_container, db) = start_postgres().await;
+
+    let promo = repo::Promo::new(db.clone());
+    let params = |code: &str| PromoCodeParams {
+        code: code.to_owned(),
+        bonus_length: PROMO_BONUS,
+        capacity: 1,
+    };
+    let created = promo
+        .create(params(PROMO_CODE))
+        .await
+        .expect("couldn't create a promo code");
+    assert!(created);
+    let created = promo
+        .create(params(PROMO_CODE_UPPERCASE))
+        .await
+        .expect("couldn't try to create a duplicate promo code");
+    assert!(!created);
+
+    let disabled = promo
+        .disable(PROMO_CODE_UPPERCASE)
+        .await
+        .expect("couldn't disable the promo code");
+    assert!(disabled);
+    let disabled = promo
+        .disable(PROMO_CODE)
+        .await
+        .expect("couldn't disable the promo code twice");
+    assert!(!disabled);
+
+    create_user(&db).await;
+    create_dick(&db).await;
+    let res = promo.activate(UserId(UID as u64), PROMO_CODE).await;
+    assert!(res.is_err());
+}
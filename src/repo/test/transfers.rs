@@ -0,0 +1,73 @@
+use crate::config::GiftsConfig;
+use crate::repo;
+use crate::repo::test::dicks::create_another_user_and_dick;
+use crate::repo::test::{start_postgres, CHAT_ID, NAME, UID, USER_ID};
+use crate::repo::{ChatIdPartiality, TransferKind, TransferLeg, TransferRejection};
+use teloxide::types::{ChatId, UserId};
+
+#[tokio::test]
+async fn transfer() {
+    let (_container, db) = start_postgres().await;
+    let users = repo::Users::new(db.clone());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let transfers = repo::Transfers::new(db.clone(), Default::default());
+    let chat_id = ChatIdPartiality::from(ChatId(CHAT_ID));
+    let recipient = UserId(UID as u64 + 1);
+
+    users.create_or_update(USER_ID, NAME, None).await
+        .expect("couldn't create a user");
+    dicks.create_or_grow(USER_ID, &chat_id, 20).await
+        .expect("couldn't create a dick");
+    create_another_user_and_dick(&db, &chat_id, 2, "recipient", 1).await;
+
+    let gift = |amount| [TransferLeg { to: recipient, amount }];
+    let mut limits = GiftsConfig::default();
+
+    let receipt = transfers.transfer(&chat_id, USER_ID, &gift(5), TransferKind::Gift, &limits, true).await
+        .expect("couldn't make a transfer")
+        .expect("the transfer without limits was rejected");
+    assert_eq!(receipt.sender_length, 15);
+    assert_eq!(receipt.recipient_lengths, vec![6]);
+    assert_eq!(receipt.tax, 0);
+
+    let rejection = transfers.transfer(&chat_id, USER_ID, &gift(100), TransferKind::Gift, &limits, true).await
+        .expect("couldn't try to transfer too much")
+        .expect_err("too much was transferred");
+    assert_eq!(rejection, TransferRejection::NotEnough { current: 15 });
+
+    limits.tax_ratio = 0.5;
+    limits.daily_cap = 10;
+    let receipt = transfers.transfer(&chat_id, USER_ID, &gift(4), TransferKind::Gift, &limits, true).await
+        .expect("couldn't make a taxed transfer")
+        .expect("the taxed transfer was rejected");
+    assert_eq!(receipt.sender_length, 11);
+    assert_eq!(receipt.recipient_lengths, vec![8]);
+    assert_eq!(receipt.tax, 2);
+    let rejection = transfers.transfer(&chat_id, USER_ID, &gift(2), TransferKind::Fire, &limits, true).await
+        .expect("couldn't try to exceed the daily cap")
+        .expect_err("the daily cap was exceeded");
+    assert_eq!(rejection, TransferRejection::DailyCap { left: 1 });
+
+    limits.cooldown_minutes = 10;
+    let rejection = transfers.transfer(&chat_id, USER_ID, &gift(1), TransferKind::Gift, &limits, true).await
+        .expect("couldn't try to transfer during the cooldown")
+        .expect_err("the cooldown was ignored");
+    assert_eq!(rejection, TransferRejection::Cooldown { minutes_left: 10 });
+
+    limits = GiftsConfig {
+        recipient_min_account_age_days: 1,
+        ..Default::default()
+    };
+    let rejection = transfers.transfer(&chat_id, USER_ID, &gift(1), TransferKind::Gift, &limits, true).await
+        .expect("couldn't try to transfer to a newcomer")
+        .expect_err("a newcomer received a gift");
+    assert_eq!(rejection, TransferRejection::RecipientTooNew(recipient));
+    // randomly chosen recipients are not checked
+    transfers.transfer(&chat_id, USER_ID, &gift(1), TransferKind::Fire, &limits, false).await
+        .expect("couldn't fire to a newcomer")
+        .expect("the fire to a newcomer was rejected");
+
+    let length = dicks.fetch_length(recipient, &chat_id.kind()).await
+        .expect("couldn't fetch the length of the recipient");
+    assert_eq!(length, 9);
+}
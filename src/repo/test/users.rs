@@ -18,7 +18,7 @@ async fn create_or_update() {
     assert_eq!(members.len(), 0);
 
     let u = users
-        .create_or_update(UserId(UID as u64), NAME)
+        .create_or_update(UserId(UID as u64), NAME, None)
         .await
         .expect("creation failed");
     check_user_with_name(&u, NAME);
@@ -32,7 +32,7 @@ async fn create_or_update() {
     const NEW_NAME: &str = "foo_bar";
 
     let u = users
-        .create_or_update(UserId(UID as u64), NEW_NAME)
+        .create_or_update(UserId(UID as u64), NEW_NAME, None)
         .await
         .expect("creation failed");
     check_user_with_name(&u, NEW_NAME);
@@ -198,7 +198,7 @@ async fn create_member(db: &Pool<Postgres>) {
     let uid = UserId(UID as u64);
 
     users
-        .create_or_update(uid, NAME)
+        .create_or_update(uid, NAME, None)
         .await
         .expect("couldn't create a user");
     dicks
@@ -206,3 +206,115 @@ async fn create_member(db: &Pool<Postgres>) {
         .await
         .expect("couldn't create a dick");
 }
+
+#[tokio::test]
+async fn delete_from_chat() {
+    let (_container, db) = start_postgres().await;
+    let users = repo::Users::new(db.clone());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let user_id = UserId(UID as u64);
+    let chat_id = ChatIdKind::ID(ChatId(CHAT_ID));
+    let another_chat_id = ChatIdKind::ID(ChatId(CHAT_ID + 1));
+
+    users.create_or_update(user_id, NAME, None).await.expect("couldn't create a user");
+    for chat_id in [&chat_id, &another_chat_id] {
+        dicks.create_or_grow(user_id, &chat_id.clone().into(), 1).await
+            .expect("couldn't create a dick");
+    }
+
+    let deleted = users.delete_from_chat(user_id, &chat_id).await
+        .expect("couldn't delete the user from the chat");
+    assert_eq!(deleted, 1);
+    assert!(users.get_chat_members(&chat_id).await.expect("couldn't fetch the members").is_empty());
+    assert_eq!(users.get_chat_members(&another_chat_id).await.expect("couldn't fetch the members").len(), 1);
+    assert!(users.get(user_id).await.expect("couldn't fetch the user").is_some());
+
+    let deleted = users.delete_from_chat(user_id, &chat_id).await
+        .expect("couldn't delete the user from the chat twice");
+    assert_eq!(deleted, 0);
+}
+
+#[tokio::test]
+async fn set_membership() {
+    let (_container, db) = start_postgres().await;
+    let users = repo::Users::new(db.clone());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let user_id = UserId(UID as u64);
+    let chat_id = ChatId(CHAT_ID);
+
+    let recorded = users.set_membership(chat_id, user_id, false).await
+        .expect("couldn't set the membership of an unknown user");
+    assert!(!recorded);
+
+    create_member(&db).await;
+    let recorded = users.set_membership(chat_id, user_id, false).await
+        .expect("couldn't mark the user as left");
+    assert!(recorded);
+    let top = dicks.get_top(&chat_id.into(), 0, 10).await
+        .expect("couldn't fetch the top");
+    assert!(top.is_empty());
+    let member = users.get_random_active_member(&chat_id.into()).await
+        .expect("couldn't fetch a random active member");
+    assert!(member.is_none());
+
+    users.set_membership(chat_id, user_id, true).await
+        .expect("couldn't mark the user as rejoined");
+    let top = dicks.get_top(&chat_id.into(), 0, 10).await
+        .expect("couldn't fetch the top");
+    assert_eq!(top.len(), 1);
+    let member = users.get_random_active_member(&chat_id.into()).await
+        .expect("couldn't fetch a random active member");
+    assert!(member.is_some());
+
+    // the update about the return has been missed, but the user grows in the chat again
+    users.set_membership(chat_id, user_id, false).await
+        .expect("couldn't mark the user as left again");
+    sqlx::query!("UPDATE Dicks SET updated_at = updated_at - interval '1 day', bonus_attempts = (bonus_attempts + 1) WHERE uid = $1",
+        UID)
+        .execute(&db)
+        .await
+        .expect("couldn't move the growth to yesterday");
+    dicks.create_or_grow(user_id, &ChatIdKind::from(chat_id).into(), 1).await
+        .expect("couldn't grow the dick");
+    let top = dicks.get_top(&chat_id.into(), 0, 10).await
+        .expect("couldn't fetch the top");
+    assert_eq!(top.len(), 1);
+}
+
+#[tokio::test]
+async fn get_by_username() {
+    let (_container, db) = start_postgres().await;
+    let users = repo::Users::new(db.clone());
+    let (uid, another_uid) = (UserId(UID as u64), UserId(UID as u64 + 1));
+
+    users.create_or_update(uid, NAME, Some("Grower")).await
+        .expect("couldn't create a user");
+    let user = users.get_by_username("@grower").await
+        .expect("couldn't look up the user")
+        .expect("the user must be found regardless of the case");
+    assert_eq!(user.uid, UID);
+
+    // the username was taken by another user
+    users.create_or_update(another_uid, NAME, None).await
+        .expect("couldn't create another user");
+    users.update_username(another_uid, Some("grower")).await
+        .expect("couldn't update the username");
+    let user = users.get_by_username("Grower").await
+        .expect("couldn't look up the user again")
+        .expect("the new owner of the username must be found");
+    assert_eq!(user.uid, UID + 1);
+
+    // the username was taken back by the first user who came with it again
+    users.create_or_update(uid, NAME, Some("GROWER")).await
+        .expect("couldn't update the first user");
+    let user = users.get_by_username("grower").await
+        .expect("couldn't look up the user once more")
+        .expect("the first user must own the username again");
+    assert_eq!(user.uid, UID);
+
+    users.update_username(uid, None).await
+        .expect("couldn't reset the username");
+    let user = users.get_by_username("grower").await
+        .expect("couldn't look up a released username");
+    assert!(user.is_none());
+}
@@ -1,6 +1,9 @@
 use super::ChatIdPartiality;
+use crate::config;
 use crate::repository;
 use anyhow::Context;
+use sqlx::{Postgres, Transaction};
+use std::collections::{HashMap, HashSet};
 use teloxide::types::UserId;
 
 #[derive(sqlx::Type, Debug, Clone, Copy)]
@@ -10,29 +13,238 @@ pub enum TransferKind {
     Fire,
 }
 
+/// A part of a transfer going to one recipient.
+#[derive(Debug, Clone, Copy)]
+pub struct TransferLeg {
+    pub to: UserId,
+    pub amount: u16,
+}
+
+#[derive(Debug)]
+pub struct TransferReceipt {
+    pub sender_length: i32,
+    /// New lengths of the recipients in the order of the legs
+    pub recipient_lengths: Vec<i32>,
+    /// How much was burned in total
+    pub tax: i32,
+    /// How much is held until an operator reviews the collusion flags the transfer matches
+    pub held: i32,
+}
+
+/// Why a transfer was not made. Nothing is changed in such a case.
+#[derive(Debug, PartialEq)]
+pub enum TransferRejection {
+    NotEnough { current: i32 },
+    Cooldown { minutes_left: i64 },
+    DailyCap { left: i64 },
+    RecipientTooNew(UserId),
+}
+
 repository!(
     Transfers,
     with_(chats)_(Chats),
-    pub async fn log(
+    /// Moves the centimeters from the sender to the recipients and logs every leg in one transaction, unless
+    /// one of the limits is violated. The dicks of all the participants are locked during the transaction to keep
+    /// the checks against the history of transfers consistent when the same user sends several commands at once.
+    /// The age of the recipients is checked only if `check_recipients` is set: those chosen randomly
+    /// cannot be alt accounts of the sender. Legs matching a collusion flag are frozen if it's enabled:
+    /// the recipient gets nothing until the flag is reviewed.
+    pub async fn transfer(
         &self,
         chat_id: &ChatIdPartiality,
         from: UserId,
-        to: UserId,
-        amount: i32,
+        legs: &[TransferLeg],
         kind: TransferKind,
-    ) -> anyhow::Result<()> {
+        limits: &config::GiftsConfig,
+        check_recipients: bool,
+    ) -> anyhow::Result<Result<TransferReceipt, TransferRejection>> {
         let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
-        let kind_str = match kind { TransferKind::Gift => "gift", TransferKind::Fire => "fire" };
-        sqlx::query(r#"INSERT INTO transfers(chat_id, from_uid, to_uid, amount, kind)
-               VALUES ($1, $2, $3, $4, $5)"#)
-        .bind(internal_chat_id)
-        .bind(from.0 as i64)
-        .bind(to.0 as i64)
-        .bind(amount)
-        .bind(kind_str)
-        .execute(&self.pool)
-        .await
-        .context("couldn't insert transfer record")?;
+        let sender = from.0 as i64;
+        let total: i32 = legs.iter().map(|leg| leg.amount as i32).sum();
+        let recipients: Vec<i64> = legs.iter().map(|leg| leg.to.0 as i64).collect();
+        let participants: Vec<i64> = recipients.iter().copied().chain([sender]).collect();
+
+        let mut tx = self.pool.begin().await?;
+        // the rows are always locked in the same order not to deadlock when two users gift each other
+        let lengths: HashMap<i64, i32> = sqlx::query!(
+            "SELECT uid, length FROM Dicks WHERE chat_id = $1 AND uid = ANY($2) ORDER BY uid FOR UPDATE",
+            internal_chat_id, &participants)
+            .fetch_all(&mut *tx)
+            .await
+            .context(format!("couldn't lock the dicks of the participants of a transfer from {from} in {chat_id}"))?
+            .into_iter()
+            .map(|row| (row.uid, row.length))
+            .collect();
+        let current = lengths.get(&sender).copied().unwrap_or_default();
+        if current < total {
+            return Ok(Err(TransferRejection::NotEnough { current }))
+        }
+
+        if let Some(rejection) = Self::check_limits(&mut tx, internal_chat_id, sender, total, limits).await? {
+            return Ok(Err(rejection))
+        }
+        if check_recipients {
+            if let Some(uid) = Self::find_too_new_recipient(&mut tx, internal_chat_id, &recipients, limits).await? {
+                return Ok(Err(TransferRejection::RecipientTooNew(uid)))
+            }
+        }
+
+        let frozen = Self::find_frozen_recipients(&mut tx, internal_chat_id, sender, &recipients, limits).await?;
+
+        let sender_length = Self::change_length(&mut tx, internal_chat_id, sender, -total).await?;
+        let mut recipient_lengths = Vec::with_capacity(legs.len());
+        let (mut total_tax, mut held) = (0, 0);
+        for leg in legs {
+            let (recipient, amount) = (leg.to.0 as i64, leg.amount as i32);
+            let tax = calc_tax(amount, limits);
+            let (length, status) = if frozen.contains(&recipient) {
+                held += amount - tax;
+                (lengths.get(&recipient).copied().unwrap_or_default(), "frozen")
+            } else {
+                (Self::change_length(&mut tx, internal_chat_id, recipient, amount - tax).await?, "done")
+            };
+            Self::log_leg(&mut tx, internal_chat_id, sender, leg, tax, kind, status).await?;
+            recipient_lengths.push(length);
+            total_tax += tax;
+        }
+        tx.commit().await?;
+
+        Ok(Ok(TransferReceipt {
+            sender_length,
+            recipient_lengths,
+            tax: total_tax,
+            held,
+        }))
+    },
+    /// Checks the cooldown and the daily cap of the sender. The unclaimed centimeters of fire rains
+    /// count as given away until they are refunded.
+    pub(super) async fn check_limits(
+        tx: &mut Transaction<'_, Postgres>,
+        chat_id_internal: i64,
+        sender: i64,
+        total: i32,
+        limits: &config::GiftsConfig,
+    ) -> anyhow::Result<Option<TransferRejection>> {
+        if limits.cooldown_minutes > 0 {
+            let minutes_left = sqlx::query_scalar!(
+                "SELECT ceil(extract(epoch FROM greatest(
+                        (SELECT max(created_at) FROM transfers WHERE chat_id = $1 AND from_uid = $2),
+                        (SELECT max(created_at) FROM Fire_Rains WHERE chat_id = $1 AND uid = $2)
+                    ) + make_interval(mins => $3) - current_timestamp) / 60)::bigint",
+                chat_id_internal, sender, i32::try_from(limits.cooldown_minutes).unwrap_or(i32::MAX))
+                .fetch_one(&mut **tx)
+                .await
+                .context(format!("couldn't check the cooldown of transfers from {sender} in {chat_id_internal}"))?;
+            if let Some(minutes_left) = minutes_left.filter(|minutes| *minutes > 0) {
+                return Ok(Some(TransferRejection::Cooldown { minutes_left }))
+            }
+        }
+
+        if limits.daily_cap > 0 {
+            let given = sqlx::query_scalar!(
+                r#"SELECT (SELECT coalesce(sum(amount), 0) FROM transfers
+                            WHERE chat_id = $1 AND from_uid = $2 AND created_at > current_timestamp - interval '1 day')
+                      + (SELECT coalesce(sum(remaining), 0) FROM Fire_Rains
+                            WHERE chat_id = $1 AND uid = $2 AND finished_at IS NULL) AS "given!""#,
+                chat_id_internal, sender)
+                .fetch_one(&mut **tx)
+                .await
+                .context(format!("couldn't sum the transfers from {sender} in {chat_id_internal} for the last day"))?;
+            let left = (limits.daily_cap as i64 - given).max(0);
+            if total as i64 > left {
+                return Ok(Some(TransferRejection::DailyCap { left }))
+            }
+        }
+        Ok(None)
+    },
+    /// Returns one of the recipients who are too new to the bot or to the chat, if any.
+    pub(super) async fn find_too_new_recipient(
+        tx: &mut Transaction<'_, Postgres>,
+        chat_id_internal: i64,
+        recipients: &[i64],
+        limits: &config::GiftsConfig,
+    ) -> anyhow::Result<Option<UserId>> {
+        let (min_account_age, min_chat_tenure) = (limits.recipient_min_account_age_days, limits.recipient_min_chat_tenure_days);
+        if min_account_age == 0 && min_chat_tenure == 0 {
+            return Ok(None)
+        }
+        sqlx::query_scalar!(
+            "SELECT u.uid FROM Users u
+                JOIN Dicks d ON d.uid = u.uid AND d.chat_id = $1
+                WHERE u.uid = ANY($2) AND (
+                    u.created_at > current_timestamp - make_interval(days => $3)
+                    OR d.created_at > current_timestamp - make_interval(days => $4)
+                )
+                LIMIT 1",
+            chat_id_internal, recipients, min_account_age as i32, min_chat_tenure as i32)
+            .fetch_optional(&mut **tx)
+            .await
+            .map(|uid| uid.map(|uid| UserId(uid as u64)))
+            .context(format!("couldn't check the recipients {recipients:?} in {chat_id_internal}"))
+    },
+    /// Returns the recipients whose transfers from the sender must be frozen because of collusion flags.
+    pub(super) async fn find_frozen_recipients(
+        tx: &mut Transaction<'_, Postgres>,
+        chat_id_internal: i64,
+        sender: i64,
+        recipients: &[i64],
+        limits: &config::GiftsConfig,
+    ) -> anyhow::Result<HashSet<i64>> {
+        if !limits.freeze_flagged {
+            return Ok(HashSet::new())
+        }
+        sqlx::query_scalar!(
+            r#"SELECT r.uid AS "uid!" FROM unnest($3::bigint[]) AS r(uid)
+                WHERE EXISTS (SELECT 1 FROM Collusion_Flags f
+                    WHERE f.chat_id = $1 AND f.uid = $2 AND f.status <> 'dismissed' AND coalesce(f.counterpart_uid, r.uid) = r.uid)"#,
+            chat_id_internal, sender, recipients)
+            .fetch_all(&mut **tx)
+            .await
+            .map(|uids| uids.into_iter().collect())
+            .context(format!("couldn't check the collusion flags of transfers from {sender} in {chat_id_internal}"))
+    },
+    pub(super) async fn log_leg(
+        tx: &mut Transaction<'_, Postgres>,
+        chat_id_internal: i64,
+        sender: i64,
+        leg: &TransferLeg,
+        tax: i32,
+        kind: TransferKind,
+        status: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query!("INSERT INTO transfers(chat_id, from_uid, to_uid, amount, tax, kind, status) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            chat_id_internal, sender, leg.to.0 as i64, leg.amount as i32, tax, kind.as_str(), status)
+            .execute(&mut **tx)
+            .await
+            .context("couldn't insert transfer record")?;
         Ok(())
+    },
+    pub(super) async fn change_length(
+        tx: &mut Transaction<'_, Postgres>,
+        chat_id_internal: i64,
+        uid: i64,
+        change: i32,
+    ) -> anyhow::Result<i32> {
+        sqlx::query_scalar!(
+            "UPDATE Dicks SET length = (length + $3), bonus_attempts = (bonus_attempts + 1)
+                WHERE chat_id = $1 AND uid = $2 RETURNING length",
+            chat_id_internal, uid, change)
+            .fetch_one(&mut **tx)
+            .await
+            .context(format!("couldn't update the length by {change} for {chat_id_internal}, {uid}"))
     }
 );
+
+/// The part of a transfer which is burned instead of being given to the recipient.
+pub(super) fn calc_tax(amount: i32, limits: &config::GiftsConfig) -> i32 {
+    (amount as f64 * limits.tax_ratio).floor() as i32
+}
+
+impl TransferKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            TransferKind::Gift => "gift",
+            TransferKind::Fire => "fire",
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use anyhow::Context;
 use chrono::{DateTime, Utc};
-use teloxide::types::UserId;
+use sqlx::{Postgres, Transaction};
+use std::collections::HashSet;
+use teloxide::types::{ChatId, UserId};
 
 use crate::domain::{Ratio, Username};
-use crate::repo::ChatIdKind;
+use crate::repo::{ChatIdKind, UID};
 use crate::repository;
 
 #[derive(sqlx::FromRow, Debug)]
@@ -15,24 +17,49 @@ pub struct User {
 
 repository!(
     Users,
-    pub async fn create_or_update(&self, user_id: UserId, name: &str) -> anyhow::Result<User> {
-        let uid = user_id.0 as i64;
-        sqlx::query_as!(
+    pub async fn create_or_update(&self, user_id: UserId, name: &str, username: Option<&str>) -> anyhow::Result<User> {
+        let mut tx = self.pool.begin().await?;
+        Self::release_username(&mut tx, user_id, username).await?;
+        let user = sqlx::query_as!(
             User,
-            "INSERT INTO Users(uid, name) VALUES ($1, $2)
-                ON CONFLICT (uid) DO UPDATE SET name = $2
+            "INSERT INTO Users(uid, name, username) VALUES ($1, $2, $3)
+                ON CONFLICT (uid) DO UPDATE SET name = $2, username = $3
                 RETURNING uid, name, created_at",
-            uid,
-            name
-        )
-        .fetch_one(&self.pool)
-        .await
-        .context(format!("couldn't upsert a user with id = {user_id}"))
+            user_id.0 as i64, name, username)
+            .fetch_one(&mut *tx)
+            .await
+            .context(format!("couldn't upsert a user with id = {user_id}"))?;
+        tx.commit().await?;
+        Ok(user)
+    },
+    /// Updates the @username of a known user. Nothing is written if the username is the same.
+    pub async fn update_username(&self, user_id: UserId, username: Option<&str>) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        Self::release_username(&mut tx, user_id, username).await?;
+        sqlx::query!("UPDATE Users SET username = $2 WHERE uid = $1 AND username IS DISTINCT FROM $2",
+            user_id.0 as i64, username)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't update the username of the user {user_id}"))?;
+        tx.commit().await?;
+        Ok(())
+    },
+    /// Usernames may be changed and taken by someone else, so the one who had the username before loses it.
+    async fn release_username(tx: &mut Transaction<'_, Postgres>, user_id: UserId, username: Option<&str>) -> anyhow::Result<()> {
+        let Some(username) = username else {
+            return Ok(())
+        };
+        sqlx::query!("UPDATE Users SET username = NULL WHERE lower(username) = lower($2) AND uid <> $1",
+            user_id.0 as i64, username)
+            .execute(&mut **tx)
+            .await
+            .context(format!("couldn't release the username {username} for the user {user_id}"))?;
+        Ok(())
     },
     pub async fn get_chat_members(&self, chat_id: &ChatIdKind) -> anyhow::Result<Vec<User>> {
         sqlx::query_as!(
             User,
-            "SELECT u.uid, name, created_at FROM Users u
+            "SELECT u.uid, u.name, u.created_at FROM Users u
                 JOIN Dicks d USING (uid)
                 JOIN Chats c ON d.chat_id = c.id
                 WHERE c.chat_id = $1::bigint OR c.chat_instance = $1::text",
@@ -55,6 +82,8 @@ repository!(
                 JOIN Chats c ON d.chat_id = c.id
                 WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text)
                     AND updated_at > current_timestamp - interval '1 week'
+                    AND NOT EXISTS (SELECT 1 FROM Chat_Bans b WHERE b.chat_id = d.chat_id AND b.uid = d.uid)
+                    AND NOT EXISTS (SELECT 1 FROM Chat_Members m WHERE m.chat_id = d.chat_id AND m.uid = d.uid AND m.left_at IS NOT NULL)
                 ORDER BY random() LIMIT 1",
             chat_id.value() as String
         )
@@ -77,6 +106,8 @@ repository!(
                     JOIN Chats c ON d.chat_id = c.id
                     WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text)
                         AND updated_at > current_timestamp - interval '1 week'
+                        AND NOT EXISTS (SELECT 1 FROM Chat_Bans b WHERE b.chat_id = d.chat_id AND b.uid = d.uid)
+                        AND NOT EXISTS (SELECT 1 FROM Chat_Members m WHERE m.chat_id = d.chat_id AND m.uid = d.uid AND m.left_at IS NOT NULL)
             )
             SELECT uid, name, created_at
             FROM ranked_users
@@ -100,6 +131,8 @@ repository!(
                   JOIN Chats c ON d.chat_id = c.id
                 WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text)
                   AND d.updated_at > current_timestamp - interval '1 week'
+                  AND NOT EXISTS (SELECT 1 FROM Chat_Bans b WHERE b.chat_id = d.chat_id AND b.uid = d.uid)
+                  AND NOT EXISTS (SELECT 1 FROM Chat_Members m WHERE m.chat_id = d.chat_id AND m.uid = d.uid AND m.left_at IS NOT NULL)
             ),
                  cumulative_weights AS (
                      SELECT uid, name, created_at, weight,
@@ -130,6 +163,104 @@ repository!(
         .await
         .context(format!("couldn't get a user with id = {user_id}"))
     },
+    /// Returns those of the users who have been registered within the last `grace_days` days.
+    pub async fn filter_newcomers(&self, uids: &[UID], grace_days: u32) -> anyhow::Result<HashSet<UID>> {
+        sqlx::query_scalar!(
+            "SELECT uid FROM Users
+                WHERE uid = ANY($1) AND created_at > current_timestamp - make_interval(days => $2)",
+            &uids.iter().map(|uid| uid.0).collect::<Vec<i64>>(), i32::try_from(grace_days).unwrap_or(i32::MAX))
+            .fetch_all(&self.pool)
+            .await
+            .map(|uids| uids.into_iter().map(UID::from).collect())
+            .context(format!("couldn't filter the newcomers among {} users", uids.len()))
+    },
+    /// Marks the user as the one who left the chat, or as a member again when they rejoin.
+    /// Users and chats unknown to the bot are ignored. Returns true if the membership has been recorded.
+    pub async fn set_membership(&self, chat_id: ChatId, user_id: UserId, present: bool) -> anyhow::Result<bool> {
+        sqlx::query!(
+            "INSERT INTO Chat_Members (chat_id, uid, left_at)
+                SELECT c.id, u.uid, CASE WHEN $3 THEN NULL ELSE current_timestamp END
+                    FROM Chats c, Users u WHERE c.chat_id = $1 AND u.uid = $2
+                ON CONFLICT (chat_id, uid) DO UPDATE SET left_at = EXCLUDED.left_at, updated_at = current_timestamp",
+            chat_id.0, user_id.0 as i64, present)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't set the membership of the user {user_id} in the chat {chat_id} to {present}"))
+    },
+    /// Deletes the progress of the user in one chat only. Returns the count of deleted dicks.
+    pub async fn delete_from_chat(&self, user_id: UserId, chat_id: &ChatIdKind) -> anyhow::Result<u64> {
+        let uid = user_id.0 as i64;
+        let maybe_internal_id = sqlx::query_scalar!(
+            "SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text",
+            chat_id.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't get the internal id of the chat {chat_id}"))?;
+        let internal_id = match maybe_internal_id {
+            Some(id) => id,
+            None => return Ok(0),
+        };
+        let mut tx = self.pool.begin().await?;
+
+        // Announcements are counted per chat and language, they have no references to users
+        sqlx::query!("DELETE FROM transfers WHERE chat_id = $1 AND (from_uid = $2 OR to_uid = $2)",
+            internal_id, uid)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete transfers of the user {user_id} in the chat {chat_id}"))?;
+
+        sqlx::query!("DELETE FROM Loans WHERE chat_id = $1 AND uid = $2",
+            internal_id, uid)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete loans of the user {user_id} in the chat {chat_id}"))?;
+
+        sqlx::query!("DELETE FROM Dick_of_Day WHERE chat_id = $1 AND winner_uid = $2",
+            internal_id, uid)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete Dick_of_Day records of the user {user_id} in the chat {chat_id}"))?;
+
+        sqlx::query!("DELETE FROM Battle_Stats WHERE chat_id = $1 AND uid = $2",
+            internal_id, uid)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete battle stats of the user {user_id} in the chat {chat_id}"))?;
+
+        sqlx::query!("DELETE FROM Battle_Log WHERE chat_id = $1 AND (winner_uid = $2 OR loser_uid = $2)",
+            internal_id, uid)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete battles of the user {user_id} in the chat {chat_id}"))?;
+
+        sqlx::query!("DELETE FROM Collusion_Flags WHERE chat_id = $1 AND (uid = $2 OR counterpart_uid = $2)",
+            internal_id, uid)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete collusion flags of the user {user_id} in the chat {chat_id}"))?;
+
+        sqlx::query!("DELETE FROM Fire_Rain_Claims WHERE uid = $2 AND rain_id IN (SELECT id FROM Fire_Rains WHERE chat_id = $1)",
+            internal_id, uid)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete fire rain claims of the user {user_id} in the chat {chat_id}"))?;
+
+        sqlx::query!("DELETE FROM Fire_Rains WHERE chat_id = $1 AND uid = $2",
+            internal_id, uid)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete fire rains of the user {user_id} in the chat {chat_id}"))?;
+
+        let res = sqlx::query!("DELETE FROM Dicks WHERE chat_id = $1 AND uid = $2",
+            internal_id, uid)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't delete the dick of the user {user_id} in the chat {chat_id}"))?;
+
+        tx.commit().await?;
+        Ok(res.rows_affected())
+    },
     pub async fn delete_everything(&self, user_id: UserId) -> anyhow::Result<u64> {
         let mut tx = self.pool.begin().await?;
 
@@ -158,6 +289,30 @@ repository!(
             .await
             .context("couldn't delete battle stats for user")?;
 
+        sqlx::query!("DELETE FROM Battle_Log WHERE winner_uid = $1 OR loser_uid = $1",
+            user_id.0 as i64)
+            .execute(&mut *tx)
+            .await
+            .context("couldn't delete battles for user")?;
+
+        sqlx::query!("DELETE FROM Collusion_Flags WHERE uid = $1 OR counterpart_uid = $1",
+            user_id.0 as i64)
+            .execute(&mut *tx)
+            .await
+            .context("couldn't delete collusion flags for user")?;
+
+        sqlx::query!("DELETE FROM Fire_Rain_Claims WHERE uid = $1",
+            user_id.0 as i64)
+            .execute(&mut *tx)
+            .await
+            .context("couldn't delete fire rain claims for user")?;
+
+        sqlx::query!("DELETE FROM Fire_Rains WHERE uid = $1",
+            user_id.0 as i64)
+            .execute(&mut *tx)
+            .await
+            .context("couldn't delete fire rains for user")?;
+
         sqlx::query!("DELETE FROM Imports WHERE uid = $1", user_id.0 as i64)
             .execute(&mut *tx)
             .await
@@ -176,19 +331,20 @@ repository!(
         tx.commit().await?;
         Ok(res.rows_affected())
     },
-    // pub async fn get_by_username(&self, username: &Username) -> anyhow::Result<Option<User>> {
-    //     sqlx::query_as!(
-    //         User,
-    //         "SELECT uid, name, created_at FROM Users WHERE name = $1",
-    //         username.value_ref() as String
-    //     )
-    //     .fetch_optional(&self.pool)
-    //     .await
-    //     .context(format!("couldn't get a user with username = {username}"))
-    // },
+    /// Looks up a user by the @username, the at sign is optional. The search is case-insensitive.
+    pub async fn get_by_username(&self, username: &str) -> anyhow::Result<Option<User>> {
+        let username = username.strip_prefix('@').unwrap_or(username);
+        sqlx::query_as!(
+            User,
+            "SELECT uid, name, created_at FROM Users WHERE lower(username) = lower($1)",
+            username)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't get a user with username = {username}"))
+    },
     #[cfg(test)]
     pub async fn get_all(&self) -> anyhow::Result<Vec<User>> {
-        sqlx::query_as!(User, "SELECT * FROM Users")
+        sqlx::query_as!(User, "SELECT uid, name, created_at FROM Users")
             .fetch_all(&self.pool)
             .await
             .map_err(Into::into)